@@ -0,0 +1,22 @@
+#![no_main]
+
+use http_server::httpserver::{parse_request, RequestParser};
+use libfuzzer_sys::fuzz_target;
+
+const MAX_BODY_SIZE: usize = 64 * 1024;
+
+// Random header lines after a valid request line; they decide the body framing.
+fuzz_target!(|data: &[u8]| {
+    let mut raw = b"POST / HTTP/1.1\r\nHost: fuzz\r\n".to_vec();
+    raw.extend_from_slice(data);
+
+    // the same result, whether the data arrives at once or in two parts:
+    let whole = parse_request(&raw, MAX_BODY_SIZE);
+    let mut parser = RequestParser::new(MAX_BODY_SIZE);
+    let split = data.first().map(|b| *b as usize % (raw.len() + 1)).unwrap_or_default();
+    let pieces = match parser.parse(&raw[..split]) {
+        Ok(None) => parser.parse(&raw),
+        result => result,
+    };
+    assert_eq!(format!("{:?}", whole), format!("{:?}", pieces));
+});
@@ -0,0 +1,16 @@
+mod client;
+mod error;
+mod response;
+mod url;
+
+pub use client::{ClientRequest, HttpClient};
+pub use error::ClientError;
+pub use response::ClientResponse;
+pub use url::Url;
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod client_test;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod url_test;
@@ -0,0 +1,329 @@
+use std::collections::HashMap;
+use std::io::{BufReader, ErrorKind, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::httpclient::response::read_response;
+use crate::httpclient::{ClientError, ClientResponse, Url};
+use crate::httpserver::{HeaderMap, HttpVerb};
+
+type Connection = BufReader<TcpStream>;
+
+/// A blocking HTTP/1.1 client for `http://` URLs. Connections are kept open
+/// after a response (unless the server closes them), and reused for the next
+/// request to the same host and port. Redirects are followed.
+///
+/// ```ignore
+/// let client = HttpClient::new().timeout(Duration::from_secs(5));
+/// let response = client.post("http://localhost:3000/users")
+///     .header("Content-Type", "application/json")
+///     .body(r#"{"name": "alex"}"#)
+///     .send()?;
+/// assert_eq!(response.status.code(), 201);
+/// ```
+///
+/// The client can be shared between threads; each request uses a connection
+/// of its own.
+pub struct HttpClient {
+    timeout: Duration,
+    max_redirects: usize,
+    max_body_size: usize,
+    max_idle_per_host: usize,
+    idle: Mutex<HashMap<String, Vec<Connection>>>,
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        HttpClient::new()
+    }
+}
+
+impl HttpClient {
+    pub fn new() -> HttpClient {
+        HttpClient {
+            timeout: Duration::from_secs(30),
+            max_redirects: 10,
+            max_body_size: 64 * 1024 * 1024,
+            max_idle_per_host: 4,
+            idle: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Limits connecting, and each read and write on the connection (not the
+    /// whole request). 30 seconds by default.
+    pub fn timeout(mut self, timeout: Duration) -> HttpClient {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Number of redirects followed, before the request fails with
+    /// [ClientError::TooManyRedirects]; 0 returns the redirect response itself.
+    /// 10 by default.
+    pub fn max_redirects(mut self, max_redirects: usize) -> HttpClient {
+        self.max_redirects = max_redirects;
+        self
+    }
+
+    /// Larger response bodies fail with [ClientError::InvalidResponse]. 64 MiB by default.
+    pub fn max_body_size(mut self, bytes: usize) -> HttpClient {
+        self.max_body_size = bytes;
+        self
+    }
+
+    /// Number of open connections kept per host and port; 0 disables the reuse.
+    /// 4 by default.
+    pub fn max_idle_per_host(mut self, connections: usize) -> HttpClient {
+        self.max_idle_per_host = connections;
+        self
+    }
+
+    pub fn request(&self, method: HttpVerb, url: &str) -> ClientRequest<'_> {
+        ClientRequest {
+            client: self,
+            method,
+            url: String::from(url),
+            headers: HeaderMap::new(),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn get(&self, url: &str) -> ClientRequest<'_> {
+        self.request(HttpVerb::GET, url)
+    }
+
+    pub fn head(&self, url: &str) -> ClientRequest<'_> {
+        self.request(HttpVerb::HEAD, url)
+    }
+
+    pub fn post(&self, url: &str) -> ClientRequest<'_> {
+        self.request(HttpVerb::POST, url)
+    }
+
+    pub fn put(&self, url: &str) -> ClientRequest<'_> {
+        self.request(HttpVerb::PUT, url)
+    }
+
+    pub fn patch(&self, url: &str) -> ClientRequest<'_> {
+        self.request(HttpVerb::PATCH, url)
+    }
+
+    pub fn delete(&self, url: &str) -> ClientRequest<'_> {
+        self.request(HttpVerb::DELETE, url)
+    }
+
+    /// Number of idle connections kept open for reuse.
+    pub fn idle_connections(&self) -> usize {
+        self.idle.lock().unwrap().values().map(|c| c.len()).sum()
+    }
+
+    /// Sends a single request, without following redirects. An idle connection
+    /// may have been closed by the server in the meantime: if it is closed
+    /// before any response byte, an idempotent request is repeated on a new
+    /// connection. Other requests (e.g. POST) fail, as the server may have
+    /// processed them already (RFC 9110, 9.2.2).
+    fn execute(
+        &self,
+        method: &HttpVerb,
+        url: &Url,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<ClientResponse, ClientError> {
+        let request = request_bytes(method, url, headers, body);
+        if let Some(connection) = self.take_idle(&url.addr()) {
+            let result = self.exchange(connection, &request, method, url);
+            let closed = match &result {
+                Ok(response) => response.is_none(),
+                Err(ClientError::Io(e)) => matches!(
+                    e.kind(),
+                    ErrorKind::BrokenPipe
+                        | ErrorKind::ConnectionReset
+                        | ErrorKind::ConnectionAborted
+                ),
+                Err(_) => false,
+            };
+            if !closed || !is_idempotent(method) {
+                return result?.ok_or_else(closed_without_response);
+            }
+        }
+        let connection = self.connect(url)?;
+        self.exchange(connection, &request, method, url)?
+            .ok_or_else(closed_without_response)
+    }
+
+    fn exchange(
+        &self,
+        mut connection: Connection,
+        request: &[u8],
+        method: &HttpVerb,
+        url: &Url,
+    ) -> Result<Option<ClientResponse>, ClientError> {
+        let stream = connection.get_mut();
+        stream.write_all(request)?;
+        stream.flush()?;
+        match read_response(&mut connection, method, url, self.max_body_size)? {
+            Some((response, reusable)) => {
+                // bytes after the response would be read as the next response:
+                if reusable && connection.buffer().is_empty() {
+                    self.put_idle(url.addr(), connection);
+                }
+                Ok(Some(response))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn connect(&self, url: &Url) -> Result<Connection, ClientError> {
+        let addrs = url
+            .addr()
+            .to_socket_addrs()
+            .map_err(|_| ClientError::InvalidUrl(url.to_string()))?;
+        let mut last_error = None;
+        for addr in addrs {
+            match TcpStream::connect_timeout(&addr, self.timeout) {
+                Ok(stream) => {
+                    stream.set_read_timeout(Some(self.timeout))?;
+                    stream.set_write_timeout(Some(self.timeout))?;
+                    stream.set_nodelay(true)?;
+                    return Ok(BufReader::new(stream));
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(match last_error {
+            Some(e) => ClientError::from(e),
+            None => ClientError::InvalidUrl(url.to_string()),
+        })
+    }
+
+    fn take_idle(&self, addr: &str) -> Option<Connection> {
+        self.idle.lock().unwrap().get_mut(addr)?.pop()
+    }
+
+    fn put_idle(&self, addr: String, connection: Connection) {
+        if self.max_idle_per_host == 0 {
+            return;
+        }
+        let mut idle = self.idle.lock().unwrap();
+        let connections = idle.entry(addr).or_default();
+        if connections.len() >= self.max_idle_per_host {
+            connections.remove(0);
+        }
+        connections.push(connection);
+    }
+}
+
+/// A request of an [HttpClient], sent by [ClientRequest::send]. The `Host`
+/// header, and the `Content-Length` of a body, are added if not set.
+pub struct ClientRequest<'a> {
+    client: &'a HttpClient,
+    method: HttpVerb,
+    url: String,
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+impl ClientRequest<'_> {
+    /// Adds a header; a repeated header is combined into a list.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.append(name, value);
+        self
+    }
+
+    pub fn body<T: Into<Vec<u8>>>(mut self, body: T) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Sends the value serialized as JSON body, with the JSON Content-Type.
+    #[cfg(feature = "serde")]
+    pub fn json<T: serde::Serialize>(mut self, value: &T) -> serde_json::Result<Self> {
+        self.body = serde_json::to_vec(value)?;
+        self.headers.set("Content-Type", "application/json");
+        Ok(self)
+    }
+
+    /// Sends the request and reads the response, following redirects: a
+    /// 303 (and a 301 / 302 to a POST) is followed with a GET without body,
+    /// 307 and 308 repeat the request. `Authorization` and `Cookie` are not
+    /// sent to other hosts.
+    pub fn send(self) -> Result<ClientResponse, ClientError> {
+        let client = self.client;
+        let mut method = self.method;
+        let mut url = Url::parse(&self.url)?;
+        let mut headers = self.headers;
+        let mut body = self.body;
+        let mut redirects = 0;
+        loop {
+            let response = client.execute(&method, &url, &headers, &body)?;
+            let location = match response.headers.get("location") {
+                Some(location) if client.max_redirects > 0 => location,
+                _ => return Ok(response),
+            };
+            let code = response.status.code();
+            let method_to_get = match code {
+                301 | 302 => method == HttpVerb::POST,
+                303 => method != HttpVerb::HEAD,
+                307 | 308 => false,
+                _ => return Ok(response),
+            };
+            if redirects == client.max_redirects {
+                return Err(ClientError::TooManyRedirects);
+            }
+            redirects += 1;
+
+            if method_to_get {
+                method = HttpVerb::GET;
+                body.clear();
+                for header in ["content-length", "content-type", "transfer-encoding"] {
+                    headers.remove(header);
+                }
+            }
+            let next = url.join(&location)?;
+            if next.addr() != url.addr() {
+                headers.remove("authorization");
+                headers.remove("cookie");
+                headers.remove("host");
+            }
+            url = next;
+        }
+    }
+}
+
+/// Whether repeating the request has the same effect as sending it once (RFC 9110, 9.2.2).
+fn is_idempotent(method: &HttpVerb) -> bool {
+    matches!(
+        method,
+        HttpVerb::GET
+            | HttpVerb::HEAD
+            | HttpVerb::PUT
+            | HttpVerb::DELETE
+            | HttpVerb::OPTIONS
+            | HttpVerb::TRACE
+    )
+}
+
+fn closed_without_response() -> ClientError {
+    ClientError::InvalidResponse(String::from("connection closed without response"))
+}
+
+fn request_bytes(method: &HttpVerb, url: &Url, headers: &HeaderMap, body: &[u8]) -> Vec<u8> {
+    let mut head = format!("{} {} HTTP/1.1\r\n", method, url.target);
+    if !headers.contains("host") {
+        head += &format!("Host: {}\r\n", url.authority());
+    }
+    for (name, value) in headers.iter() {
+        head += &format!("{}: {}\r\n", name, value);
+    }
+    let expects_body = matches!(method, HttpVerb::POST | HttpVerb::PUT | HttpVerb::PATCH);
+    if (expects_body || !body.is_empty())
+        && !headers.contains("content-length")
+        && !headers.contains("transfer-encoding")
+    {
+        head += &format!("Content-Length: {}\r\n", body.len());
+    }
+    head += "\r\n";
+    let mut bytes = head.into_bytes();
+    bytes.extend_from_slice(body);
+    bytes
+}
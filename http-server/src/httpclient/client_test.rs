@@ -0,0 +1,231 @@
+#[cfg(test)]
+mod client_test {
+    use super::super::{ClientError, HttpClient};
+    use crate::httpserver::{HTTPStatusCode, HttpServer, HttpVerb, Request, Response};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{SocketAddr, TcpListener};
+    use std::thread::{self, JoinHandle};
+    use std::time::Duration;
+
+    /// A server answering each request with the next scripted response: one
+    /// list of responses per connection. Returns the requests received on
+    /// each connection.
+    fn scripted_server(
+        connections: Vec<Vec<&'static str>>,
+    ) -> (SocketAddr, JoinHandle<Vec<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let mut received = Vec::new();
+            for responses in connections {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut requests = Vec::new();
+                for response in responses {
+                    let mut request = String::new();
+                    let mut content_length = 0;
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        if let Some(length) = line.to_lowercase().strip_prefix("content-length:") {
+                            content_length = length.trim().parse().unwrap();
+                        }
+                        request += &line;
+                        if line == "\r\n" {
+                            break;
+                        }
+                    }
+                    let mut body = vec![0u8; content_length];
+                    reader.read_exact(&mut body).unwrap();
+                    request += &String::from_utf8(body).unwrap();
+                    requests.push(request);
+                    reader.get_mut().write_all(response.as_bytes()).unwrap();
+                }
+                received.push(requests);
+            }
+            received
+        });
+        (addr, server)
+    }
+
+    #[test]
+    fn test_requests_to_server() {
+        let mut server = HttpServer::new("127.0.0.1:0");
+        server.route(HttpVerb::POST, "/echo", |request: &Request| {
+            Response::text(
+                HTTPStatusCode::Success(201),
+                &format!(
+                    "{} {}",
+                    request.headers.get("x-name").unwrap_or_default(),
+                    request.body.as_deref().unwrap_or_default()
+                ),
+            )
+        });
+        server.route(HttpVerb::POST, "/old", |_| {
+            Response::new(HTTPStatusCode::Redirect(303)).with_header("Location", "/new?page=2")
+        });
+        server.route(HttpVerb::GET, "/new", |request: &Request| {
+            Response::text(
+                HTTPStatusCode::Success(200),
+                &format!("{} {:?}", request.full_url, request.body),
+            )
+        });
+        let server = server.spawn_ephemeral().unwrap();
+        let client = HttpClient::new();
+
+        let response = client
+            .post(&server.url("/echo"))
+            .header("X-Name", "client")
+            .body("hello")
+            .send()
+            .unwrap();
+        assert_eq!(response.status.code(), 201);
+        assert_eq!(response.version, "HTTP/1.1");
+        assert_eq!(response.text(), "client hello");
+        assert_eq!(
+            response.headers.get("content-type").unwrap(),
+            "text/plain; charset=utf-8"
+        );
+
+        // 303 is followed with a GET without body:
+        let response = client
+            .post(&server.url("/old"))
+            .body("data")
+            .send()
+            .unwrap();
+        assert_eq!(response.status.code(), 200);
+        assert_eq!(response.text(), "/new?page=2 None");
+        assert_eq!(response.url.to_string(), server.url("/new?page=2"));
+
+        let response = client.get(&server.url("/missing")).send().unwrap();
+        assert_eq!(response.status.code(), 404);
+        // the server closes the connections:
+        assert_eq!(client.idle_connections(), 0);
+    }
+
+    #[test]
+    fn test_keep_alive_and_chunked() {
+        let (addr, server) = scripted_server(vec![vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nfirst",
+            "HTTP/1.1 100 Continue\r\n\r\n\
+             HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+             6;ext=1\r\nsecond\r\n3\r\n!!!\r\n0\r\nTrailer: x\r\n\r\n",
+            "HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n",
+        ]]);
+        let client = HttpClient::new();
+        let url = format!("http://{}/path", addr);
+
+        assert_eq!(client.get(&url).send().unwrap().text(), "first");
+        assert_eq!(client.idle_connections(), 1);
+        assert_eq!(client.put(&url).send().unwrap().text(), "second!!!");
+        let response = client.delete(&url).send().unwrap();
+        assert_eq!(response.status.code(), 204);
+        assert_eq!(client.idle_connections(), 0);
+
+        // all on one connection:
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0][0],
+            format!("GET /path HTTP/1.1\r\nHost: {}\r\n\r\n", addr)
+        );
+        assert!(requests[0][1].starts_with("PUT /path HTTP/1.1\r\n"));
+        assert!(requests[0][1].contains("Content-Length: 0\r\n"));
+    }
+
+    #[test]
+    fn test_closed_idle_connection() {
+        // the first connection is closed by the server after the first response:
+        let (addr, server) = scripted_server(vec![
+            vec!["HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\none"],
+            vec!["HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\ntwo"],
+        ]);
+        let client = HttpClient::new();
+        let url = format!("http://{}/", addr);
+        assert_eq!(client.get(&url).send().unwrap().text(), "one");
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(client.get(&url).send().unwrap().text(), "two");
+        assert_eq!(server.join().unwrap().len(), 2);
+
+        // a POST is not repeated, the server may have processed it:
+        let (addr, server) = scripted_server(vec![
+            vec!["HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\none"],
+            vec!["HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\ntwo"],
+        ]);
+        let url = format!("http://{}/", addr);
+        assert_eq!(client.get(&url).send().unwrap().text(), "one");
+        thread::sleep(Duration::from_millis(50));
+        assert!(client.post(&url).body("data").send().is_err());
+        assert_eq!(client.get(&url).send().unwrap().text(), "two");
+        let requests = server.join().unwrap();
+        assert!(requests[1][0].starts_with("GET / HTTP/1.1\r\n"));
+    }
+
+    #[test]
+    fn test_redirects() {
+        let (addr, server) = scripted_server(vec![vec![
+            "HTTP/1.1 307 Temporary Redirect\r\nLocation: b\r\nContent-Length: 0\r\n\r\n",
+            "HTTP/1.1 302 Found\r\nLocation: /c\r\nContent-Length: 0\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok",
+        ]]);
+        let client = HttpClient::new();
+        let response = client
+            .post(&format!("http://{}/dir/a", addr))
+            .header("Authorization", "Bearer x")
+            .body("data")
+            .send()
+            .unwrap();
+        assert_eq!(response.text(), "ok");
+        let requests = &server.join().unwrap()[0];
+        // 307 repeats the request, 302 after a POST is a GET:
+        assert!(requests[1].starts_with("POST /dir/b HTTP/1.1\r\n"));
+        assert!(requests[1].ends_with("\r\n\r\ndata"));
+        assert!(requests[2].starts_with("GET /c HTTP/1.1\r\n"));
+        assert!(requests[2].contains("Authorization: Bearer x\r\n"));
+        assert!(!requests[2].contains("Content-Length"));
+
+        let (addr, _server) = scripted_server(vec![vec![
+            "HTTP/1.1 301 Moved\r\nLocation: /a\r\nContent-Length: 0\r\n\r\n",
+            "HTTP/1.1 301 Moved\r\nLocation: /b\r\nContent-Length: 0\r\n\r\n",
+        ]]);
+        let client = HttpClient::new().max_redirects(1);
+        let result = client.get(&format!("http://{}/", addr)).send();
+        assert!(matches!(result, Err(ClientError::TooManyRedirects)));
+    }
+
+    #[test]
+    fn test_invalid_responses() {
+        let (addr, _server) = scripted_server(vec![
+            vec!["HTTP/1.1 200 OK\r\nContent-Length: 5, 6\r\n\r\nabcde"],
+            vec!["HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\n"],
+            vec!["HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\n"],
+            vec!["SSH-2.0-OpenSSH\r\n\r\n"],
+            // not delimited, read until closed:
+            vec!["HTTP/1.0 200 OK\r\n\r\nuntil close"],
+        ]);
+        let client = HttpClient::new().max_body_size(10);
+        let url = format!("http://{}/", addr);
+        for _ in 0..4 {
+            assert!(matches!(
+                client.get(&url).send(),
+                Err(ClientError::InvalidResponse(_))
+            ));
+        }
+        let response = HttpClient::new().get(&url).send().unwrap();
+        assert_eq!(response.text(), "until close");
+        assert_eq!(response.version, "HTTP/1.0");
+    }
+
+    #[test]
+    fn test_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let _server = thread::spawn(move || {
+            let (_stream, _) = listener.accept().unwrap();
+            thread::sleep(Duration::from_millis(500));
+        });
+        let client = HttpClient::new().timeout(Duration::from_millis(100));
+        let result = client.get(&format!("http://{}/", addr)).send();
+        assert!(matches!(result, Err(ClientError::Timeout)));
+    }
+}
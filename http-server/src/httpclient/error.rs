@@ -0,0 +1,43 @@
+use std::fmt::{Display, Formatter};
+use std::io::{self, ErrorKind};
+
+/// The ways a request of the [HttpClient](crate::httpclient::HttpClient) can fail.
+/// A response with an error status (4xx, 5xx) is not an error.
+#[derive(Debug)]
+pub enum ClientError {
+    InvalidUrl(String),
+    /// only `http://` URLs are supported
+    UnsupportedScheme(String),
+    /// connecting, reading or writing took longer than the timeout
+    Timeout,
+    /// the response is not valid HTTP/1.x, or exceeds the size limits
+    InvalidResponse(String),
+    TooManyRedirects,
+    Io(io::Error),
+}
+
+impl Display for ClientError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClientError::InvalidUrl(url) => write!(f, "invalid URL: {}", url),
+            ClientError::UnsupportedScheme(url) => write!(f, "unsupported URL scheme: {}", url),
+            ClientError::Timeout => write!(f, "request timed out"),
+            ClientError::InvalidResponse(msg) => write!(f, "invalid response: {}", msg),
+            ClientError::TooManyRedirects => write!(f, "too many redirects"),
+            ClientError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<io::Error> for ClientError {
+    fn from(e: io::Error) -> ClientError {
+        match e.kind() {
+            // a socket read timeout is reported as WouldBlock on Unix:
+            ErrorKind::TimedOut | ErrorKind::WouldBlock => ClientError::Timeout,
+            ErrorKind::InvalidData => ClientError::InvalidResponse(e.to_string()),
+            _ => ClientError::Io(e),
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use std::io::{BufRead, Read};
+
+use crate::httpclient::{ClientError, Url};
+use crate::httpserver::{HTTPStatusCode, HeaderMap, HttpVerb, MAX_HEAD_SIZE, MAX_LINE_LENGTH};
+use crate::utils::BufReaderExt;
+
+/// A response received by the [HttpClient](crate::httpclient::HttpClient),
+/// with the whole body read (and de-chunked).
+#[derive(Debug)]
+pub struct ClientResponse {
+    pub status: HTTPStatusCode,
+    /// protocol version of the status line, e.g. `HTTP/1.1`
+    pub version: String,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+    /// the URL the response is from, after following redirects
+    pub url: Url,
+}
+
+impl ClientResponse {
+    /// The body as (lossy) utf-8 text.
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    /// Deserializes the JSON body.
+    #[cfg(feature = "serde")]
+    pub fn json<T: serde::de::DeserializeOwned>(&self) -> serde_json::Result<T> {
+        serde_json::from_slice(&self.body)
+    }
+
+    /// Whether the server keeps the connection open after this response: the
+    /// default for HTTP/1.1, opt-in (`Connection: keep-alive`) for HTTP/1.0.
+    fn keep_alive(&self) -> bool {
+        let connection = self
+            .headers
+            .get("connection")
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let has = |option: &str| connection.split(',').any(|o| o.trim() == option);
+        match self.version.as_str() {
+            "HTTP/1.1" => !has("close"),
+            _ => has("keep-alive"),
+        }
+    }
+}
+
+/// Reads a response to a request with the given method from the connection.
+/// Interim (1xx) responses are skipped. Returns None if the connection was
+/// closed before the first byte, else the response, and whether the
+/// connection can be used for another request.
+pub(crate) fn read_response<R: BufRead>(
+    reader: &mut R,
+    method: &HttpVerb,
+    url: &Url,
+    max_body_size: usize,
+) -> Result<Option<(ClientResponse, bool)>, ClientError> {
+    let mut response = loop {
+        let status_line = reader.read_max_until(b'\n', MAX_LINE_LENGTH)?;
+        if status_line.is_empty() {
+            return Ok(None);
+        }
+        let (version, status) = parse_status_line(&status_line)?;
+        let headers = read_headers(reader)?;
+        // 101 Switching Protocols ends the response, the client does not upgrade:
+        if !matches!(status, HTTPStatusCode::Info(_)) || status.code() == 101 {
+            break ClientResponse {
+                status,
+                version,
+                headers,
+                body: Vec::new(),
+                url: url.clone(),
+            };
+        }
+    };
+
+    let mut reusable = response.keep_alive();
+    let code = response.status.code();
+    if *method == HttpVerb::HEAD || code < 200 || code == 204 || code == 304 {
+        return Ok(Some((response, reusable && code != 101)));
+    }
+
+    let transfer_encoding = response.headers.get("transfer-encoding");
+    response.body = match transfer_encoding {
+        Some(codings) => {
+            let chunked = codings
+                .rsplit(',')
+                .next()
+                .is_some_and(|c| c.trim().eq_ignore_ascii_case("chunked"));
+            if chunked {
+                read_chunked(reader, max_body_size)?
+            } else {
+                // not delimited: the body ends with the connection
+                reusable = false;
+                read_until_close(reader, max_body_size)?
+            }
+        }
+        None => match content_length(&response.headers)? {
+            Some(length) if length > max_body_size => {
+                return Err(body_too_large(max_body_size));
+            }
+            Some(length) => {
+                let mut body = vec![0u8; length];
+                reader.read_exact(&mut body)?;
+                body
+            }
+            None => {
+                reusable = false;
+                read_until_close(reader, max_body_size)?
+            }
+        },
+    };
+    Ok(Some((response, reusable)))
+}
+
+fn parse_status_line(line: &[u8]) -> Result<(String, HTTPStatusCode), ClientError> {
+    let invalid = || {
+        ClientError::InvalidResponse(format!(
+            "invalid status line: {}",
+            String::from_utf8_lossy(line).trim()
+        ))
+    };
+    let line = std::str::from_utf8(line).map_err(|_| invalid())?.trim_end();
+    let mut parts = line.splitn(3, ' ');
+    let version = parts.next().unwrap_or_default();
+    let code = parts.next().unwrap_or_default();
+    if !version.starts_with("HTTP/1.") || code.len() != 3 {
+        return Err(invalid());
+    }
+    let code: usize = code.parse().map_err(|_| invalid())?;
+    if !(100..600).contains(&code) {
+        return Err(invalid());
+    }
+    Ok((String::from(version), HTTPStatusCode::from_code(code)))
+}
+
+fn read_headers<R: BufRead>(reader: &mut R) -> Result<HeaderMap, ClientError> {
+    let mut lines = Vec::new();
+    let mut head_size = 0;
+    loop {
+        let line = reader.read_max_until(b'\n', MAX_LINE_LENGTH)?;
+        if line.is_empty() {
+            return Err(ClientError::InvalidResponse(String::from(
+                "connection closed in the response head",
+            )));
+        }
+        head_size += line.len();
+        if head_size > MAX_HEAD_SIZE {
+            return Err(ClientError::InvalidResponse(String::from(
+                "response head too large",
+            )));
+        }
+        let line = String::from_utf8_lossy(&line).trim().to_string();
+        if line.is_empty() {
+            return Ok(HeaderMap::builder(&lines));
+        }
+        lines.push(line);
+    }
+}
+
+/// The Content-Length, if given: repeated headers (joined to a list) must agree.
+fn content_length(headers: &HeaderMap) -> Result<Option<usize>, ClientError> {
+    let value = match headers.get("content-length") {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+    let mut lengths = value.split(',').map(|l| l.trim());
+    let first = lengths.next().unwrap_or_default();
+    let valid = !first.is_empty() && first.bytes().all(|b| b.is_ascii_digit());
+    match first.parse() {
+        Ok(length) if valid && lengths.all(|l| l == first) => Ok(Some(length)),
+        _ => Err(ClientError::InvalidResponse(format!(
+            "invalid Content-Length: {}",
+            value
+        ))),
+    }
+}
+
+/// Decodes a chunked body (RFC 9112, 7.1); chunk extensions and trailers are ignored.
+fn read_chunked<R: BufRead>(reader: &mut R, max_body_size: usize) -> Result<Vec<u8>, ClientError> {
+    let mut body = Vec::new();
+    loop {
+        let line = reader.read_max_until(b'\n', MAX_LINE_LENGTH)?;
+        let line = String::from_utf8_lossy(&line);
+        let size = line.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size, 16).map_err(|_| {
+            ClientError::InvalidResponse(format!("invalid chunk size: {}", line.trim()))
+        })?;
+        if size == 0 {
+            read_headers(reader)?;
+            return Ok(body);
+        }
+        if size > max_body_size - body.len() {
+            return Err(body_too_large(max_body_size));
+        }
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..])?;
+        let end = reader.read_max_until(b'\n', 2)?;
+        if end != b"\r\n" && end != b"\n" {
+            return Err(ClientError::InvalidResponse(String::from(
+                "missing line break after chunk",
+            )));
+        }
+    }
+}
+
+fn read_until_close<R: BufRead>(
+    reader: &mut R,
+    max_body_size: usize,
+) -> Result<Vec<u8>, ClientError> {
+    let mut body = Vec::new();
+    reader
+        .take(max_body_size as u64 + 1)
+        .read_to_end(&mut body)?;
+    if body.len() > max_body_size {
+        return Err(body_too_large(max_body_size));
+    }
+    Ok(body)
+}
+
+fn body_too_large(max_body_size: usize) -> ClientError {
+    ClientError::InvalidResponse(format!("response body larger than {} bytes", max_body_size))
+}
@@ -0,0 +1,92 @@
+use std::fmt::{Display, Formatter};
+
+use crate::httpclient::ClientError;
+
+/// An absolute `http://` URL, split into the parts the client needs.
+/// `https` is not supported, as the crate has no TLS implementation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Url {
+    pub host: String,
+    pub port: u16,
+    /// path and query, starting with `/`; the fragment is removed
+    pub target: String,
+}
+
+impl Url {
+    pub fn parse(url: &str) -> Result<Url, ClientError> {
+        let invalid = || ClientError::InvalidUrl(String::from(url));
+        let rest = match url.split_once("://") {
+            Some((scheme, rest)) if scheme.eq_ignore_ascii_case("http") => rest,
+            Some(_) => return Err(ClientError::UnsupportedScheme(String::from(url))),
+            None => return Err(invalid()),
+        };
+        let rest = rest.split('#').next().unwrap_or_default();
+        let (authority, target) = match rest.find(['/', '?']) {
+            Some(idx) if rest[idx..].starts_with('?') => {
+                (&rest[..idx], format!("/{}", &rest[idx..]))
+            }
+            Some(idx) => (&rest[..idx], String::from(&rest[idx..])),
+            None => (rest, String::from("/")),
+        };
+        if authority.contains('@') {
+            // user info is not supported, and would be a way to disguise the host
+            return Err(invalid());
+        }
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) if !port.contains(']') => {
+                (host, port.parse().map_err(|_| invalid())?)
+            }
+            _ => (authority, 80),
+        };
+        if host.is_empty() || target.contains([' ', '\r', '\n']) {
+            return Err(invalid());
+        }
+        Ok(Url {
+            host: host.to_ascii_lowercase(),
+            port,
+            target,
+        })
+    }
+
+    /// Resolves a (redirect) location against this URL: absolute URLs,
+    /// network-path (`//host/path`), absolute-path and relative references.
+    pub fn join(&self, location: &str) -> Result<Url, ClientError> {
+        let location = location.trim();
+        if location.contains("://") {
+            return Url::parse(location);
+        }
+        if let Some(rest) = location.strip_prefix("//") {
+            return Url::parse(&format!("http://{}", rest));
+        }
+        let path = self.target.split('?').next().unwrap_or_default();
+        let target = match location.chars().next() {
+            Some('/') => String::from(location),
+            Some('?') => format!("{}{}", path, location),
+            None => self.target.clone(),
+            _ => {
+                let dir = &path[..path.rfind('/').unwrap_or_default() + 1];
+                format!("{}{}", dir, location)
+            }
+        };
+        Url::parse(&format!("http://{}{}", self.authority(), target))
+    }
+
+    /// `host:port`, the key of pooled connections and the address to connect to.
+    pub fn addr(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+
+    /// The value of the `Host` header: the port is left out if it is the default.
+    pub fn authority(&self) -> String {
+        match self.port {
+            80 => self.host.clone(),
+            port => format!("{}:{}", self.host, port),
+        }
+    }
+}
+
+impl Display for Url {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "http://{}{}", self.authority(), self.target)
+    }
+}
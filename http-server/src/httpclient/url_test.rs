@@ -0,0 +1,67 @@
+#[cfg(test)]
+mod url_test {
+    use super::super::{ClientError, Url};
+
+    fn url(host: &str, port: u16, target: &str) -> Url {
+        Url {
+            host: String::from(host),
+            port,
+            target: String::from(target),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Url::parse("http://Example.com").unwrap(),
+            url("example.com", 80, "/")
+        );
+        assert_eq!(
+            Url::parse("HTTP://localhost:3000/a/b?c=d#frag").unwrap(),
+            url("localhost", 3000, "/a/b?c=d")
+        );
+        assert_eq!(
+            Url::parse("http://localhost?x=1").unwrap(),
+            url("localhost", 80, "/?x=1")
+        );
+        assert_eq!(
+            Url::parse("http://[::1]:8080/").unwrap(),
+            url("[::1]", 8080, "/")
+        );
+        assert_eq!(Url::parse("http://[::1]/").unwrap(), url("[::1]", 80, "/"));
+
+        assert!(matches!(
+            Url::parse("https://example.com/"),
+            Err(ClientError::UnsupportedScheme(_))
+        ));
+        for invalid in [
+            "example.com",
+            "http://",
+            "http://host:port/",
+            "http://user@host/",
+            "http://host/a b",
+        ] {
+            assert!(
+                matches!(Url::parse(invalid), Err(ClientError::InvalidUrl(_))),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_join() {
+        let base = Url::parse("http://localhost:3000/a/b?q=1").unwrap();
+        let join = |location: &str| base.join(location).unwrap().to_string();
+        assert_eq!(join("http://other/x"), "http://other/x");
+        assert_eq!(join("//other:81/x"), "http://other:81/x");
+        assert_eq!(join("/c"), "http://localhost:3000/c");
+        assert_eq!(join("c?d=2"), "http://localhost:3000/a/c?d=2");
+        assert_eq!(join("?q=2"), "http://localhost:3000/a/b?q=2");
+        assert_eq!(join(""), "http://localhost:3000/a/b?q=1");
+        assert_eq!(
+            Url::parse("http://host:80/").unwrap().to_string(),
+            "http://host/"
+        );
+    }
+}
@@ -1,15 +1,135 @@
+#[cfg(feature = "tokio")]
+mod async_server;
+mod conditional;
+mod connection_limits;
+#[cfg(feature = "event-loop")]
+mod event_loop;
 mod header_map;
+mod health;
+mod http_verb;
+pub mod http2;
+#[allow(clippy::module_inception)]
 mod httpserver;
+mod ip_filter;
 mod request;
+mod request_head;
+mod request_parser;
 mod request_params;
 mod http_status_codes;
+mod lifecycle;
+mod metrics;
+mod negotiation;
+mod problem;
+mod reload;
+mod response;
+mod router;
+mod sse;
+mod test_client;
+mod virtual_hosts;
+pub mod middleware;
 
 
-pub use httpserver::HttpServer;
-use header_map::HeaderMap;
-use request::Request;
-use request_params::RequestParams;
-use http_status_codes::HTTPStatusCode;
+pub use httpserver::{HttpServer, IoMode};
+#[cfg(feature = "tokio")]
+pub use async_server::AsyncHttpServer;
+pub use conditional::{
+    check_preconditions, etag, etag_matches, evaluate_preconditions, not_modified,
+    parse_etag_list, Precondition,
+};
+pub use connection_limits::ConnectionLimits;
+pub use header_map::HeaderMap;
+pub use http_verb::HttpVerb;
+pub use request::{normalize_host, Request};
+pub use request_head::{parse_head, RequestHead, MAX_HEAD_SIZE, MAX_LINE_LENGTH};
+pub use request_parser::{parse_request, ParsedRequest, RequestParser};
+pub use request_params::RequestParams;
+pub use http_status_codes::HTTPStatusCode;
+pub use response::Response;
+pub use problem::Problem;
+pub use negotiation::{best_match, parse_accept, MediaRange, Representations};
+pub use metrics::HttpMetrics;
+pub use health::{Health, HealthCheck};
+pub use lifecycle::ShutdownHandle;
+pub use reload::{ConfigLoader, ReloadHandle, ServerConfig};
+pub use ip_filter::{FilterAction, IpFilter, SharedIpFilter};
+pub use router::{Handler, Router};
+pub use sse::{Event, EventSender, EventStream, MIN_HEARTBEAT};
+pub use test_client::{EphemeralServer, TestClient, TestRequest, TestResponse};
+pub use virtual_hosts::VirtualHosts;
+pub use middleware::{Middleware, Next};
 
 #[cfg(test)]
-mod request_params_test;
\ No newline at end of file
+#[allow(clippy::module_inception)]
+mod request_params_test;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod auth_test;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod cors_test;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod router_test;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod http_verb_test;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod health_test;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod problem_test;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod negotiation_test;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod conditional_test;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod cache_test;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod rate_limit_test;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod ip_filter_test;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod virtual_hosts_test;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod request_head_test;
+#[cfg(all(test, feature = "tokio"))]
+#[allow(clippy::module_inception)]
+mod async_server_test;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod hpack_test;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod http2_test;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod sse_test;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod test_client_test;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod unix_socket_test;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod reload_test;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod metrics_test;
+#[cfg(all(test, feature = "event-loop"))]
+#[allow(clippy::module_inception)]
+mod event_loop_test;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod request_parser_test;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod request_test;
@@ -0,0 +1,265 @@
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::timeout;
+
+use crate::httpserver::{
+    ConnectionLimits, HTTPStatusCode, HttpVerb, Middleware, ParsedRequest, Problem, Request,
+    RequestParser, Response, Router, VirtualHosts,
+};
+use crate::utils::logging::LogSeverity;
+
+/// An async (tokio based) server front end, with the same routing, middlewares,
+/// request parsing and response types as the [HttpServer](crate::httpserver::HttpServer).
+/// Handlers can be async functions:
+///
+/// ```ignore
+/// async fn show_user(request: Request) -> Response {
+///     let user = db.load_user(request.headers.get("x-user-id")).await;
+///     Response::json(&user)
+/// }
+///
+/// let mut server = AsyncHttpServer::new("127.0.0.1:3000");
+/// server.route_async(HttpVerb::GET, "/user", show_user);
+/// server.route(HttpVerb::GET, "/status", status);
+/// server.run().await?;
+/// ```
+///
+/// The connections are read and written async. As the middlewares and the routing
+/// are synchronous, each request is then dispatched on tokio's blocking thread pool,
+/// where async handlers are driven to completion.
+pub struct AsyncHttpServer {
+    bind_addr: String,
+    router: Router,
+    /// the middlewares for all hosts
+    shared: Router,
+    virtual_hosts: Vec<(String, Router)>,
+    limits: ConnectionLimits,
+}
+
+impl AsyncHttpServer {
+    pub fn new(bind_addr: &str) -> AsyncHttpServer {
+        AsyncHttpServer {
+            bind_addr: String::from(bind_addr),
+            router: Router::new(),
+            shared: Router::new(),
+            virtual_hosts: Vec::new(),
+            limits: ConnectionLimits::default(),
+        }
+    }
+
+    /// Sets the timeouts for the client connections.
+    pub fn connection_limits(&mut self, limits: ConnectionLimits) {
+        self.limits = limits;
+    }
+
+    /// Registers a (synchronous) handler for the given method and path, see [Router].
+    pub fn route<F>(&mut self, method: HttpVerb, path: &str, handler: F)
+    where
+        F: Fn(&Request) -> Response + Send + Sync + 'static,
+    {
+        self.router.route(method, path, handler);
+    }
+
+    /// Registers an async handler for the given method and path. It gets its own
+    /// copy of the request.
+    pub fn route_async<F, Fut>(&mut self, method: HttpVerb, path: &str, handler: F)
+    where
+        F: Fn(Request) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Response>,
+    {
+        self.router.route(method, path, move |request: &Request| {
+            // runs on a blocking thread, see handle_connection:
+            tokio::runtime::Handle::current().block_on(handler(request.clone()))
+        });
+    }
+
+    /// Registers a middleware for all requests below the given path prefix, on
+    /// all virtual hosts.
+    pub fn middleware<M>(&mut self, prefix: &str, middleware: M)
+    where
+        M: Middleware + 'static,
+    {
+        self.shared.middleware(prefix, middleware);
+    }
+
+    /// Serves the requests for the given host name with their own router, see [VirtualHosts].
+    pub fn virtual_host(&mut self, host: &str, router: Router) {
+        self.virtual_hosts.push((String::from(host), router));
+    }
+
+    /// Binds to the address and serves the connections, see [AsyncHttpServer::serve].
+    pub async fn run(self) -> io::Result<()> {
+        let listener = TcpListener::bind(&self.bind_addr).await?;
+        eprintln!("Async server started on {}", listener.local_addr()?);
+        self.serve(listener).await
+    }
+
+    /// Creates a tokio runtime and runs the server on it, for use from synchronous code.
+    pub fn start(self) -> io::Result<()> {
+        tokio::runtime::Runtime::new()?.block_on(self.run())
+    }
+
+    /// Accepts and serves connections from the given listener, each in its own task.
+    pub async fn serve(self, listener: TcpListener) -> io::Result<()> {
+        let mut hosts = VirtualHosts::with_shared(self.shared, self.router);
+        for (host, router) in self.virtual_hosts {
+            hosts.host(&host, router);
+        }
+        let hosts = Arc::new(hosts);
+
+        loop {
+            let (stream, peer) = match listener.accept().await {
+                Ok(s) => s,
+                Err(e) => {
+                    log(
+                        &format!("Cannot accept connection: {}", e),
+                        LogSeverity::ERROR,
+                    );
+                    continue;
+                }
+            };
+            let hosts = Arc::clone(&hosts);
+            let limits = self.limits;
+            tokio::spawn(async move {
+                if let Err(e) = handle_connection(stream, peer, hosts, limits).await {
+                    log(&e.to_string(), LogSeverity::ERROR);
+                }
+            });
+        }
+    }
+}
+
+/// Reads, dispatches and answers the requests of a connection, as long as it is kept alive.
+async fn handle_connection(
+    mut stream: TcpStream,
+    peer: SocketAddr,
+    hosts: Arc<VirtualHosts>,
+    limits: ConnectionLimits,
+) -> io::Result<()> {
+    let mut buf = Vec::new();
+    let mut served = 0;
+    loop {
+        let mut parser = RequestParser::new(limits.max_body_size);
+        let read_head = read_request(&mut stream, &mut buf, &mut parser, true);
+        let parsed = match timeout(limits.header_timeout, read_head).await {
+            Ok(Ok(Progress::Complete(parsed))) => parsed,
+            // the head is complete, the body has its own timeout:
+            Ok(Ok(Progress::Head)) => {
+                let read_body = read_request(&mut stream, &mut buf, &mut parser, false);
+                match timeout(limits.body_timeout, read_body).await {
+                    Ok(Ok(Progress::Complete(parsed))) => parsed,
+                    Ok(Ok(_)) => return Ok(()),
+                    Ok(Err(status)) => return respond_error(&mut stream, status, &limits).await,
+                    Err(_) => {
+                        return respond_error(&mut stream, HTTPStatusCode::ClientError(408), &limits)
+                            .await
+                    }
+                }
+            }
+            // closed, or idle keep-alive connection:
+            Ok(Ok(Progress::Closed)) => return Ok(()),
+            Err(_) if served > 0 && buf.is_empty() => return Ok(()),
+            Err(_) => {
+                return respond_error(&mut stream, HTTPStatusCode::ClientError(408), &limits).await
+            }
+            Ok(Err(status)) => return respond_error(&mut stream, status, &limits).await,
+        };
+        buf.drain(..parsed.len);
+
+        let keep_alive = parsed.head.keep_alive();
+        let request = parsed.into_request(Some(peer));
+        let method = request.method.clone();
+
+        let hosts = Arc::clone(&hosts);
+        let dispatched = tokio::task::spawn_blocking(move || {
+            let router = hosts.select(request.host().as_deref());
+            request.respond(router)
+        })
+        .await;
+        let mut response = dispatched.unwrap_or_else(|_| {
+            // the handler panicked:
+            Problem::new(HTTPStatusCode::ServerError(500)).into()
+        });
+        response.headers.set(
+            "Connection",
+            if keep_alive { "keep-alive" } else { "close" },
+        );
+
+        match timeout(
+            limits.write_timeout,
+            stream.write_all(&response.to_bytes(&method)),
+        )
+        .await
+        {
+            Ok(result) => result?,
+            Err(_) => return Ok(()),
+        }
+        served += 1;
+        if !keep_alive {
+            return stream.shutdown().await;
+        }
+    }
+}
+
+/// How far [read_request] got.
+enum Progress {
+    /// the connection was closed (or broke) before a request was started
+    Closed,
+    /// the head is complete, the body is still to be read
+    Head,
+    Complete(ParsedRequest),
+}
+
+/// Reads until the parser has a complete request, or only its head if
+/// `head_only` is set (to read the body with a separate timeout).
+async fn read_request(
+    stream: &mut TcpStream,
+    buf: &mut Vec<u8>,
+    parser: &mut RequestParser,
+    head_only: bool,
+) -> Result<Progress, HTTPStatusCode> {
+    let mut chunk = [0u8; 8192];
+    loop {
+        if let Some(parsed) = parser.parse(buf)? {
+            return Ok(Progress::Complete(parsed));
+        }
+        if head_only && parser.head_complete() {
+            return Ok(Progress::Head);
+        }
+        let n = match stream.read(&mut chunk).await {
+            Ok(n) => n,
+            Err(_) => return Ok(Progress::Closed),
+        };
+        if n == 0 {
+            return match buf.is_empty() {
+                true => Ok(Progress::Closed),
+                // connection closed in the middle of the request
+                false => Err(HTTPStatusCode::ClientError(400)),
+            };
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+}
+
+async fn respond_error(
+    stream: &mut TcpStream,
+    status: HTTPStatusCode,
+    limits: &ConnectionLimits,
+) -> io::Result<()> {
+    let response = Response::from(Problem::new(status)).with_header("Connection", "close");
+    let _ = timeout(
+        limits.write_timeout,
+        stream.write_all(&response.to_bytes(&HttpVerb::GET)),
+    )
+    .await;
+    stream.shutdown().await
+}
+
+fn log(msg: &str, severity: LogSeverity) {
+    eprintln!("{}: {}\n", severity, msg);
+}
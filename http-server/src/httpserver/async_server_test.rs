@@ -0,0 +1,91 @@
+#[cfg(test)]
+mod async_server_test {
+    use super::super::{
+        AsyncHttpServer, ConnectionLimits, HTTPStatusCode, HttpVerb, Request, Response,
+    };
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    async fn greet(request: Request) -> Response {
+        tokio::task::yield_now().await;
+        let name = request.headers.get("x-name").unwrap_or_default();
+        Response::text(HTTPStatusCode::Success(200), &format!("hello {}", name))
+    }
+
+    #[test]
+    fn test_async_and_sync_routes() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let mut server = AsyncHttpServer::new("127.0.0.1:0");
+            server.route_async(HttpVerb::GET, "/greet", greet);
+            server.route(HttpVerb::GET, "/sync", |_| {
+                Response::text(HTTPStatusCode::Success(200), "sync")
+            });
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(server.serve(listener));
+
+            // two requests on a kept-alive connection:
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream
+                .write_all(b"GET /greet HTTP/1.1\r\nHost: a\r\nX-Name: tokio\r\n\r\n")
+                .await
+                .unwrap();
+            stream
+                .write_all(b"GET /sync HTTP/1.1\r\nHost: a\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+            assert!(response.contains("Connection: keep-alive\r\n"));
+            assert!(response.contains("hello tokio"));
+            assert!(response.ends_with("\r\n\r\nsync"));
+
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream
+                .write_all(b"GET /missing HTTP/1.0\r\n\r\n")
+                .await
+                .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        });
+    }
+
+    /// Sends the request on a new connection and returns the status line of the response.
+    async fn status_line(addr: std::net::SocketAddr, raw: &[u8]) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(raw).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        String::from(response.lines().next().unwrap_or_default())
+    }
+
+    #[test]
+    fn test_framing_errors() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let mut server = AsyncHttpServer::new("127.0.0.1:0");
+            server.connection_limits(ConnectionLimits {
+                max_body_size: 10,
+                ..ConnectionLimits::default()
+            });
+            server.route(HttpVerb::POST, "/", |_| {
+                Response::text(HTTPStatusCode::Success(200), "ok")
+            });
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(server.serve(listener));
+
+            // the chunked body must not be read as a second request:
+            let smuggled = b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 5\r\n\
+                             Transfer-Encoding: chunked\r\n\r\n0\r\n\r\nGET /admin HTTP/1.1\r\n\r\n";
+            assert_eq!(status_line(addr, smuggled).await, "HTTP/1.1 400 Bad Request");
+            let too_large = b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 11\r\n\r\n";
+            assert_eq!(status_line(addr, too_large).await, "HTTP/1.1 413 Payload Too Large");
+            let ok = b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 10\r\nConnection: close\r\n\r\n0123456789";
+            assert_eq!(status_line(addr, ok).await, "HTTP/1.1 200 OK");
+        });
+    }
+}
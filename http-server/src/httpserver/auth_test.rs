@@ -0,0 +1,95 @@
+#[cfg(test)]
+mod auth_test {
+    use super::super::middleware::auth::*;
+    use super::super::{HTTPStatusCode, HttpVerb, Response, Router, TestClient};
+    use crate::utils::base64;
+
+    // user "alex", password "password"
+    const HTPASSWD: &str = "# test users\nalex:{SHA}W6ph5Mm5Pz8GgiULbPgzG37mj9g=\n";
+
+    fn basic(user: &str, password: &str) -> String {
+        format!("Basic {}", base64::encode(format!("{}:{}", user, password).as_bytes()))
+    }
+
+    #[test]
+    fn test_verify_sha_password() {
+        assert!(verify_password("{SHA}W6ph5Mm5Pz8GgiULbPgzG37mj9g=", "password"));
+        assert!(!verify_password("{SHA}W6ph5Mm5Pz8GgiULbPgzG37mj9g=", "Password"));
+        assert!(!verify_password("plaintext", "plaintext"));
+    }
+
+    #[test]
+    fn test_basic_auth() {
+        let auth = AuthMiddleware::new("test").htpasswd(HTPASSWD);
+        assert!(auth.is_authorized(&basic("alex", "password")));
+        assert!(auth.is_authorized(&format!("basic {}", base64::encode(b"alex:password"))));
+        assert!(!auth.is_authorized(&basic("alex", "wrong")));
+        // verified against the hash of another user, but never accepted:
+        assert!(!auth.is_authorized(&basic("unknown", "password")));
+        assert!(!auth.is_authorized("Basic not-base64!"));
+    }
+
+    #[test]
+    fn test_bearer_auth() {
+        let auth = AuthMiddleware::new("test").bearer_token("s3cr3t-token");
+        assert!(auth.is_authorized("Bearer s3cr3t-token"));
+        assert!(!auth.is_authorized("Bearer s3cr3t-toke"));
+        assert!(!auth.is_authorized("Bearer"));
+        assert!(!auth.is_authorized(&basic("s3cr3t-token", "")));
+    }
+
+    #[test]
+    fn test_middleware() {
+        let mut router = Router::new();
+        router.route(HttpVerb::GET, "/internal", |_| {
+            Response::new(HTTPStatusCode::Success(200))
+        });
+        let auth = AuthMiddleware::new("test")
+            .htpasswd(HTPASSWD)
+            .bearer_token("s3cr3t-token");
+        router.middleware("/internal", auth);
+        let client = TestClient::new(router);
+
+        let response = client.get("/internal").send();
+        assert_eq!(response.status.code(), 401);
+        assert_eq!(
+            response.headers.get("www-authenticate").unwrap(),
+            "Basic realm=\"test\", charset=\"UTF-8\", Bearer realm=\"test\""
+        );
+
+        let response = client
+            .get("/internal")
+            .header("Authorization", "Bearer wrong")
+            .send();
+        assert_eq!(response.status.code(), 401);
+        assert!(response
+            .headers
+            .get("www-authenticate")
+            .unwrap()
+            .ends_with("Bearer realm=\"test\", error=\"invalid_token\""));
+
+        let response = client
+            .get("/internal")
+            .header("Authorization", &basic("alex", "password"))
+            .send();
+        assert_eq!(response.status.code(), 200);
+    }
+
+    #[test]
+    fn test_realm_is_quoted() {
+        let mut router = Router::new();
+        let auth = AuthMiddleware::new(r#"the "internal" \ area"#).bearer_token("s3cr3t-token");
+        router.middleware("/", auth);
+        let response = TestClient::new(router).get("/").send();
+        assert_eq!(
+            response.headers.get("www-authenticate").unwrap(),
+            r#"Bearer realm="the \"internal\" \\ area""#
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid realm")]
+    fn test_realm_with_line_break() {
+        AuthMiddleware::new("internal\r\nSet-Cookie: a=b");
+    }
+}
@@ -0,0 +1,186 @@
+#[cfg(test)]
+mod cache_test {
+    use super::super::middleware::cache::*;
+    use super::super::{HTTPStatusCode, HeaderMap, HttpVerb, Response, Router, TestClient};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    fn response(body: &str) -> Response {
+        Response::text(HTTPStatusCode::Success(200), body)
+    }
+
+    fn headers(name: &str, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.set(name, value);
+        headers
+    }
+
+    #[test]
+    fn test_response_ttl() {
+        let ttl = |cc: &str, route: Option<u64>| {
+            response_ttl(
+                &headers("Cache-Control", cc),
+                route.map(Duration::from_secs),
+            )
+        };
+        assert_eq!(
+            ttl("public, max-age=60", None),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(
+            ttl("max-age=60, s-maxage=10", None),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(ttl("max-age=60", Some(5)), Some(Duration::from_secs(5)));
+        assert_eq!(ttl("", Some(5)), Some(Duration::from_secs(5)));
+        assert_eq!(ttl("", None), None);
+        assert_eq!(ttl("max-age=0", None), None);
+        assert_eq!(ttl("no-store", Some(5)), None);
+        assert_eq!(ttl("private, max-age=60", None), None);
+        assert_eq!(
+            response_ttl(&headers("Set-Cookie", "a=b"), Some(Duration::from_secs(5))),
+            None
+        );
+    }
+
+    #[test]
+    fn test_store_and_lookup() {
+        let cache = ResponseCache::new(10_000);
+        let ttl = Duration::from_secs(60);
+        assert!(cache.lookup("GET /a", &HeaderMap::new()).is_none());
+        assert!(cache.store("GET /a", &HeaderMap::new(), &response("a"), ttl));
+
+        let hit = cache.lookup("GET /a", &HeaderMap::new()).unwrap();
+        assert_eq!(hit.body, b"a");
+        assert_eq!(hit.headers.get("age"), Some(String::from("0")));
+
+        // replacing keeps the size accounting right:
+        let size = cache.size();
+        assert!(cache.store("GET /a", &HeaderMap::new(), &response("b"), ttl));
+        assert_eq!(cache.size(), size);
+        assert_eq!(
+            cache.lookup("GET /a", &HeaderMap::new()).unwrap().body,
+            b"b"
+        );
+    }
+
+    #[test]
+    fn test_vary() {
+        let cache = ResponseCache::new(10_000);
+        let ttl = Duration::from_secs(60);
+        let json = response("{}").with_header("Vary", "Accept");
+        let html = response("<p>").with_header("Vary", "Accept");
+        cache.store("GET /a", &headers("Accept", "application/json"), &json, ttl);
+        cache.store("GET /a", &headers("Accept", "text/html"), &html, ttl);
+
+        let hit = cache
+            .lookup("GET /a", &headers("accept", "text/html"))
+            .unwrap();
+        assert_eq!(hit.body, b"<p>");
+        assert!(cache.lookup("GET /a", &HeaderMap::new()).is_none());
+        assert!(!cache.store(
+            "GET /a",
+            &HeaderMap::new(),
+            &response("").with_header("Vary", "*"),
+            ttl
+        ));
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let body = "x".repeat(100);
+        let cache = ResponseCache::new(400);
+        let ttl = Duration::from_secs(60);
+        cache.store("GET /1", &HeaderMap::new(), &response(&body), ttl);
+        cache.store("GET /2", &HeaderMap::new(), &response(&body), ttl);
+        // /1 is now used more recently than /2:
+        cache.lookup("GET /1", &HeaderMap::new()).unwrap();
+        cache.store("GET /3", &HeaderMap::new(), &response(&body), ttl);
+
+        assert!(cache.lookup("GET /1", &HeaderMap::new()).is_some());
+        assert!(cache.lookup("GET /2", &HeaderMap::new()).is_none());
+        assert!(cache.lookup("GET /3", &HeaderMap::new()).is_some());
+        assert!(cache.size() <= 400);
+        assert!(!cache.store(
+            "GET /big",
+            &HeaderMap::new(),
+            &response(&"x".repeat(500)),
+            ttl
+        ));
+    }
+
+    #[test]
+    fn test_lru_order() {
+        let body = "x".repeat(100);
+        let ttl = Duration::from_secs(60);
+        let probe = ResponseCache::new(10_000);
+        probe.store("GET /1", &HeaderMap::new(), &response(&body), ttl);
+        // room for four entries:
+        let cache = ResponseCache::new(4 * probe.size());
+        for key in ["GET /1", "GET /2", "GET /3", "GET /4"] {
+            cache.store(key, &HeaderMap::new(), &response(&body), ttl);
+        }
+        cache.lookup("GET /2", &HeaderMap::new()).unwrap();
+        cache.lookup("GET /1", &HeaderMap::new()).unwrap();
+        // replacing an entry counts as a use, too:
+        cache.store("GET /3", &HeaderMap::new(), &response(&body), ttl);
+        cache.store("GET /5", &HeaderMap::new(), &response(&body), ttl);
+        cache.store("GET /6", &HeaderMap::new(), &response(&body), ttl);
+
+        let cached: Vec<&str> = ["GET /1", "GET /2", "GET /3", "GET /4", "GET /5", "GET /6"]
+            .into_iter()
+            .filter(|key| cache.lookup(key, &HeaderMap::new()).is_some())
+            .collect();
+        assert_eq!(cached, ["GET /1", "GET /3", "GET /5", "GET /6"]);
+    }
+
+    #[test]
+    fn test_concurrent_reads() {
+        let cache = Arc::new(ResponseCache::new(100_000));
+        let ttl = Duration::from_secs(60);
+        for i in 0..10 {
+            let key = format!("GET /{}", i);
+            cache.store(&key, &HeaderMap::new(), &response(&key), ttl);
+        }
+        let readers: Vec<_> = (0..8)
+            .map(|_| {
+                let cache = Arc::clone(&cache);
+                thread::spawn(move || {
+                    for n in 0..1000 {
+                        let key = format!("GET /{}", n % 10);
+                        let hit = cache.lookup(&key, &HeaderMap::new()).unwrap();
+                        assert_eq!(hit.body, key.as_bytes());
+                    }
+                })
+            })
+            .collect();
+        // writes in between:
+        for i in 10..100 {
+            let key = format!("GET /other/{}", i);
+            cache.store(&key, &HeaderMap::new(), &response(&key), ttl);
+        }
+        for reader in readers {
+            reader.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_host_in_key() {
+        let mut router = Router::new();
+        router.route(HttpVerb::GET, "/", |request| {
+            response(&request.host().unwrap_or_default()).with_header("Cache-Control", "max-age=60")
+        });
+        router.middleware("/", CacheMiddleware::new(10_000));
+        let client = TestClient::new(router);
+        let get = |host: &str| client.get("/").header("Host", host).send();
+
+        assert_eq!(get("a.example.com").headers.get("x-cache").unwrap(), "MISS");
+        let response = get("b.example.com");
+        assert_eq!(response.headers.get("x-cache").unwrap(), "MISS");
+        assert_eq!(response.text(), "b.example.com");
+        let response = get("A.Example.com:8080");
+        assert_eq!(response.headers.get("x-cache").unwrap(), "HIT");
+        assert_eq!(response.text(), "a.example.com");
+    }
+}
@@ -0,0 +1,196 @@
+use std::time::SystemTime;
+
+use crate::httpserver::{HTTPStatusCode, HeaderMap, HttpVerb, Problem, Request, Response};
+use crate::utils::{http_date, sha1};
+
+/// Headers a 304 response carries over from the 200 response it replaces
+/// (RFC 9110, 15.4.5).
+const NOT_MODIFIED_HEADERS: [&str; 7] = [
+    "Cache-Control",
+    "Content-Location",
+    "Date",
+    "ETag",
+    "Expires",
+    "Last-Modified",
+    "Vary",
+];
+
+/// The outcome of evaluating the conditional request headers.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Precondition {
+    /// no condition failed: process the request as usual
+    Passed,
+    /// the cached representation is still valid: answer with 304 Not Modified
+    NotModified,
+    /// a condition failed: answer with 412 Precondition Failed
+    Failed,
+}
+
+/// Computes the entity tag of a representation from its body: a strong ETag
+/// is the quoted SHA-1 hex digest, a weak one is prefixed with `W/`.
+pub fn etag(body: &[u8], weak: bool) -> String {
+    let hash = sha1::hex_digest(body);
+    if weak {
+        format!("W/\"{}\"", hash)
+    } else {
+        format!("\"{}\"", hash)
+    }
+}
+
+/// Compares two entity tags. The strong comparison requires both tags to be
+/// strong and equal, the weak comparison ignores the `W/` prefix.
+pub fn etag_matches(a: &str, b: &str, strong: bool) -> bool {
+    let (a_weak, a) = split_weak(a);
+    let (b_weak, b) = split_weak(b);
+    a == b && !(strong && (a_weak || b_weak))
+}
+
+fn split_weak(etag: &str) -> (bool, &str) {
+    match etag.strip_prefix("W/") {
+        Some(tag) => (true, tag),
+        None => (false, etag),
+    }
+}
+
+/// Splits an `If-Match` / `If-None-Match` value into its entity tags.
+/// The opaque tags may contain commas, so the list is split outside of quotes only.
+pub fn parse_etag_list(value: &str) -> Vec<&str> {
+    let mut tags = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    for (i, c) in value.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                tags.push(value[start..i].trim());
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    tags.push(value[start..].trim());
+    tags.retain(|t| !t.is_empty());
+    tags
+}
+
+/// Evaluates the preconditions of a request against the current state of the
+/// target resource, in the order of RFC 9110, 13.2.2. `etag` / `last_modified`
+/// describe the current representation, `None` for an etag means the resource
+/// does not exist.
+pub fn evaluate_preconditions(
+    method: &HttpVerb,
+    headers: &HeaderMap,
+    etag: Option<&str>,
+    last_modified: Option<SystemTime>,
+) -> Precondition {
+    let is_get_or_head = matches!(method, HttpVerb::GET | HttpVerb::HEAD);
+
+    if let Some(if_match) = headers.get("if-match") {
+        let passed = match etag {
+            None => false,
+            Some(_) if if_match.trim() == "*" => true,
+            Some(etag) => parse_etag_list(&if_match)
+                .iter()
+                .any(|tag| etag_matches(tag, etag, true)),
+        };
+        if !passed {
+            return Precondition::Failed;
+        }
+    } else if let Some(since) = headers.get("if-unmodified-since") {
+        // an invalid date is ignored:
+        if let (Some(since), Some(modified)) = (http_date::parse(&since), last_modified) {
+            if seconds(modified) > seconds(since) {
+                return Precondition::Failed;
+            }
+        }
+    }
+
+    if let Some(if_none_match) = headers.get("if-none-match") {
+        let matched = match etag {
+            None => false,
+            Some(_) if if_none_match.trim() == "*" => true,
+            Some(etag) => parse_etag_list(&if_none_match)
+                .iter()
+                .any(|tag| etag_matches(tag, etag, false)),
+        };
+        if matched {
+            return if is_get_or_head {
+                Precondition::NotModified
+            } else {
+                Precondition::Failed
+            };
+        }
+    } else if is_get_or_head {
+        if let Some(since) = headers.get("if-modified-since") {
+            if let (Some(since), Some(modified)) = (http_date::parse(&since), last_modified) {
+                if seconds(modified) <= seconds(since) {
+                    return Precondition::NotModified;
+                }
+            }
+        }
+    }
+
+    Precondition::Passed
+}
+
+/// HTTP dates have a resolution of one second.
+fn seconds(time: SystemTime) -> u64 {
+    time.duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Checks the preconditions of a request before a handler modifies a resource,
+/// e.g. for optimistic locking on PUT with `If-Match`. Returns the response to
+/// send instead (304 or 412), or None if the request can be processed.
+///
+/// ```ignore
+/// server.route(HttpVerb::PUT, "/doc", move |request| {
+///     let current = doc.read().unwrap();
+///     if let Some(response) = check_preconditions(request, Some(&etag(current.as_bytes(), false)), None) {
+///         return response;
+///     }
+///     ...
+/// });
+/// ```
+pub fn check_preconditions(
+    request: &Request,
+    etag: Option<&str>,
+    last_modified: Option<SystemTime>,
+) -> Option<Response> {
+    match evaluate_preconditions(&request.method, &request.headers, etag, last_modified) {
+        Precondition::Passed => None,
+        Precondition::NotModified => {
+            let mut response = Response::new(HTTPStatusCode::Redirect(304));
+            if let Some(etag) = etag {
+                response.headers.set("ETag", etag);
+            }
+            if let Some(modified) = last_modified {
+                response
+                    .headers
+                    .set("Last-Modified", &http_date::format(modified));
+            }
+            Some(response)
+        }
+        Precondition::Failed => Some(precondition_failed(&request.url)),
+    }
+}
+
+/// Turns a full response into its 304 Not Modified counterpart: no body,
+/// only the headers relevant for updating the cached representation.
+pub fn not_modified(response: &Response) -> Response {
+    let mut not_modified = Response::new(HTTPStatusCode::Redirect(304));
+    for name in NOT_MODIFIED_HEADERS {
+        if let Some(value) = response.headers.get(name) {
+            not_modified.headers.set(name, &value);
+        }
+    }
+    not_modified
+}
+
+pub(crate) fn precondition_failed(url: &str) -> Response {
+    Problem::new(HTTPStatusCode::ClientError(412))
+        .with_detail("A precondition given in the request headers failed.")
+        .with_instance(url)
+        .into()
+}
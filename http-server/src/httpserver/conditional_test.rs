@@ -0,0 +1,103 @@
+#[cfg(test)]
+mod conditional_test {
+    use super::super::{
+        etag, etag_matches, evaluate_preconditions, parse_etag_list, HeaderMap, HttpVerb,
+        Precondition,
+    };
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn headers(name: &str, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.set(name, value);
+        headers
+    }
+
+    #[test]
+    fn test_etag() {
+        assert_eq!(etag(b"abc", false), "\"a9993e364706816aba3e25717850c26c9cd0d89d\"");
+        assert_eq!(etag(b"abc", true), "W/\"a9993e364706816aba3e25717850c26c9cd0d89d\"");
+    }
+
+    #[test]
+    fn test_etag_comparison() {
+        assert!(etag_matches("\"1\"", "\"1\"", true));
+        assert!(!etag_matches("W/\"1\"", "\"1\"", true));
+        assert!(etag_matches("W/\"1\"", "\"1\"", false));
+        assert!(!etag_matches("\"1\"", "\"2\"", false));
+        assert_eq!(parse_etag_list("\"a,b\", W/\"c\" ,"), vec!["\"a,b\"", "W/\"c\""]);
+    }
+
+    #[test]
+    fn test_if_none_match() {
+        let h = headers("If-None-Match", "\"x\", W/\"1\"");
+        assert_eq!(
+            evaluate_preconditions(&HttpVerb::GET, &h, Some("\"1\""), None),
+            Precondition::NotModified
+        );
+        assert_eq!(
+            evaluate_preconditions(&HttpVerb::PUT, &h, Some("\"1\""), None),
+            Precondition::Failed
+        );
+        assert_eq!(
+            evaluate_preconditions(&HttpVerb::GET, &h, Some("\"2\""), None),
+            Precondition::Passed
+        );
+        // create only if it does not exist yet:
+        let h = headers("If-None-Match", "*");
+        assert_eq!(
+            evaluate_preconditions(&HttpVerb::PUT, &h, None, None),
+            Precondition::Passed
+        );
+    }
+
+    #[test]
+    fn test_if_match() {
+        let h = headers("If-Match", "\"1\"");
+        assert_eq!(
+            evaluate_preconditions(&HttpVerb::PUT, &h, Some("\"1\""), None),
+            Precondition::Passed
+        );
+        assert_eq!(
+            evaluate_preconditions(&HttpVerb::PUT, &h, Some("\"2\""), None),
+            Precondition::Failed
+        );
+        // If-Match uses the strong comparison:
+        assert_eq!(
+            evaluate_preconditions(&HttpVerb::PUT, &h, Some("W/\"1\""), None),
+            Precondition::Failed
+        );
+        let h = headers("If-Match", "*");
+        assert_eq!(
+            evaluate_preconditions(&HttpVerb::DELETE, &h, None, None),
+            Precondition::Failed
+        );
+    }
+
+    #[test]
+    fn test_dates() {
+        let modified = Some(UNIX_EPOCH + Duration::from_secs(784111777));
+        let h = headers("If-Modified-Since", "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(
+            evaluate_preconditions(&HttpVerb::GET, &h, None, modified),
+            Precondition::NotModified
+        );
+        let h = headers("If-Unmodified-Since", "Sun, 06 Nov 1994 08:49:36 GMT");
+        assert_eq!(
+            evaluate_preconditions(&HttpVerb::PUT, &h, None, modified),
+            Precondition::Failed
+        );
+        // invalid dates are ignored:
+        let h = headers("If-Unmodified-Since", "yesterday");
+        assert_eq!(
+            evaluate_preconditions(&HttpVerb::PUT, &h, None, modified),
+            Precondition::Passed
+        );
+        // If-None-Match takes precedence over If-Modified-Since:
+        let mut h = headers("If-Modified-Since", "Sun, 06 Nov 1994 08:49:37 GMT");
+        h.set("If-None-Match", "\"other\"");
+        assert_eq!(
+            evaluate_preconditions(&HttpVerb::GET, &h, Some("\"1\""), modified),
+            Precondition::Passed
+        );
+    }
+}
@@ -0,0 +1,33 @@
+use std::time::Duration;
+
+/// Limits that protect the worker threads from slow or stalled clients.
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectionLimits {
+    /// time the client has to send the request line and all headers
+    pub header_timeout: Duration,
+    /// time the client has to send the request body
+    pub body_timeout: Duration,
+    /// minimum transfer rate for the request body, in bytes per second
+    pub min_body_rate: Option<u64>,
+    /// maximum size of a request body, in bytes: larger requests are
+    /// answered with 413 before the body is read
+    pub max_body_size: usize,
+    /// timeout for each write of the response
+    pub write_timeout: Duration,
+    /// number of accepted connections that may wait for a free worker thread:
+    /// when exceeded, new connections are answered with 503 and closed
+    pub max_pending_connections: usize,
+}
+
+impl Default for ConnectionLimits {
+    fn default() -> Self {
+        ConnectionLimits {
+            header_timeout: Duration::from_secs(10),
+            body_timeout: Duration::from_secs(60),
+            min_body_rate: Some(1024),
+            max_body_size: 10 * 1024 * 1024,
+            write_timeout: Duration::from_secs(30),
+            max_pending_connections: 64,
+        }
+    }
+}
@@ -0,0 +1,104 @@
+#[cfg(test)]
+mod cors_test {
+    use super::super::middleware::cors::*;
+    use super::super::{HTTPStatusCode, HttpVerb};
+
+    fn cors() -> CorsMiddleware {
+        CorsMiddleware::new()
+            .allow_origin("http://localhost:8080")
+            .allow_origin("https://*.example.com")
+            .allow_methods(&[HttpVerb::GET, HttpVerb::PUT])
+            .allow_headers(&["Content-Type"])
+            .max_age(600)
+    }
+
+    #[test]
+    fn test_origin_matching() {
+        let cors = cors();
+        assert!(cors.is_origin_allowed("http://localhost:8080"));
+        assert!(cors.is_origin_allowed("https://app.example.com"));
+        assert!(cors.is_origin_allowed("https://a.b.example.com"));
+        assert!(!cors.is_origin_allowed("https://example.com"));
+        assert!(!cors.is_origin_allowed("http://app.example.com"));
+        assert!(!cors.is_origin_allowed("https://app.example.com.evil.org"));
+        assert!(!cors.is_origin_allowed("http://localhost:8081"));
+        assert!(CorsMiddleware::new().allow_origin("*").is_origin_allowed("http://any.org"));
+    }
+
+    #[test]
+    fn test_preflight_allowed() {
+        let response = cors().preflight_response(
+            "https://app.example.com",
+            "PUT",
+            Some("content-type"),
+        );
+        assert_eq!(response.status, HTTPStatusCode::Success(204));
+        assert_eq!(
+            response.headers.get("Access-Control-Allow-Origin").as_deref(),
+            Some("https://app.example.com")
+        );
+        assert_eq!(
+            response.headers.get("Access-Control-Allow-Methods").as_deref(),
+            Some("GET, PUT")
+        );
+        assert_eq!(
+            response.headers.get("Access-Control-Allow-Headers").as_deref(),
+            Some("content-type")
+        );
+        assert_eq!(response.headers.get("Access-Control-Max-Age").as_deref(), Some("600"));
+        assert!(response.headers.get("Vary").unwrap().contains("Origin"));
+    }
+
+    #[test]
+    fn test_preflight_denied() {
+        let cors = cors();
+        for (origin, method, headers) in [
+            ("https://evil.org", "GET", None),
+            ("http://localhost:8080", "DELETE", None),
+            ("http://localhost:8080", "GET", Some("Content-Type, X-Custom")),
+        ] {
+            let response = cors.preflight_response(origin, method, headers);
+            assert_eq!(response.status, HTTPStatusCode::ClientError(403));
+            assert!(response.headers.get("Access-Control-Allow-Origin").is_none());
+        }
+    }
+
+    #[test]
+    fn test_credentials_never_use_wildcard_origin() {
+        // `*` does not allow credentials, but the listed origins do:
+        let cors = CorsMiddleware::new()
+            .allow_origin("*")
+            .allow_origin("https://app.example.com")
+            .allow_credentials(true);
+        let response = cors.preflight_response("http://foo.org", "GET", None);
+        assert_eq!(response.status, HTTPStatusCode::Success(204));
+        assert_eq!(
+            response.headers.get("Access-Control-Allow-Origin").as_deref(),
+            Some("*")
+        );
+        assert!(response.headers.get("Access-Control-Allow-Credentials").is_none());
+
+        let response = cors.preflight_response("https://app.example.com", "GET", None);
+        assert_eq!(
+            response.headers.get("Access-Control-Allow-Origin").as_deref(),
+            Some("https://app.example.com")
+        );
+        assert_eq!(
+            response.headers.get("Access-Control-Allow-Credentials").as_deref(),
+            Some("true")
+        );
+        assert!(response.headers.get("Vary").unwrap().contains("Origin"));
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_origin_regex_is_anchored() {
+        let cors = CorsMiddleware::new()
+            .allow_origin_regex(r"https://[a-z]+\.example\.com")
+            .unwrap();
+        assert!(cors.is_origin_allowed("https://app.example.com"));
+        assert!(!cors.is_origin_allowed("https://app.example.com.evil.org"));
+        assert!(!cors.is_origin_allowed("http://evil.org/https://app.example.com"));
+        assert!(CorsMiddleware::new().allow_origin_regex("(").is_err());
+    }
+}
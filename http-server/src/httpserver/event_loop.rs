@@ -0,0 +1,509 @@
+use std::collections::HashMap;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{Shutdown, SocketAddr};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use mio::net::{TcpListener, TcpStream};
+use mio::{Events, Interest, Poll, Token, Waker};
+
+use crate::httpserver::lifecycle::Lifecycle;
+use crate::httpserver::reload::SharedHosts;
+use crate::httpserver::{
+    ConnectionLimits, FilterAction, HTTPStatusCode, HttpMetrics, HttpVerb, Problem, Request,
+    RequestParser, Response, SharedIpFilter,
+};
+use crate::utils::logging::LogSeverity;
+use crate::utils::threadpool::ThreadPool;
+
+const LISTENER: Token = Token(0);
+const WAKER: Token = Token(1);
+
+/// How often the reactor wakes up to check the connection timeouts.
+const TICK: Duration = Duration::from_millis(250);
+
+/// Bytes read from a connection at once, before the buffered data is parsed.
+const READ_CHUNK: usize = 64 * 1024;
+
+/// How long the input of a closing connection is drained, see [State::Closing].
+const LINGER: Duration = Duration::from_millis(500);
+
+enum State {
+    /// waiting for (the rest of) a request
+    Reading,
+    /// a worker thread handles the request
+    Processing,
+    /// the response is written
+    Writing,
+    /// the write side is shut down after the last response, the input is
+    /// discarded until the client closes too: closing with unread input would
+    /// reset the connection, possibly before the client has read the response
+    Closing,
+}
+
+struct Connection {
+    stream: TcpStream,
+    peer_addr: Option<SocketAddr>,
+    state: State,
+    read_buf: Vec<u8>,
+    parser: RequestParser,
+    write_buf: Vec<u8>,
+    written: usize,
+    /// timeout of the current state
+    deadline: Instant,
+    /// the head of the current request is read, waiting for the body
+    head_complete: bool,
+    keep_alive: bool,
+    /// number of requests answered on this connection
+    served: usize,
+    /// a trusted proxy: the IP filter is checked per request
+    check_requests: bool,
+}
+
+/// A response built by a worker thread, to be written by the reactor.
+struct Completion {
+    token: Token,
+    bytes: Vec<u8>,
+    keep_alive: bool,
+}
+
+/// Event-driven connection handling: a single reactor thread accepts the
+/// connections and reads and writes them non-blocking (epoll / kqueue, via mio),
+/// only complete requests are handed to the worker threads. Idle keep-alive
+/// connections therefore do not occupy a worker.
+pub(crate) struct EventLoop {
+    pub hosts: SharedHosts,
+    pub limits: ConnectionLimits,
+    pub metrics: Arc<HttpMetrics>,
+    pub lifecycle: Lifecycle,
+    pub ip_filter: Option<SharedIpFilter>,
+}
+
+impl EventLoop {
+    /// Runs the reactor until the server is stopped and all connections with
+    /// requests in progress are answered.
+    pub fn run(self, listener: std::net::TcpListener, pool: &ThreadPool) -> io::Result<()> {
+        listener.set_nonblocking(true)?;
+        let mut listener = Some(TcpListener::from_std(listener));
+        let mut poll = Poll::new()?;
+        poll.registry()
+            .register(listener.as_mut().unwrap(), LISTENER, Interest::READABLE)?;
+        let waker = Arc::new(Waker::new(poll.registry(), WAKER)?);
+        let (sender, receiver): (Sender<Completion>, Receiver<Completion>) = mpsc::channel();
+
+        let mut connections: HashMap<Token, Connection> = HashMap::new();
+        let mut next_token = 2;
+        let mut events = Events::with_capacity(1024);
+
+        loop {
+            if let Err(e) = poll.poll(&mut events, Some(TICK)) {
+                if e.kind() != ErrorKind::Interrupted {
+                    return Err(e);
+                }
+            }
+
+            for event in events.iter() {
+                match event.token() {
+                    LISTENER => {
+                        if let Some(listener) = listener.as_mut() {
+                            self.accept(listener, &poll, &mut connections, &mut next_token);
+                        }
+                    }
+                    WAKER => (),
+                    token => self.handle_io(token, &mut connections, pool, &sender, &waker),
+                }
+            }
+
+            // responses of the worker threads:
+            while let Ok(completion) = receiver.try_recv() {
+                if let Some(conn) = connections.get_mut(&completion.token) {
+                    conn.write_buf = completion.bytes;
+                    conn.written = 0;
+                    conn.keep_alive = completion.keep_alive;
+                    conn.state = State::Writing;
+                    conn.deadline = Instant::now() + self.limits.write_timeout;
+                    self.handle_io(completion.token, &mut connections, pool, &sender, &waker);
+                }
+            }
+
+            self.expire(&mut connections);
+
+            if self.lifecycle.is_stopped() {
+                if let Some(mut l) = listener.take() {
+                    let _ = poll.registry().deregister(&mut l);
+                    log(
+                        "Server stopped accepting connections, waiting for running requests",
+                        LogSeverity::INFO,
+                    );
+                }
+                // idle connections are closed, the others after their response:
+                let idle: Vec<Token> = connections
+                    .iter()
+                    .filter(|(_, c)| matches!(c.state, State::Reading))
+                    .map(|(t, _)| *t)
+                    .collect();
+                for token in idle {
+                    self.close(&mut connections, token);
+                }
+                if connections.is_empty() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    fn accept(
+        &self,
+        listener: &mut TcpListener,
+        poll: &Poll,
+        connections: &mut HashMap<Token, Connection>,
+        next_token: &mut usize,
+    ) {
+        loop {
+            let (mut stream, peer) = match listener.accept() {
+                Ok(s) => s,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return,
+                Err(e) => {
+                    log(
+                        &format!("Cannot accept connection: {}", e),
+                        LogSeverity::ERROR,
+                    );
+                    return;
+                }
+            };
+
+            let mut check_requests = false;
+            let mut forbidden = false;
+            if let Some(filter) = &self.ip_filter {
+                let filter = filter.read().unwrap();
+                if filter.is_trusted_proxy(peer.ip()) {
+                    check_requests = true;
+                } else if !filter.is_allowed(peer.ip()) {
+                    log(
+                        &format!("Connection denied by the IP filter: {}", peer.ip()),
+                        LogSeverity::WARNING,
+                    );
+                    if filter.get_action() == FilterAction::Drop {
+                        continue;
+                    }
+                    forbidden = true;
+                }
+            }
+
+            let token = Token(*next_token);
+            *next_token += 1;
+            if let Err(e) = poll.registry().register(
+                &mut stream,
+                token,
+                Interest::READABLE | Interest::WRITABLE,
+            ) {
+                log(&e.to_string(), LogSeverity::ERROR);
+                continue;
+            }
+            self.metrics.connection_opened();
+            connections.insert(
+                token,
+                Connection {
+                    stream,
+                    peer_addr: Some(peer),
+                    state: State::Reading,
+                    read_buf: Vec::new(),
+                    parser: RequestParser::new(self.limits.max_body_size),
+                    write_buf: Vec::new(),
+                    written: 0,
+                    deadline: Instant::now() + self.limits.header_timeout,
+                    head_complete: false,
+                    keep_alive: false,
+                    served: 0,
+                    check_requests,
+                },
+            );
+            // answered and closed like any connection, once it is writable:
+            if forbidden {
+                let conn = connections.get_mut(&token).unwrap();
+                let response = Problem::new(HTTPStatusCode::ClientError(403)).into();
+                self.respond_directly(conn, response);
+            }
+        }
+    }
+
+    /// Advances a connection as far as possible without blocking: reads and
+    /// dispatches complete requests, writes responses.
+    fn handle_io(
+        &self,
+        token: Token,
+        connections: &mut HashMap<Token, Connection>,
+        pool: &ThreadPool,
+        sender: &Sender<Completion>,
+        waker: &Arc<Waker>,
+    ) {
+        let conn = match connections.get_mut(&token) {
+            Some(c) => c,
+            None => return,
+        };
+
+        loop {
+            match conn.state {
+                State::Processing => return,
+                State::Writing => {
+                    match write_pending(conn) {
+                        Ok(true) => (),
+                        Ok(false) => return,
+                        Err(_) => return self.close(connections, token),
+                    }
+                    if !conn.keep_alive {
+                        let _ = conn.stream.flush();
+                        if conn.stream.shutdown(Shutdown::Write).is_err() {
+                            return self.close(connections, token);
+                        }
+                        conn.state = State::Closing;
+                        conn.deadline = Instant::now() + LINGER;
+                        continue;
+                    }
+                    // wait for the next request on the kept-alive connection:
+                    conn.served += 1;
+                    conn.state = State::Reading;
+                    conn.deadline = Instant::now() + self.limits.header_timeout;
+                }
+                State::Closing => {
+                    conn.read_buf.clear();
+                    match read_available(conn) {
+                        Ok(Input::Pending) => (),
+                        Ok(Input::Drained) => return,
+                        _ => return self.close(connections, token),
+                    }
+                }
+                State::Reading => {
+                    let input = match read_available(conn) {
+                        Ok(input) => input,
+                        Err(_) => return self.close(connections, token),
+                    };
+                    match self.take_request(conn) {
+                        Ok(Some(request)) => {
+                            self.dispatch(token, conn, request, pool, sender, waker)
+                        }
+                        Ok(None) if input == Input::Closed => {
+                            return self.close(connections, token)
+                        }
+                        // more data is waiting in the socket:
+                        Ok(None) if input == Input::Pending => (),
+                        Ok(None) => return,
+                        Err(status) => {
+                            self.metrics.observe_request(
+                                HttpVerb::UNKNOWN.as_str(),
+                                "unmatched",
+                                status.code(),
+                                Duration::ZERO,
+                                0,
+                                0,
+                            );
+                            let response = Response::from(Problem::new(status));
+                            self.respond_directly(conn, response);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Takes the next complete request from the read buffer, if any.
+    fn take_request(&self, conn: &mut Connection) -> Result<Option<Request>, HTTPStatusCode> {
+        let parsed = match conn.parser.parse(&conn.read_buf)? {
+            Some(parsed) => parsed,
+            None => {
+                // the head is complete, the body has its own timeout from now on:
+                if conn.parser.head_complete() && !conn.head_complete {
+                    conn.head_complete = true;
+                    conn.deadline = Instant::now() + self.limits.body_timeout;
+                }
+                return Ok(None);
+            }
+        };
+        conn.read_buf.drain(..parsed.len);
+        conn.head_complete = false;
+        conn.keep_alive = parsed.head.keep_alive();
+        Ok(Some(parsed.into_request(conn.peer_addr)))
+    }
+
+    /// Hands a complete request to a worker thread.
+    fn dispatch(
+        &self,
+        token: Token,
+        conn: &mut Connection,
+        request: Request,
+        pool: &ThreadPool,
+        sender: &Sender<Completion>,
+        waker: &Arc<Waker>,
+    ) {
+        if conn.check_requests {
+            if let Some(filter) = &self.ip_filter {
+                let filter = filter.read().unwrap();
+                if let Some(client) = filter.denied_client(&request) {
+                    log(
+                        &format!("Connection denied by the IP filter: {}", client),
+                        LogSeverity::WARNING,
+                    );
+                    if filter.get_action() == FilterAction::Drop {
+                        conn.read_buf.clear();
+                        conn.keep_alive = false;
+                        conn.write_buf.clear();
+                        conn.written = 0;
+                        conn.state = State::Writing;
+                        return;
+                    }
+                    drop(filter);
+                    let response = Problem::new(HTTPStatusCode::ClientError(403)).into();
+                    return self.respond_directly(conn, response);
+                }
+            }
+        }
+        if pool.queued_jobs() >= self.limits.max_pending_connections {
+            log(
+                "Too many pending requests, rejecting the request",
+                LogSeverity::WARNING,
+            );
+            let response = Response::from(Problem::new(HTTPStatusCode::ServerError(503)))
+                .with_header("Retry-After", "1");
+            return self.respond_directly(conn, response);
+        }
+
+        conn.state = State::Processing;
+        let keep_alive = conn.keep_alive && !self.lifecycle.is_draining();
+        let hosts = self.hosts.current();
+        let metrics = Arc::clone(&self.metrics);
+        let sender = sender.clone();
+        let waker = Arc::clone(waker);
+        pool.execute(move |_| {
+            let start = Instant::now();
+            let router = hosts.select(request.host().as_deref());
+            let mut response = request.respond(router);
+            response.headers.set(
+                "Connection",
+                if keep_alive { "keep-alive" } else { "close" },
+            );
+            let bytes = response.to_bytes(&request.method);
+            metrics.observe_request(
+                request.method.as_str(),
+                &router.route_label(&request.method, &request.url),
+                response.status.code(),
+                start.elapsed(),
+                request.bytes_read(),
+                bytes.len() as u64,
+            );
+            if sender
+                .send(Completion {
+                    token,
+                    bytes,
+                    keep_alive,
+                })
+                .is_ok()
+            {
+                let _ = waker.wake();
+            }
+        });
+    }
+
+    /// Answers from the reactor itself (errors, rejections) and closes the connection.
+    fn respond_directly(&self, conn: &mut Connection, response: Response) {
+        let response = response.with_header("Connection", "close");
+        conn.read_buf.clear();
+        conn.parser = RequestParser::new(self.limits.max_body_size);
+        conn.keep_alive = false;
+        conn.write_buf = response.to_bytes(&HttpVerb::GET);
+        conn.written = 0;
+        conn.state = State::Writing;
+        conn.deadline = Instant::now() + self.limits.write_timeout;
+    }
+
+    /// Closes connections whose current state timed out: a started request is
+    /// answered with 408, idle keep-alive connections are closed silently.
+    fn expire(&self, connections: &mut HashMap<Token, Connection>) {
+        let now = Instant::now();
+        let expired: Vec<Token> = connections
+            .iter()
+            .filter(|(_, c)| c.deadline <= now && !matches!(c.state, State::Processing))
+            .map(|(t, _)| *t)
+            .collect();
+        for token in expired {
+            let conn = connections.get_mut(&token).unwrap();
+            let idle = conn.served > 0 && conn.read_buf.is_empty();
+            if matches!(conn.state, State::Reading) && !idle {
+                let response = Response::from(Problem::new(HTTPStatusCode::ClientError(408)))
+                    .with_header("Connection", "close");
+                // best effort, the client is slow anyway:
+                let bytes_written = conn
+                    .stream
+                    .write(&response.to_bytes(&HttpVerb::GET))
+                    .unwrap_or(0);
+                self.metrics.observe_request(
+                    HttpVerb::UNKNOWN.as_str(),
+                    "unmatched",
+                    408,
+                    Duration::ZERO,
+                    conn.read_buf.len() as u64,
+                    bytes_written as u64,
+                );
+            }
+            self.close(connections, token);
+        }
+    }
+
+    fn close(&self, connections: &mut HashMap<Token, Connection>, token: Token) {
+        if connections.remove(&token).is_some() {
+            self.metrics.connection_closed();
+        }
+    }
+}
+
+/// What [read_available] left in the socket.
+#[derive(PartialEq)]
+enum Input {
+    /// everything available is read
+    Drained,
+    /// a chunk is read, more data may be waiting: as the socket is
+    /// edge-triggered, it must be read again without waiting for an event
+    Pending,
+    /// the client closed its side of the connection
+    Closed,
+}
+
+/// Reads the data available from the socket, up to [READ_CHUNK] bytes, so
+/// that the parser can reject a too large request before all of it is read.
+fn read_available(conn: &mut Connection) -> io::Result<Input> {
+    let mut buf = [0u8; 8192];
+    let mut read = 0;
+    loop {
+        match conn.stream.read(&mut buf) {
+            Ok(0) => return Ok(Input::Closed),
+            Ok(n) => {
+                conn.read_buf.extend_from_slice(&buf[..n]);
+                read += n;
+                if read >= READ_CHUNK {
+                    return Ok(Input::Pending);
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(Input::Drained),
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Writes as much of the pending response as possible. Returns true when done.
+fn write_pending(conn: &mut Connection) -> io::Result<bool> {
+    while conn.written < conn.write_buf.len() {
+        match conn.stream.write(&conn.write_buf[conn.written..]) {
+            Ok(0) => return Err(io::Error::from(ErrorKind::WriteZero)),
+            Ok(n) => conn.written += n,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(false),
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(true)
+}
+
+fn log(msg: &str, severity: LogSeverity) {
+    eprintln!("{}: {}\n", severity, msg);
+}
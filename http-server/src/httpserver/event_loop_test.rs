@@ -0,0 +1,321 @@
+#[cfg(test)]
+mod event_loop_test {
+    use super::super::event_loop::EventLoop;
+    use super::super::lifecycle::Lifecycle;
+    use super::super::reload::SharedHosts;
+    use super::super::{
+        ConnectionLimits, HTTPStatusCode, HttpMetrics, HttpVerb, IpFilter, Request, Response,
+        Router, SharedIpFilter, ShutdownHandle, VirtualHosts,
+    };
+    use crate::utils::threadpool::{ThreadPool, ThreadPoolStatus};
+    use std::io::{self, BufRead, BufReader, Read, Write};
+    use std::net::{SocketAddr, TcpListener, TcpStream};
+    use std::sync::mpsc::{self, Receiver, Sender};
+    use std::sync::{Arc, Mutex, RwLock};
+    use std::thread::{self, JoinHandle};
+    use std::time::{Duration, Instant};
+
+    const GET: &[u8] = b"GET / HTTP/1.1\r\nHost: a\r\n\r\n";
+
+    /// An event loop on a free port, with a pool of `threads` workers.
+    struct TestLoop {
+        addr: SocketAddr,
+        pool: ThreadPoolStatus,
+        shutdown: ShutdownHandle,
+        thread: Option<JoinHandle<io::Result<()>>>,
+    }
+
+    impl TestLoop {
+        fn start(
+            router: Router,
+            threads: usize,
+            limits: ConnectionLimits,
+            ip_filter: Option<SharedIpFilter>,
+        ) -> TestLoop {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = listener.local_addr().unwrap();
+            let lifecycle = Lifecycle::new();
+            let shutdown = lifecycle.shutdown_handle();
+            let event_loop = EventLoop {
+                hosts: SharedHosts::new(VirtualHosts::new(router)),
+                limits,
+                metrics: Arc::new(HttpMetrics::new()),
+                lifecycle,
+                ip_filter,
+            };
+            let mut pool = ThreadPool::builder(threads);
+            let status = pool.status();
+            let thread = thread::spawn(move || {
+                let result = event_loop.run(listener, &pool);
+                pool.shutdown();
+                result
+            });
+            TestLoop {
+                addr,
+                pool: status,
+                shutdown,
+                thread: Some(thread),
+            }
+        }
+
+        fn connect(&self) -> BufReader<TcpStream> {
+            let stream = TcpStream::connect(self.addr).unwrap();
+            stream
+                .set_read_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+            BufReader::new(stream)
+        }
+
+        /// Stops the event loop, returns the result of its run.
+        fn stop(mut self) -> io::Result<()> {
+            self.shutdown.shutdown(Duration::ZERO);
+            self.thread.take().unwrap().join().unwrap()
+        }
+    }
+
+    impl Drop for TestLoop {
+        fn drop(&mut self) {
+            self.shutdown.shutdown(Duration::ZERO);
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+
+    fn router() -> Router {
+        let mut router = Router::new();
+        router.route(HttpVerb::GET, "/*", |request: &Request| {
+            Response::text(HTTPStatusCode::Success(200), &request.url)
+        });
+        router
+    }
+
+    /// A route that blocks until released, to keep a worker busy (registered
+    /// before the catch-all route).
+    struct Gate {
+        started: Receiver<()>,
+        release: Sender<()>,
+    }
+
+    fn blocking_route(router: &mut Router) -> Gate {
+        let (started_tx, started) = mpsc::channel();
+        let (release, release_rx) = mpsc::channel();
+        let started_tx = Mutex::new(started_tx);
+        let release_rx = Mutex::new(release_rx);
+        router.route(HttpVerb::GET, "/block", move |_| {
+            started_tx.lock().unwrap().send(()).unwrap();
+            release_rx.lock().unwrap().recv().unwrap();
+            Response::text(HTTPStatusCode::Success(200), "released")
+        });
+        Gate { started, release }
+    }
+
+    /// Reads one response: the status, the headers (lower-cased) and the body.
+    fn read_response(reader: &mut BufReader<TcpStream>) -> (usize, String, String) {
+        let mut status_line = String::new();
+        reader.read_line(&mut status_line).unwrap();
+        let status = status_line.split(' ').nth(1).unwrap().parse().unwrap();
+        let mut headers = String::new();
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line == "\r\n" {
+                break;
+            }
+            let line = line.to_lowercase();
+            if let Some(length) = line.strip_prefix("content-length:") {
+                content_length = length.trim().parse().unwrap();
+            }
+            headers += &line;
+        }
+        let mut body = vec![0u8; content_length];
+        reader.read_exact(&mut body).unwrap();
+        (status, headers, String::from_utf8(body).unwrap())
+    }
+
+    /// Whether the server closed the connection, without sending anything more.
+    fn is_closed(reader: &mut BufReader<TcpStream>) -> bool {
+        let mut rest = Vec::new();
+        matches!(reader.read_to_end(&mut rest), Ok(0))
+    }
+
+    #[test]
+    fn test_idle_connections_do_not_block_workers() {
+        let server = TestLoop::start(router(), 1, ConnectionLimits::default(), None);
+        // more idle keep-alive connections than workers:
+        let mut idle: Vec<_> = (0..6).map(|_| server.connect()).collect();
+        for conn in idle.iter_mut() {
+            conn.get_mut().write_all(GET).unwrap();
+            let (status, headers, _) = read_response(conn);
+            assert_eq!(status, 200);
+            assert!(headers.contains("connection: keep-alive"));
+        }
+
+        let mut conn = server.connect();
+        conn.get_mut().write_all(GET).unwrap();
+        assert_eq!(read_response(&mut conn).0, 200);
+        // the idle connections are still served:
+        idle[0].get_mut().write_all(GET).unwrap();
+        assert_eq!(read_response(&mut idle[0]).0, 200);
+    }
+
+    #[test]
+    fn test_pipelining() {
+        let server = TestLoop::start(router(), 2, ConnectionLimits::default(), None);
+        let mut conn = server.connect();
+        conn.get_mut()
+            .write_all(
+                b"GET /a HTTP/1.1\r\nHost: a\r\n\r\n\
+                  GET /b HTTP/1.1\r\nHost: a\r\n\r\n\
+                  GET /c HTTP/1.1\r\nHost: a\r\nConnection: close\r\n\r\n",
+            )
+            .unwrap();
+        // answered in order:
+        for path in ["/a", "/b", "/c"] {
+            assert_eq!(read_response(&mut conn).2, path);
+        }
+        assert!(is_closed(&mut conn));
+    }
+
+    #[test]
+    fn test_timeouts() {
+        let limits = ConnectionLimits {
+            header_timeout: Duration::from_millis(300),
+            ..ConnectionLimits::default()
+        };
+        let server = TestLoop::start(router(), 1, limits, None);
+
+        // an incomplete request is answered with 408:
+        let mut slow = server.connect();
+        slow.get_mut().write_all(b"GET / HTTP/1.1\r\n").unwrap();
+        // an idle keep-alive connection is closed silently:
+        let mut idle = server.connect();
+        idle.get_mut().write_all(GET).unwrap();
+        assert_eq!(read_response(&mut idle).0, 200);
+
+        let start = Instant::now();
+        let (status, headers, _) = read_response(&mut slow);
+        assert_eq!(status, 408);
+        assert!(headers.contains("connection: close"));
+        assert!(is_closed(&mut slow));
+        assert!(is_closed(&mut idle));
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_trusted_proxy() {
+        let filter = IpFilter::parse("trusted-proxy 127.0.0.1\ndeny 10.0.0.0/8\naction 403");
+        let filter = Arc::new(RwLock::new(filter.unwrap()));
+        let limits = ConnectionLimits::default();
+        let server = TestLoop::start(router(), 1, limits, Some(Arc::clone(&filter)));
+        let forwarded_for = |client: &str| {
+            format!(
+                "GET / HTTP/1.1\r\nHost: a\r\nX-Forwarded-For: {}\r\n\r\n",
+                client
+            )
+        };
+
+        // the proxy's connection is accepted, the clients are checked per request:
+        let mut conn = server.connect();
+        conn.get_mut()
+            .write_all(forwarded_for("192.0.2.1").as_bytes())
+            .unwrap();
+        assert_eq!(read_response(&mut conn).0, 200);
+        conn.get_mut()
+            .write_all(forwarded_for("10.1.1.1").as_bytes())
+            .unwrap();
+        assert_eq!(read_response(&mut conn).0, 403);
+        assert!(is_closed(&mut conn));
+
+        *filter.write().unwrap() =
+            IpFilter::parse("trusted-proxy 127.0.0.1\ndeny 10.0.0.0/8\naction drop").unwrap();
+        let mut conn = server.connect();
+        conn.get_mut()
+            .write_all(forwarded_for("10.1.1.1").as_bytes())
+            .unwrap();
+        assert!(is_closed(&mut conn));
+    }
+
+    #[test]
+    fn test_forbidden_with_unread_request() {
+        let filter = IpFilter::parse("deny 127.0.0.1\naction 403").unwrap();
+        let filter = Some(Arc::new(RwLock::new(filter)));
+        let server = TestLoop::start(router(), 1, ConnectionLimits::default(), filter);
+        let mut conn = server.connect();
+        let mut writer = conn.get_ref().try_clone().unwrap();
+        // the request is still arriving when the connection is rejected:
+        let upload = thread::spawn(move || {
+            let _ =
+                writer.write_all(b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 262144\r\n\r\n");
+            let _ = writer.write_all(&[b'x'; 262144]);
+        });
+        assert_eq!(read_response(&mut conn).0, 403);
+        upload.join().unwrap();
+        assert!(is_closed(&mut conn));
+    }
+
+    #[test]
+    fn test_max_pending_connections() {
+        let mut router = Router::new();
+        let gate = blocking_route(&mut router);
+        router.merge(self::router());
+        let limits = ConnectionLimits {
+            max_pending_connections: 1,
+            ..ConnectionLimits::default()
+        };
+        let server = TestLoop::start(router, 1, limits, None);
+        let block = b"GET /block HTTP/1.1\r\nHost: a\r\n\r\n";
+
+        let mut running = server.connect();
+        running.get_mut().write_all(block).unwrap();
+        gate.started.recv().unwrap();
+        let mut queued = server.connect();
+        queued.get_mut().write_all(block).unwrap();
+        let start = Instant::now();
+        while server.pool.queued_jobs() == 0 {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        let mut rejected = server.connect();
+        rejected.get_mut().write_all(GET).unwrap();
+        let (status, headers, _) = read_response(&mut rejected);
+        assert_eq!(status, 503);
+        assert!(headers.contains("retry-after: 1"));
+        assert!(is_closed(&mut rejected));
+
+        for conn in [&mut running, &mut queued] {
+            gate.release.send(()).unwrap();
+            assert_eq!(read_response(conn).2, "released");
+        }
+    }
+
+    #[test]
+    fn test_drain_on_stop() {
+        let mut router = Router::new();
+        let gate = blocking_route(&mut router);
+        router.merge(self::router());
+        let server = TestLoop::start(router, 1, ConnectionLimits::default(), None);
+        let mut idle = server.connect();
+        idle.get_mut().write_all(GET).unwrap();
+        assert_eq!(read_response(&mut idle).0, 200);
+        let mut busy = server.connect();
+        busy.get_mut()
+            .write_all(b"GET /block HTTP/1.1\r\nHost: a\r\n\r\n")
+            .unwrap();
+        gate.started.recv().unwrap();
+
+        let addr = server.addr;
+        let stopped = thread::spawn(move || server.stop());
+        // idle connections are closed, no new ones accepted:
+        assert!(is_closed(&mut idle));
+        assert!(TcpStream::connect(addr).is_err());
+
+        // the request in progress is still answered:
+        gate.release.send(()).unwrap();
+        assert_eq!(read_response(&mut busy).2, "released");
+        assert!(is_closed(&mut busy));
+        stopped.join().unwrap().unwrap();
+    }
+}
@@ -1,30 +1,76 @@
-use std::collections::HashMap;
-
-#[derive(Debug)]
+/// Case-insensitive collection of HTTP headers. The header names are stored
+/// in their original spelling (used when writing a response), while lookups
+/// ignore the case.
+#[derive(Debug, Default, Clone)]
 pub struct HeaderMap {
-	header_map: HashMap<String, String>
+    headers: Vec<(String, String)>,
 }
 
 impl HeaderMap {
-	pub fn builder(header_lines: &Vec<String>) -> HeaderMap {
-        let mut header_map = HeaderMap {
-			header_map: HashMap::new()
-		};
+    pub fn new() -> HeaderMap {
+        HeaderMap {
+            headers: Vec::new(),
+        }
+    }
+
+    pub fn builder(header_lines: &[String]) -> HeaderMap {
+        let mut header_map = HeaderMap::new();
 
         for line in header_lines {
             let (left, right) = match line.split_once(':') {
                 Some(res) => (res.0.trim(), res.1.trim()),
                 None => continue,
             };
-			header_map.header_map.insert(left.to_lowercase(), String::from(right));
+            // repeated headers are combined into a comma-separated list, see RFC 9110, 5.3
+            header_map.append(left, right);
         }
         header_map
-	}
-
-	pub fn get(&self, key: &str) -> Option<String> {
-		match self.header_map.get(&key.to_lowercase()) {
-			Some(val) => Some(String::from(val)),
-			None => None
-		}
-	}
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.position(key).map(|idx| self.headers[idx].1.clone())
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.position(key).is_some()
+    }
+
+    /// Sets the header, replacing an already existing value.
+    pub fn set(&mut self, key: &str, value: &str) {
+        match self.position(key) {
+            Some(idx) => self.headers[idx].1 = String::from(value),
+            None => self
+                .headers
+                .push((String::from(key), String::from(value))),
+        }
+    }
+
+    /// Adds a value to the header, appending it to an already existing value
+    /// as a comma-separated list.
+    pub fn append(&mut self, key: &str, value: &str) {
+        match self.position(key) {
+            Some(idx) => {
+                let existing = &mut self.headers[idx].1;
+                existing.push_str(", ");
+                existing.push_str(value);
+            }
+            None => self
+                .headers
+                .push((String::from(key), String::from(value))),
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        self.position(key).map(|idx| self.headers.remove(idx).1)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.headers.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    fn position(&self, key: &str) -> Option<usize> {
+        self.headers
+            .iter()
+            .position(|(k, _)| k.eq_ignore_ascii_case(key))
+    }
 }
@@ -0,0 +1,124 @@
+use std::sync::{Arc, RwLock};
+
+use crate::httpserver::lifecycle::Lifecycle;
+use crate::httpserver::{HTTPStatusCode, Response};
+use crate::utils::json;
+use crate::utils::threadpool::ThreadPoolStatus;
+
+/// A named health check: returns Ok if healthy, or an error message.
+pub type HealthCheck = dyn Fn() -> Result<(), String> + Send + Sync;
+
+type Checks = RwLock<Vec<(String, Arc<HealthCheck>)>>;
+
+/// Liveness and readiness checks, answered on the routes registered with
+/// `HttpServer::health_routes`.
+///
+/// Besides the registered checks, readiness includes two built-in checks:
+/// `shutdown` (down while the server drains) and `threadpool` (down while more
+/// jobs are queued than the threshold).
+pub struct Health {
+    liveness: Checks,
+    readiness: Checks,
+    lifecycle: Lifecycle,
+    pool: ThreadPoolStatus,
+    max_queued_jobs: RwLock<usize>,
+}
+
+impl Health {
+    pub fn new(lifecycle: Lifecycle, pool: ThreadPoolStatus, max_queued_jobs: usize) -> Health {
+        Health {
+            liveness: RwLock::new(Vec::new()),
+            readiness: RwLock::new(Vec::new()),
+            lifecycle,
+            pool,
+            max_queued_jobs: RwLock::new(max_queued_jobs),
+        }
+    }
+
+    pub fn add_liveness_check(&self, name: &str, check: Arc<HealthCheck>) {
+        self.liveness.write().unwrap().push((String::from(name), check));
+    }
+
+    pub fn add_readiness_check(&self, name: &str, check: Arc<HealthCheck>) {
+        self.readiness.write().unwrap().push((String::from(name), check));
+    }
+
+    /// Readiness fails while more than this number of jobs wait for a worker thread.
+    pub fn set_max_queued_jobs(&self, max_queued_jobs: usize) {
+        *self.max_queued_jobs.write().unwrap() = max_queued_jobs;
+    }
+
+    pub fn liveness(&self) -> Response {
+        let results = run_checks(&self.liveness.read().unwrap());
+        report(results)
+    }
+
+    pub fn readiness(&self) -> Response {
+        let mut results = Vec::new();
+        results.push((
+            String::from("shutdown"),
+            match self.lifecycle.is_draining() {
+                true => Err(String::from("server is shutting down")),
+                false => Ok(()),
+            },
+        ));
+
+        let max_queued_jobs = *self.max_queued_jobs.read().unwrap();
+        let queued_jobs = self.pool.queued_jobs();
+        results.push((
+            String::from("threadpool"),
+            match queued_jobs > max_queued_jobs {
+                true => Err(format!(
+                    "{} queued jobs (max {})",
+                    queued_jobs, max_queued_jobs
+                )),
+                false => Ok(()),
+            },
+        ));
+
+        results.append(&mut run_checks(&self.readiness.read().unwrap()));
+        report(results)
+    }
+}
+
+fn run_checks(checks: &[(String, Arc<HealthCheck>)]) -> Vec<(String, Result<(), String>)> {
+    checks
+        .iter()
+        .map(|(name, check)| (name.clone(), check()))
+        .collect()
+}
+
+/// Builds the JSON report, e.g.:
+/// `{"status":"down","checks":{"shutdown":{"status":"down","message":"..."}}}`,
+/// with status 200 if all checks are up, else 503.
+fn report(results: Vec<(String, Result<(), String>)>) -> Response {
+    let all_up = results.iter().all(|(_, result)| result.is_ok());
+    let checks: Vec<String> = results
+        .iter()
+        .map(|(name, result)| match result {
+            Ok(()) => format!("{}:{{\"status\":\"up\"}}", json::quote(name)),
+            Err(msg) => format!(
+                "{}:{{\"status\":\"down\",\"message\":{}}}",
+                json::quote(name),
+                json::quote(msg)
+            ),
+        })
+        .collect();
+    let body = format!(
+        "{{\"status\":\"{}\",\"checks\":{{{}}}}}",
+        match all_up {
+            true => "up",
+            false => "down",
+        },
+        checks.join(",")
+    );
+
+    let status = match all_up {
+        true => HTTPStatusCode::Success(200),
+        false => HTTPStatusCode::ServerError(503),
+    };
+    Response::new(status)
+        .with_header("Content-Type", "application/json")
+        .with_header("Cache-Control", "no-store")
+        .with_body(body)
+}
@@ -0,0 +1,51 @@
+#[cfg(test)]
+mod health_test {
+    use super::super::lifecycle::Lifecycle;
+    use super::super::{HTTPStatusCode, Health};
+    use crate::utils::threadpool::ThreadPool;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn body(health_response: &super::super::Response) -> String {
+        String::from_utf8(health_response.body.clone()).unwrap()
+    }
+
+    #[test]
+    fn test_liveness_checks() {
+        let pool = ThreadPool::builder(1);
+        let health = Health::new(Lifecycle::new(), pool.status(), 10);
+        health.add_liveness_check("db", Arc::new(|| Ok(())));
+
+        let response = health.liveness();
+        assert_eq!(response.status, HTTPStatusCode::Success(200));
+        assert_eq!(body(&response), r#"{"status":"up","checks":{"db":{"status":"up"}}}"#);
+
+        health.add_liveness_check("disk", Arc::new(|| Err(String::from("disk \"/\" full"))));
+        let response = health.liveness();
+        assert_eq!(response.status, HTTPStatusCode::ServerError(503));
+        assert_eq!(
+            body(&response),
+            r#"{"status":"down","checks":{"db":{"status":"up"},"disk":{"status":"down","message":"disk \"/\" full"}}}"#
+        );
+    }
+
+    #[test]
+    fn test_readiness_while_draining() {
+        let pool = ThreadPool::builder(1);
+        let lifecycle = Lifecycle::new();
+        let health = Health::new(lifecycle.clone(), pool.status(), 10);
+        health.add_readiness_check("cache", Arc::new(|| Ok(())));
+
+        let response = health.readiness();
+        assert_eq!(response.status, HTTPStatusCode::Success(200));
+        assert_eq!(
+            body(&response),
+            r#"{"status":"up","checks":{"shutdown":{"status":"up"},"threadpool":{"status":"up"},"cache":{"status":"up"}}}"#
+        );
+
+        lifecycle.shutdown_handle().shutdown(Duration::from_secs(60));
+        let response = health.readiness();
+        assert_eq!(response.status, HTTPStatusCode::ServerError(503));
+        assert!(body(&response).contains(r#""shutdown":{"status":"down""#));
+    }
+}
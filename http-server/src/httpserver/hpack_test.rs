@@ -0,0 +1,209 @@
+#[cfg(test)]
+mod hpack_test {
+    use super::super::http2::hpack::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        let s: String = s.split_whitespace().collect();
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn fields(list: &[(&str, &str)]) -> Vec<(String, String)> {
+        list.iter()
+            .map(|(n, v)| (String::from(*n), String::from(*v)))
+            .collect()
+    }
+
+    #[test]
+    fn test_integers() {
+        // RFC 7541, C.1
+        let mut out = Vec::new();
+        encode_int(&mut out, 0x00, 5, 10);
+        assert_eq!(out, [0x0a]);
+        out.clear();
+        encode_int(&mut out, 0x00, 5, 1337);
+        assert_eq!(out, [0x1f, 0x9a, 0x0a]);
+        out.clear();
+        encode_int(&mut out, 0x00, 8, 42);
+        assert_eq!(out, [0x2a]);
+
+        let mut pos = 0;
+        assert_eq!(decode_int(&[0xff, 0x9a, 0x0a], &mut pos, 5), Ok(1337));
+        assert_eq!(pos, 3);
+        let mut pos = 0;
+        assert!(decode_int(&[0x1f, 0x9a], &mut pos, 5).is_err());
+        let mut pos = 0;
+        assert!(decode_int(&[0x1f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff], &mut pos, 5).is_err());
+    }
+
+    #[test]
+    fn test_decode_requests() {
+        // RFC 7541, C.3: requests without Huffman coding, sharing the dynamic table
+        let mut decoder = Decoder::new(DEFAULT_TABLE_SIZE, usize::MAX);
+        let first = hex("8286 8441 0f77 7777 2e65 7861 6d70 6c65 2e63 6f6d");
+        assert_eq!(
+            decoder.decode(&first).unwrap(),
+            fields(&[
+                (":method", "GET"),
+                (":scheme", "http"),
+                (":path", "/"),
+                (":authority", "www.example.com"),
+            ])
+        );
+        let second = hex("8286 84be 5808 6e6f 2d63 6163 6865");
+        assert_eq!(
+            decoder.decode(&second).unwrap(),
+            fields(&[
+                (":method", "GET"),
+                (":scheme", "http"),
+                (":path", "/"),
+                (":authority", "www.example.com"),
+                ("cache-control", "no-cache"),
+            ])
+        );
+        let third = hex("8287 85bf 400a 6375 7374 6f6d 2d6b 6579 0c63 7573 746f 6d2d 7661 6c75 65");
+        assert_eq!(
+            decoder.decode(&third).unwrap(),
+            fields(&[
+                (":method", "GET"),
+                (":scheme", "https"),
+                (":path", "/index.html"),
+                (":authority", "www.example.com"),
+                ("custom-key", "custom-value"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_decode_huffman_requests() {
+        // RFC 7541, C.4: the same requests, Huffman coded
+        let mut decoder = Decoder::new(DEFAULT_TABLE_SIZE, usize::MAX);
+        let first = hex("8286 8441 8cf1 e3c2 e5f2 3a6b a0ab 90f4 ff");
+        assert_eq!(
+            decoder.decode(&first).unwrap()[3],
+            (String::from(":authority"), String::from("www.example.com"))
+        );
+        let second = hex("8286 84be 5886 a8eb 1064 9cbf");
+        assert_eq!(
+            decoder.decode(&second).unwrap()[4],
+            (String::from("cache-control"), String::from("no-cache"))
+        );
+        let third = hex("8287 85bf 4088 25a8 49e9 5ba9 7d7f 8925 a849 e95b b8e8 b4bf");
+        assert_eq!(
+            decoder.decode(&third).unwrap()[4],
+            (String::from("custom-key"), String::from("custom-value"))
+        );
+    }
+
+    #[test]
+    fn test_eviction() {
+        // RFC 7541, C.5: responses with a table of 256 bytes, evicting entries
+        let mut decoder = Decoder::new(256, usize::MAX);
+        let first = hex(
+            "4803 3330 3258 0770 7269 7661 7465 611d 4d6f 6e2c 2032 3120 4f63 7420 3230
+             3133 2032 303a 3133 3a32 3120 474d 546e 1768 7474 7073 3a2f 2f77 7777 2e65
+             7861 6d70 6c65 2e63 6f6d",
+        );
+        assert_eq!(
+            decoder.decode(&first).unwrap(),
+            fields(&[
+                (":status", "302"),
+                ("cache-control", "private"),
+                ("date", "Mon, 21 Oct 2013 20:13:21 GMT"),
+                ("location", "https://www.example.com"),
+            ])
+        );
+        // ":status: 307" evicts ":status: 302":
+        let second = hex("4803 3330 37c1 c0bf");
+        assert_eq!(
+            decoder.decode(&second).unwrap(),
+            fields(&[
+                (":status", "307"),
+                ("cache-control", "private"),
+                ("date", "Mon, 21 Oct 2013 20:13:21 GMT"),
+                ("location", "https://www.example.com"),
+            ])
+        );
+        let third = hex(
+            "88c1 611d 4d6f 6e2c 2032 3120 4f63 7420 3230 3133 2032 303a 3133 3a32 3220
+             474d 54c0 5a04 677a 6970 7738 666f 6f3d 4153 444a 4b48 514b 425a 584f 5157
+             454f 5049 5541 5851 5745 4f49 553b 206d 6178 2d61 6765 3d33 3630 303b 2076
+             6572 7369 6f6e 3d31",
+        );
+        assert_eq!(
+            decoder.decode(&third).unwrap(),
+            fields(&[
+                (":status", "200"),
+                ("cache-control", "private"),
+                ("date", "Mon, 21 Oct 2013 20:13:22 GMT"),
+                ("location", "https://www.example.com"),
+                ("content-encoding", "gzip"),
+                (
+                    "set-cookie",
+                    "foo=ASDJKHQKBZXOQWEOPIUAXQWEOIU; max-age=3600; version=1"
+                ),
+            ])
+        );
+        // the first response's entries are gone by now:
+        assert!(decoder.decode(&hex("c4")).is_err());
+    }
+
+    #[test]
+    fn test_table_size_update() {
+        let mut decoder = Decoder::new(DEFAULT_TABLE_SIZE, usize::MAX);
+        decoder
+            .decode(&hex("418c f1e3 c2e5 f23a 6ba0 ab90 f4ff"))
+            .unwrap();
+        assert!(decoder.decode(&hex("be")).is_ok());
+        // size 0 empties the table:
+        assert_eq!(decoder.decode(&hex("20 82")).unwrap().len(), 1);
+        assert!(decoder.decode(&hex("be")).is_err());
+        // above our limit, or after a header:
+        assert!(decoder.decode(&hex("3fe2 1f")).is_err());
+        assert!(decoder.decode(&hex("82 20")).is_err());
+    }
+
+    #[test]
+    fn test_invalid_blocks() {
+        let mut decoder = Decoder::new(DEFAULT_TABLE_SIZE, usize::MAX);
+        // index 0, index beyond the tables, truncated string:
+        assert!(decoder.decode(&hex("80")).is_err());
+        assert!(decoder.decode(&hex("ff00")).is_err());
+        assert!(decoder.decode(&hex("0003 6162")).is_err());
+        // Huffman coded "a" with zero bits instead of the EOS prefix as padding:
+        assert!(decoder.decode(&hex("0081 1f81 1f")).is_ok());
+        assert!(matches!(
+            decoder.decode(&hex("0081 1f81 18")),
+            Err(DecodeError::Compression(_))
+        ));
+    }
+
+    #[test]
+    fn test_list_too_large() {
+        let mut decoder = Decoder::new(DEFAULT_TABLE_SIZE, 150);
+        let block = hex("8286 8441 0f77 7777 2e65 7861 6d70 6c65 2e63 6f6d");
+        assert_eq!(decoder.decode(&block), Err(DecodeError::ListTooLarge));
+        // the block was still processed, the dynamic table is intact:
+        assert_eq!(
+            decoder.decode(&hex("be")).unwrap(),
+            fields(&[(":authority", "www.example.com")])
+        );
+    }
+
+    #[test]
+    fn test_encode() {
+        let headers = [
+            (":status", "200"),
+            (":status", "201"),
+            ("content-type", "text/plain; charset=utf-8"),
+            ("x-request-id", "A1b2~{}"),
+        ];
+        let block = Encoder::new().encode(headers);
+        // a full match of the static table is a single byte:
+        assert_eq!(block[0], 0x88);
+        let mut decoder = Decoder::new(DEFAULT_TABLE_SIZE, usize::MAX);
+        assert_eq!(decoder.decode(&block).unwrap(), fields(&headers));
+    }
+}
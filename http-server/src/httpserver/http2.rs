@@ -0,0 +1,116 @@
+//! HTTP/2 over cleartext TCP (h2c), started either with prior knowledge (the
+//! client sends the connection preface right away) or with an `Upgrade: h2c`
+//! HTTP/1.1 request. The streams are mapped to [Request]s and dispatched to
+//! the same routers as the HTTP/1.1 requests.
+
+use std::io::{self, Cursor, Read};
+
+use crate::httpserver::{
+    ConnectionLimits, HTTPStatusCode, HttpMetrics, HttpVerb, Request, Response, SharedIpFilter,
+    VirtualHosts,
+};
+use crate::utils::base64;
+
+mod connection;
+pub mod frame;
+pub mod hpack;
+mod huffman;
+
+pub use connection::Http2Connection;
+
+/// The client connection preface (RFC 9113, 3.4).
+pub const PREFACE: &[u8; 24] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+/// The part of the preface an HTTP/1 parser reads as a request (line and headers).
+const PREFACE_HTTP1_LEN: usize = 18;
+
+/// Whether the request is the start of the connection preface, as read by the
+/// HTTP/1 parser: the client speaks HTTP/2 with prior knowledge.
+pub fn is_preface(request: &Request) -> bool {
+    request.method == HttpVerb::Extension(String::from("PRI"))
+        && request.full_url == "*"
+        && request.version == "HTTP/2.0"
+        && request.headers.iter().next().is_none()
+        && request.body.is_none()
+}
+
+/// Checks if the request asks for an upgrade to h2c (RFC 7540, 3.2): returns
+/// the client's settings from the `HTTP2-Settings` header then.
+pub fn upgrade_settings(request: &Request) -> Option<Vec<(u16, u32)>> {
+    let has_token = |header: &str, token: &str| {
+        request
+            .headers
+            .get(header)
+            .is_some_and(|v| v.split(',').any(|t| t.trim().eq_ignore_ascii_case(token)))
+    };
+    if request.version != "HTTP/1.1"
+        || !has_token("upgrade", "h2c")
+        || !has_token("connection", "upgrade")
+        || !has_token("connection", "http2-settings")
+    {
+        return None;
+    }
+    // base64url without padding; a repeated header is joined to an invalid value:
+    let mut settings: String = request
+        .headers
+        .get("http2-settings")?
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '-' => '+',
+            '_' => '/',
+            c => c,
+        })
+        .collect();
+    while !settings.len().is_multiple_of(4) {
+        settings.push('=');
+    }
+    frame::parse_settings(&base64::decode(&settings)?)
+}
+
+/// Continues the blocking connection of the request with HTTP/2: the request is
+/// either the start of the preface, or an upgrade request, which is answered
+/// with 101 Switching Protocols and then as stream 1. Returns when the
+/// connection is closed; idle connections are closed after the header timeout.
+/// The IP filter is given if the peer is a trusted proxy: it is checked for each stream.
+pub(crate) fn serve_h2c(
+    mut request: Request,
+    hosts: &VirtualHosts,
+    metrics: &HttpMetrics,
+    limits: &ConnectionLimits,
+    ip_filter: Option<SharedIpFilter>,
+) -> io::Result<()> {
+    let (mut stream, mut reader) = match request.take_connection() {
+        Some(connection) => connection,
+        None => return Ok(()),
+    };
+    let deadline_reader = reader.get_mut();
+    deadline_reader.set_deadline(None);
+    deadline_reader.set_min_rate(None);
+    deadline_reader.set_idle_timeout(Some(limits.header_timeout));
+    let peer_addr = request.peer_addr;
+
+    if is_preface(&request) {
+        // the preface is read again by the connection:
+        let reader = Cursor::new(&PREFACE[..PREFACE_HTTP1_LEN]).chain(reader);
+        return Http2Connection::new(reader, stream, hosts, metrics)
+            .peer_addr(peer_addr)
+            .max_body_size(limits.max_body_size)
+            .ip_filter(ip_filter)
+            .serve();
+    }
+
+    let settings = upgrade_settings(&request).unwrap_or_default();
+    Response::new(HTTPStatusCode::Info(101))
+        .with_header("Connection", "Upgrade")
+        .with_header("Upgrade", "h2c")
+        .write_to(&mut stream)?;
+    for header in ["connection", "upgrade", "http2-settings"] {
+        request.headers.remove(header);
+    }
+    Http2Connection::new(reader, stream, hosts, metrics)
+        .peer_addr(peer_addr)
+        .max_body_size(limits.max_body_size)
+        .ip_filter(ip_filter)
+        .serve_upgrade(request, &settings)
+}
@@ -0,0 +1,786 @@
+use std::collections::BTreeMap;
+use std::io::{self, ErrorKind, Read, Write};
+use std::mem;
+use std::net::SocketAddr;
+use std::time::Instant;
+
+use super::frame::*;
+use super::hpack::{DecodeError, Decoder, Encoder, DEFAULT_TABLE_SIZE};
+use super::PREFACE;
+use crate::httpserver::{
+    ConnectionLimits, FilterAction, HTTPStatusCode, HeaderMap, HttpMetrics, HttpVerb, Problem,
+    Request, RequestHead, Response, SharedIpFilter, VirtualHosts, MAX_HEAD_SIZE,
+};
+use crate::utils::logging::LogSeverity;
+
+/// Number of streams a client may have open at the same time.
+const MAX_CONCURRENT_STREAMS: u32 = 100;
+
+/// CONTINUATION frames allowed per header block: the size limit of the block
+/// does not stop a flood of empty ones.
+const MAX_CONTINUATIONS: usize = 32;
+
+/// Connection-specific header fields, not allowed in HTTP/2 (RFC 9113, 8.2.2).
+const CONNECTION_HEADERS: [&str; 5] = [
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "transfer-encoding",
+    "upgrade",
+];
+
+enum Error {
+    /// ends the connection with a GOAWAY frame
+    Connection(ErrorCode, String),
+    /// resets the stream with a RST_STREAM frame, the connection goes on
+    Stream(u32, ErrorCode),
+    Io(io::Error),
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+struct Stream {
+    /// header list and body of the request, while it is received
+    fields: Vec<(String, String)>,
+    body: Vec<u8>,
+    received: u64,
+    start: Instant,
+    /// the request is complete (the stream is half-closed), the response body
+    /// is sent as far as the flow control windows allow
+    responding: bool,
+    response_body: Vec<u8>,
+    sent: usize,
+    send_window: i64,
+}
+
+impl Stream {
+    fn new(send_window: i64) -> Stream {
+        Stream {
+            fields: Vec::new(),
+            body: Vec::new(),
+            received: 0,
+            start: Instant::now(),
+            responding: false,
+            response_body: Vec::new(),
+            sent: 0,
+            send_window,
+        }
+    }
+}
+
+/// The server side of an HTTP/2 connection. The frames are read and written
+/// blocking by the calling thread; each complete request is dispatched to the
+/// router of its host, so the streams are multiplexed, but handled one after
+/// the other.
+pub struct Http2Connection<'a, R: Read, W: Write> {
+    reader: R,
+    writer: W,
+    hosts: &'a VirtualHosts,
+    metrics: &'a HttpMetrics,
+    peer_addr: Option<SocketAddr>,
+    decoder: Decoder,
+    encoder: Encoder,
+    streams: BTreeMap<u32, Stream>,
+    /// highest stream id opened by the client
+    last_stream_id: u32,
+    /// stream id, fragments and END_STREAM flag of a header block continued
+    /// in CONTINUATION frames
+    header_block: Option<(u32, Vec<u8>, bool)>,
+    /// CONTINUATION frames received for the current header block
+    continuations: usize,
+    /// connection flow control window for sending
+    send_window: i64,
+    /// the client's settings
+    initial_window_size: i64,
+    max_frame_size: u32,
+    goaway_received: bool,
+    /// larger request bodies are answered with 413
+    max_body_size: usize,
+    /// checked for each stream, for a peer that is a trusted proxy
+    ip_filter: Option<SharedIpFilter>,
+}
+
+impl<'a, R: Read, W: Write> Http2Connection<'a, R, W> {
+    pub fn new(
+        reader: R,
+        writer: W,
+        hosts: &'a VirtualHosts,
+        metrics: &'a HttpMetrics,
+    ) -> Http2Connection<'a, R, W> {
+        Http2Connection {
+            reader,
+            writer,
+            hosts,
+            metrics,
+            peer_addr: None,
+            decoder: Decoder::new(DEFAULT_TABLE_SIZE, MAX_HEAD_SIZE),
+            encoder: Encoder::new(),
+            streams: BTreeMap::new(),
+            last_stream_id: 0,
+            header_block: None,
+            continuations: 0,
+            send_window: DEFAULT_WINDOW_SIZE as i64,
+            initial_window_size: DEFAULT_WINDOW_SIZE as i64,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            goaway_received: false,
+            max_body_size: ConnectionLimits::default().max_body_size,
+            ip_filter: None,
+        }
+    }
+
+    /// Sets the client address, passed on to the requests.
+    pub fn peer_addr(mut self, peer_addr: Option<SocketAddr>) -> Self {
+        self.peer_addr = peer_addr;
+        self
+    }
+
+    /// Sets the maximum size of request bodies.
+    pub fn max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Sets the IP filter to check the client address (`X-Forwarded-For`) of
+    /// each request with: for a trusted proxy, the streams of a connection
+    /// may come from different clients.
+    pub fn ip_filter(mut self, ip_filter: Option<SharedIpFilter>) -> Self {
+        self.ip_filter = ip_filter;
+        self
+    }
+
+    /// Serves a connection with prior knowledge: it starts with the client
+    /// connection preface. Returns when the client closes the connection or
+    /// goes away, or after a connection error.
+    pub fn serve(self) -> io::Result<()> {
+        self.run(None)
+    }
+
+    /// Serves a connection upgraded from HTTP/1.1 (after the 101 response was
+    /// sent): the upgrade request is answered as stream 1, with the settings
+    /// from its `HTTP2-Settings` header.
+    pub fn serve_upgrade(self, request: Request, settings: &[(u16, u32)]) -> io::Result<()> {
+        self.run(Some((request, settings)))
+    }
+
+    fn run(mut self, upgrade: Option<(Request, &[(u16, u32)])>) -> io::Result<()> {
+        self.write(Frame::settings(&[
+            (SETTINGS_MAX_CONCURRENT_STREAMS, MAX_CONCURRENT_STREAMS),
+            (SETTINGS_MAX_HEADER_LIST_SIZE, MAX_HEAD_SIZE as u32),
+        ]))?;
+        if let Some((request, settings)) = upgrade {
+            self.last_stream_id = 1;
+            if let Err(e) = self.apply_settings(settings) {
+                return self.fail(e);
+            }
+            self.respond(1, request, Instant::now())?;
+        }
+
+        let mut preface = [0u8; PREFACE.len()];
+        match self.reader.read_exact(&mut preface) {
+            Ok(_) if &preface == PREFACE => (),
+            Ok(_) => {
+                let error = Error::Connection(ErrorCode::ProtocolError, "invalid preface".into());
+                return self.fail(error);
+            }
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e),
+        }
+
+        let mut first_frame = true;
+        loop {
+            self.send_pending()?;
+            if self.goaway_received && self.streams.is_empty() {
+                break;
+            }
+            let frame = match Frame::read_from(&mut self.reader, DEFAULT_MAX_FRAME_SIZE) {
+                Ok(Some(frame)) => frame,
+                Ok(None) => break,
+                Err(e) if e.kind() == ErrorKind::InvalidData => {
+                    let error = Error::Connection(ErrorCode::FrameSizeError, e.to_string());
+                    return self.fail(error);
+                }
+                Err(e) if e.kind() == ErrorKind::TimedOut => {
+                    // idle connection:
+                    self.write(Frame::goaway(self.last_stream_id, ErrorCode::NoError, ""))?;
+                    break;
+                }
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e),
+            };
+            // the client preface ends with a SETTINGS frame:
+            if first_frame && frame.kind != FrameType::Settings {
+                let error = Error::Connection(
+                    ErrorCode::ProtocolError,
+                    "expected SETTINGS after the preface".into(),
+                );
+                return self.fail(error);
+            }
+            first_frame = false;
+
+            match self.handle_frame(frame) {
+                Ok(()) => (),
+                Err(Error::Stream(id, code)) => {
+                    log(
+                        &format!("Resetting HTTP/2 stream {}: {}", id, code),
+                        LogSeverity::WARNING,
+                    );
+                    self.streams.remove(&id);
+                    self.write(Frame::rst_stream(id, code))?;
+                }
+                Err(e) => return self.fail(e),
+            }
+        }
+        self.writer.flush()
+    }
+
+    /// Ends the connection after a connection error, see [Error].
+    fn fail(&mut self, error: Error) -> io::Result<()> {
+        match error {
+            Error::Connection(code, msg) => {
+                log(
+                    &format!("HTTP/2 connection error {}: {}", code, msg),
+                    LogSeverity::WARNING,
+                );
+                self.write(Frame::goaway(self.last_stream_id, code, &msg))?;
+                self.writer.flush()
+            }
+            Error::Stream(id, code) => {
+                self.write(Frame::rst_stream(id, code))?;
+                self.writer.flush()
+            }
+            Error::Io(e) => Err(e),
+        }
+    }
+
+    fn handle_frame(&mut self, frame: Frame) -> Result<(), Error> {
+        // a header block must not be interrupted by other frames:
+        if let Some((id, _, _)) = &self.header_block {
+            if frame.kind != FrameType::Continuation || frame.stream_id != *id {
+                return Err(protocol_error("expected CONTINUATION"));
+            }
+        }
+        match frame.kind {
+            FrameType::Data => self.on_data(frame),
+            FrameType::Headers => self.on_headers(frame),
+            FrameType::Continuation => self.on_continuation(frame),
+            FrameType::Priority => match (frame.stream_id, frame.payload.len()) {
+                (0, _) => Err(protocol_error("PRIORITY on stream 0")),
+                (_, 5) => Ok(()),
+                (id, _) => Err(Error::Stream(id, ErrorCode::FrameSizeError)),
+            },
+            FrameType::RstStream => self.on_rst_stream(frame),
+            FrameType::Settings => self.on_settings(frame),
+            FrameType::PushPromise => Err(protocol_error("PUSH_PROMISE from a client")),
+            FrameType::Ping => {
+                if frame.stream_id != 0 {
+                    return Err(protocol_error("PING on a stream"));
+                }
+                if frame.payload.len() != 8 {
+                    return Err(frame_size_error("PING"));
+                }
+                if !frame.has_flag(FLAG_ACK) {
+                    self.write(Frame::ping_ack(&frame.payload))?;
+                }
+                Ok(())
+            }
+            FrameType::GoAway => {
+                if frame.stream_id != 0 {
+                    return Err(protocol_error("GOAWAY on a stream"));
+                }
+                self.goaway_received = true;
+                Ok(())
+            }
+            FrameType::WindowUpdate => self.on_window_update(frame),
+            FrameType::Unknown(_) => Ok(()),
+        }
+    }
+
+    fn on_headers(&mut self, frame: Frame) -> Result<(), Error> {
+        if frame.stream_id == 0 {
+            return Err(protocol_error("HEADERS on stream 0"));
+        }
+        let block = frame
+            .content()
+            .ok_or_else(|| protocol_error("invalid padding"))?
+            .to_vec();
+        let end_stream = frame.has_flag(FLAG_END_STREAM);
+        match frame.has_flag(FLAG_END_HEADERS) {
+            true => self.on_header_block(frame.stream_id, block, end_stream),
+            false => {
+                self.header_block = Some((frame.stream_id, block, end_stream));
+                self.continuations = 0;
+                Ok(())
+            }
+        }
+    }
+
+    fn on_continuation(&mut self, frame: Frame) -> Result<(), Error> {
+        let (id, mut block, end_stream) = self
+            .header_block
+            .take()
+            .ok_or_else(|| protocol_error("CONTINUATION without HEADERS"))?;
+        block.extend_from_slice(&frame.payload);
+        if block.len() > MAX_HEAD_SIZE {
+            return Err(Error::Connection(
+                ErrorCode::EnhanceYourCalm,
+                "header block too large".into(),
+            ));
+        }
+        self.continuations += 1;
+        if self.continuations > MAX_CONTINUATIONS {
+            return Err(Error::Connection(
+                ErrorCode::EnhanceYourCalm,
+                "too many CONTINUATION frames".into(),
+            ));
+        }
+        match frame.has_flag(FLAG_END_HEADERS) {
+            true => self.on_header_block(id, block, end_stream),
+            false => {
+                self.header_block = Some((id, block, end_stream));
+                Ok(())
+            }
+        }
+    }
+
+    /// A complete header block: a new request, or the trailers of a request.
+    fn on_header_block(&mut self, id: u32, block: Vec<u8>, end_stream: bool) -> Result<(), Error> {
+        // always decoded, to keep the dynamic table in sync:
+        let fields = match self.decoder.decode(&block) {
+            Ok(fields) => Some(fields),
+            Err(DecodeError::ListTooLarge) => None,
+            Err(DecodeError::Compression(msg)) => {
+                return Err(Error::Connection(ErrorCode::CompressionError, msg))
+            }
+        };
+
+        if let Some(stream) = self.streams.get_mut(&id) {
+            if stream.responding {
+                return Err(Error::Stream(id, ErrorCode::StreamClosed));
+            }
+            // trailers, they must end the stream (and are not passed on):
+            if !end_stream {
+                return Err(Error::Stream(id, ErrorCode::ProtocolError));
+            }
+            stream.received += block.len() as u64;
+            return self.request_complete(id);
+        }
+        if id <= self.last_stream_id {
+            return Err(Error::Connection(
+                ErrorCode::StreamClosed,
+                format!("HEADERS on closed stream {}", id),
+            ));
+        }
+        if id.is_multiple_of(2) {
+            return Err(protocol_error("even stream id from a client"));
+        }
+        self.last_stream_id = id;
+
+        let open = self.streams.values().filter(|s| !s.responding).count();
+        if open >= MAX_CONCURRENT_STREAMS as usize {
+            return Err(Error::Stream(id, ErrorCode::RefusedStream));
+        }
+        let mut stream = Stream::new(self.initial_window_size);
+        stream.received = block.len() as u64;
+        self.streams.insert(id, stream);
+
+        let fields = match fields {
+            Some(fields) => fields,
+            None => {
+                let response = Problem::new(HTTPStatusCode::ClientError(431)).into();
+                self.send_response(id, response, &HttpVerb::UNKNOWN)?;
+                return Ok(());
+            }
+        };
+        self.streams.get_mut(&id).unwrap().fields = fields;
+        match end_stream {
+            true => self.request_complete(id),
+            false => Ok(()),
+        }
+    }
+
+    fn on_data(&mut self, frame: Frame) -> Result<(), Error> {
+        let id = frame.stream_id;
+        if id == 0 {
+            return Err(protocol_error("DATA on stream 0"));
+        }
+        // the whole frame counts for flow control (RFC 9113, 6.9.1),
+        // the window is opened again right away:
+        let len = frame.payload.len() as u32;
+        if len > 0 {
+            self.write(Frame::window_update(0, len))?;
+        }
+        let data = frame
+            .content()
+            .ok_or_else(|| protocol_error("invalid padding"))?;
+        let end_stream = frame.has_flag(FLAG_END_STREAM);
+        match self.streams.get_mut(&id) {
+            Some(stream) if !stream.responding => {
+                if data.len() > self.max_body_size - stream.body.len() {
+                    // the stream is closed by the response, further DATA is refused:
+                    stream.body = Vec::new();
+                    let response = Problem::new(HTTPStatusCode::ClientError(413)).into();
+                    self.send_response(id, response, &HttpVerb::POST)?;
+                    return Ok(());
+                }
+                stream.body.extend_from_slice(data);
+                stream.received += len as u64;
+                if end_stream {
+                    return self.request_complete(id);
+                }
+                if len > 0 {
+                    Frame::window_update(id, len).write_to(&mut self.writer)?;
+                }
+                Ok(())
+            }
+            None if id > self.last_stream_id => Err(protocol_error("DATA on an idle stream")),
+            _ => Err(Error::Stream(id, ErrorCode::StreamClosed)),
+        }
+    }
+
+    fn on_rst_stream(&mut self, frame: Frame) -> Result<(), Error> {
+        if frame.stream_id == 0 || frame.stream_id > self.last_stream_id {
+            return Err(protocol_error("RST_STREAM on an idle stream"));
+        }
+        if frame.payload.len() != 4 {
+            return Err(frame_size_error("RST_STREAM"));
+        }
+        self.streams.remove(&frame.stream_id);
+        Ok(())
+    }
+
+    fn on_settings(&mut self, frame: Frame) -> Result<(), Error> {
+        if frame.stream_id != 0 {
+            return Err(protocol_error("SETTINGS on a stream"));
+        }
+        if frame.has_flag(FLAG_ACK) {
+            return match frame.payload.is_empty() {
+                true => Ok(()),
+                false => Err(frame_size_error("SETTINGS ack")),
+            };
+        }
+        let settings =
+            parse_settings(&frame.payload).ok_or_else(|| frame_size_error("SETTINGS"))?;
+        self.apply_settings(&settings)?;
+        self.write(Frame::settings_ack())?;
+        Ok(())
+    }
+
+    fn apply_settings(&mut self, settings: &[(u16, u32)]) -> Result<(), Error> {
+        for (id, value) in settings {
+            match *id {
+                SETTINGS_ENABLE_PUSH if *value > 1 => {
+                    return Err(protocol_error("invalid SETTINGS_ENABLE_PUSH"))
+                }
+                SETTINGS_INITIAL_WINDOW_SIZE => {
+                    if *value > MAX_WINDOW_SIZE {
+                        return Err(Error::Connection(
+                            ErrorCode::FlowControlError,
+                            "invalid SETTINGS_INITIAL_WINDOW_SIZE".into(),
+                        ));
+                    }
+                    // the change applies to all open streams (RFC 9113, 6.9.2):
+                    let delta = *value as i64 - self.initial_window_size;
+                    for stream in self.streams.values_mut() {
+                        stream.send_window += delta;
+                        if stream.send_window > MAX_WINDOW_SIZE as i64 {
+                            return Err(Error::Connection(
+                                ErrorCode::FlowControlError,
+                                "stream window overflow".into(),
+                            ));
+                        }
+                    }
+                    self.initial_window_size = *value as i64;
+                }
+                SETTINGS_MAX_FRAME_SIZE => {
+                    if !(DEFAULT_MAX_FRAME_SIZE..=0xff_ffff).contains(value) {
+                        return Err(protocol_error("invalid SETTINGS_MAX_FRAME_SIZE"));
+                    }
+                    self.max_frame_size = *value;
+                }
+                // the encoder does not use the dynamic table, the other
+                // settings are advisory or unknown:
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    fn on_window_update(&mut self, frame: Frame) -> Result<(), Error> {
+        let payload: [u8; 4] = frame
+            .payload
+            .as_slice()
+            .try_into()
+            .map_err(|_| frame_size_error("WINDOW_UPDATE"))?;
+        let increment = (u32::from_be_bytes(payload) & MAX_WINDOW_SIZE) as i64;
+        let id = frame.stream_id;
+        if id == 0 {
+            if increment == 0 {
+                return Err(protocol_error("window increment of 0"));
+            }
+            self.send_window += increment;
+            if self.send_window > MAX_WINDOW_SIZE as i64 {
+                return Err(Error::Connection(
+                    ErrorCode::FlowControlError,
+                    "connection window overflow".into(),
+                ));
+            }
+            return Ok(());
+        }
+        match self.streams.get_mut(&id) {
+            Some(_) if increment == 0 => Err(Error::Stream(id, ErrorCode::ProtocolError)),
+            Some(stream) => {
+                stream.send_window += increment;
+                match stream.send_window > MAX_WINDOW_SIZE as i64 {
+                    true => Err(Error::Stream(id, ErrorCode::FlowControlError)),
+                    false => Ok(()),
+                }
+            }
+            None if id > self.last_stream_id => {
+                Err(protocol_error("WINDOW_UPDATE on an idle stream"))
+            }
+            // a closed stream:
+            None => Ok(()),
+        }
+    }
+
+    /// The client ended the stream: the request is built and dispatched.
+    fn request_complete(&mut self, id: u32) -> Result<(), Error> {
+        let stream = match self.streams.get_mut(&id) {
+            Some(stream) => stream,
+            None => return Ok(()),
+        };
+        let fields = mem::take(&mut stream.fields);
+        let body = mem::take(&mut stream.body);
+        let (received, start) = (stream.received, stream.start);
+        let mut request = match build_request(fields, body, self.peer_addr) {
+            Ok(request) => request,
+            Err(code) => return Err(Error::Stream(id, code)),
+        };
+        request.set_bytes_read(received);
+        self.respond(id, request, start)?;
+        Ok(())
+    }
+
+    /// Runs the request through the router of its host and sends the response.
+    fn respond(&mut self, id: u32, request: Request, start: Instant) -> io::Result<()> {
+        match self.denied_action(&request) {
+            Some(FilterAction::Drop) => {
+                self.streams.remove(&id);
+                self.write(Frame::rst_stream(id, ErrorCode::RefusedStream))?;
+                return Ok(());
+            }
+            Some(FilterAction::Forbidden) => {
+                let response = Problem::new(HTTPStatusCode::ClientError(403)).into();
+                self.send_response(id, response, &request.method)?;
+                return Ok(());
+            }
+            None => (),
+        }
+        let router = self.hosts.select(request.host().as_deref());
+        let mut response = request.respond(router);
+        if response.is_streaming() {
+            response = Response::stream_not_supported();
+        }
+        let status = response.status.code();
+        let bytes_written = self.send_response(id, response, &request.method)?;
+        self.metrics.observe_request(
+            request.method.as_str(),
+            &router.route_label(&request.method, &request.url),
+            status,
+            start.elapsed(),
+            request.bytes_read(),
+            bytes_written as u64,
+        );
+        Ok(())
+    }
+
+    /// The action of the IP filter, if the client of the request is denied.
+    fn denied_action(&self, request: &Request) -> Option<FilterAction> {
+        let filter = self.ip_filter.as_ref()?.read().unwrap();
+        let client = filter.denied_client(request)?;
+        log(
+            &format!("Request denied by the IP filter: {}", client),
+            LogSeverity::WARNING,
+        );
+        Some(filter.get_action())
+    }
+
+    /// Sends the response headers, and queues the body to be sent with the flow
+    /// control windows. Returns the number of header and body bytes.
+    fn send_response(
+        &mut self,
+        id: u32,
+        response: Response,
+        method: &HttpVerb,
+    ) -> io::Result<usize> {
+        let mut fields = vec![(String::from(":status"), response.status.code().to_string())];
+        for (name, value) in response.headers.iter() {
+            let name = name.to_ascii_lowercase();
+            if !CONNECTION_HEADERS.contains(&name.as_str()) {
+                fields.push((name, String::from(value)));
+            }
+        }
+        if !response.headers.contains("Content-Length") && response.may_have_content_length() {
+            fields.push((
+                String::from("content-length"),
+                response.body.len().to_string(),
+            ));
+        }
+        let block = self
+            .encoder
+            .encode(fields.iter().map(|(n, v)| (n.as_str(), v.as_str())));
+
+        let body = match method {
+            HttpVerb::HEAD => Vec::new(),
+            _ => response.body,
+        };
+        let frames = Frame::headers(id, &block, body.is_empty(), self.max_frame_size as usize);
+        for frame in frames {
+            self.write(frame)?;
+        }
+        let bytes = block.len() + body.len();
+        if body.is_empty() {
+            self.streams.remove(&id);
+        } else {
+            let initial_window_size = self.initial_window_size;
+            let stream = self
+                .streams
+                .entry(id)
+                .or_insert_with(|| Stream::new(initial_window_size));
+            stream.responding = true;
+            stream.response_body = body;
+        }
+        Ok(bytes)
+    }
+
+    /// Sends as much of the queued response bodies as the flow control windows allow.
+    fn send_pending(&mut self) -> io::Result<()> {
+        let mut finished = Vec::new();
+        for (id, stream) in self.streams.iter_mut().filter(|(_, s)| s.responding) {
+            while stream.sent < stream.response_body.len() {
+                let window = self
+                    .send_window
+                    .min(stream.send_window)
+                    .min(self.max_frame_size as i64);
+                if window <= 0 {
+                    break;
+                }
+                let end = stream
+                    .response_body
+                    .len()
+                    .min(stream.sent + window as usize);
+                let end_stream = end == stream.response_body.len();
+                let chunk = &stream.response_body[stream.sent..end];
+                Frame::data(*id, chunk, end_stream).write_to(&mut self.writer)?;
+                self.send_window -= chunk.len() as i64;
+                stream.send_window -= chunk.len() as i64;
+                stream.sent = end;
+            }
+            if stream.sent == stream.response_body.len() {
+                finished.push(*id);
+            }
+        }
+        for id in finished {
+            self.streams.remove(&id);
+        }
+        self.writer.flush()
+    }
+
+    fn write(&mut self, frame: Frame) -> io::Result<usize> {
+        frame.write_to(&mut self.writer)
+    }
+}
+
+/// Builds the request from the header list, checking that it is well-formed
+/// (RFC 9113, 8.2 and 8.3.1). A malformed request is a stream error.
+fn build_request(
+    fields: Vec<(String, String)>,
+    body: Vec<u8>,
+    peer_addr: Option<SocketAddr>,
+) -> Result<Request, ErrorCode> {
+    let malformed = ErrorCode::ProtocolError;
+    let (mut method, mut scheme, mut path, mut authority) = (None, None, None, None);
+    let mut headers = HeaderMap::new();
+    let mut cookies = Vec::new();
+    let mut regular_seen = false;
+    for (name, value) in fields {
+        if name.bytes().any(|b| b.is_ascii_uppercase()) {
+            return Err(malformed);
+        }
+        if let Some(pseudo) = name.strip_prefix(':') {
+            let field = match pseudo {
+                "method" => &mut method,
+                "scheme" => &mut scheme,
+                "path" => &mut path,
+                "authority" => &mut authority,
+                _ => return Err(malformed),
+            };
+            // pseudo-headers come first, and only once:
+            if regular_seen || field.is_some() {
+                return Err(malformed);
+            }
+            *field = Some(value);
+            continue;
+        }
+        regular_seen = true;
+        if CONNECTION_HEADERS.contains(&name.as_str()) || (name == "te" && value != "trailers") {
+            return Err(malformed);
+        }
+        match name.as_str() {
+            // split cookie fields are joined with "; " (RFC 9113, 8.2.3):
+            "cookie" => cookies.push(value),
+            _ => headers.append(&name, &value),
+        }
+    }
+
+    let method: HttpVerb = method.ok_or(malformed)?.parse().map_err(|_| malformed)?;
+    if method != HttpVerb::CONNECT
+        && (scheme.is_none() || path.as_deref().unwrap_or_default().is_empty())
+    {
+        return Err(malformed);
+    }
+    if !cookies.is_empty() {
+        headers.set("cookie", &cookies.join("; "));
+    }
+    if let Some(authority) = authority {
+        if !headers.contains("host") {
+            headers.set("host", &authority);
+        }
+    }
+    if let Some(length) = headers.get("content-length") {
+        if length.trim().parse::<usize>() != Ok(body.len()) {
+            return Err(malformed);
+        }
+    }
+
+    let head = RequestHead {
+        method,
+        full_url: path.unwrap_or_default(),
+        version: String::from("HTTP/2"),
+        headers,
+    };
+    let body = match body.is_empty() {
+        true => None,
+        false => Some(String::from_utf8_lossy(&body).into_owned()),
+    };
+    Ok(Request::from_head(head, body, peer_addr))
+}
+
+fn protocol_error(msg: &str) -> Error {
+    Error::Connection(ErrorCode::ProtocolError, String::from(msg))
+}
+
+fn frame_size_error(frame: &str) -> Error {
+    Error::Connection(
+        ErrorCode::FrameSizeError,
+        format!("invalid {} frame length", frame),
+    )
+}
+
+fn log(msg: &str, severity: LogSeverity) {
+    eprintln!("{}: {}\n", severity, msg);
+}
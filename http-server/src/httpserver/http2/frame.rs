@@ -0,0 +1,345 @@
+//! The HTTP/2 frame layer (RFC 9113, 4 and 6).
+
+use std::fmt::{Display, Formatter};
+use std::io::{self, ErrorKind, Read, Write};
+
+/// Length of the fixed frame header: payload length (24 bits), type, flags
+/// and stream identifier.
+pub const FRAME_HEADER_LEN: usize = 9;
+
+/// Initial value of SETTINGS_MAX_FRAME_SIZE, also the upper limit this server announces.
+pub const DEFAULT_MAX_FRAME_SIZE: u32 = 16_384;
+
+/// Initial flow control window of the connection and of new streams.
+pub const DEFAULT_WINDOW_SIZE: u32 = 65_535;
+
+/// Largest flow control window (2^31 - 1).
+pub const MAX_WINDOW_SIZE: u32 = 0x7fff_ffff;
+
+pub const FLAG_END_STREAM: u8 = 0x1;
+pub const FLAG_ACK: u8 = 0x1;
+pub const FLAG_END_HEADERS: u8 = 0x4;
+pub const FLAG_PADDED: u8 = 0x8;
+pub const FLAG_PRIORITY: u8 = 0x20;
+
+pub const SETTINGS_HEADER_TABLE_SIZE: u16 = 0x1;
+pub const SETTINGS_ENABLE_PUSH: u16 = 0x2;
+pub const SETTINGS_MAX_CONCURRENT_STREAMS: u16 = 0x3;
+pub const SETTINGS_INITIAL_WINDOW_SIZE: u16 = 0x4;
+pub const SETTINGS_MAX_FRAME_SIZE: u16 = 0x5;
+pub const SETTINGS_MAX_HEADER_LIST_SIZE: u16 = 0x6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameType {
+    Data,
+    Headers,
+    Priority,
+    RstStream,
+    Settings,
+    PushPromise,
+    Ping,
+    GoAway,
+    WindowUpdate,
+    Continuation,
+    /// frames of unknown type are ignored (RFC 9113, 4.1)
+    Unknown(u8),
+}
+
+impl FrameType {
+    pub fn from(kind: u8) -> FrameType {
+        match kind {
+            0x0 => FrameType::Data,
+            0x1 => FrameType::Headers,
+            0x2 => FrameType::Priority,
+            0x3 => FrameType::RstStream,
+            0x4 => FrameType::Settings,
+            0x5 => FrameType::PushPromise,
+            0x6 => FrameType::Ping,
+            0x7 => FrameType::GoAway,
+            0x8 => FrameType::WindowUpdate,
+            0x9 => FrameType::Continuation,
+            other => FrameType::Unknown(other),
+        }
+    }
+
+    pub fn code(&self) -> u8 {
+        match self {
+            FrameType::Data => 0x0,
+            FrameType::Headers => 0x1,
+            FrameType::Priority => 0x2,
+            FrameType::RstStream => 0x3,
+            FrameType::Settings => 0x4,
+            FrameType::PushPromise => 0x5,
+            FrameType::Ping => 0x6,
+            FrameType::GoAway => 0x7,
+            FrameType::WindowUpdate => 0x8,
+            FrameType::Continuation => 0x9,
+            FrameType::Unknown(other) => *other,
+        }
+    }
+}
+
+/// The error codes of RST_STREAM and GOAWAY frames (RFC 9113, 7).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    NoError,
+    ProtocolError,
+    InternalError,
+    FlowControlError,
+    SettingsTimeout,
+    StreamClosed,
+    FrameSizeError,
+    RefusedStream,
+    Cancel,
+    CompressionError,
+    ConnectError,
+    EnhanceYourCalm,
+    InadequateSecurity,
+    Http11Required,
+    /// unknown codes must not trigger any special behavior
+    Unknown(u32),
+}
+
+impl ErrorCode {
+    pub fn from(code: u32) -> ErrorCode {
+        match code {
+            0x0 => ErrorCode::NoError,
+            0x1 => ErrorCode::ProtocolError,
+            0x2 => ErrorCode::InternalError,
+            0x3 => ErrorCode::FlowControlError,
+            0x4 => ErrorCode::SettingsTimeout,
+            0x5 => ErrorCode::StreamClosed,
+            0x6 => ErrorCode::FrameSizeError,
+            0x7 => ErrorCode::RefusedStream,
+            0x8 => ErrorCode::Cancel,
+            0x9 => ErrorCode::CompressionError,
+            0xa => ErrorCode::ConnectError,
+            0xb => ErrorCode::EnhanceYourCalm,
+            0xc => ErrorCode::InadequateSecurity,
+            0xd => ErrorCode::Http11Required,
+            other => ErrorCode::Unknown(other),
+        }
+    }
+
+    pub fn code(&self) -> u32 {
+        match self {
+            ErrorCode::NoError => 0x0,
+            ErrorCode::ProtocolError => 0x1,
+            ErrorCode::InternalError => 0x2,
+            ErrorCode::FlowControlError => 0x3,
+            ErrorCode::SettingsTimeout => 0x4,
+            ErrorCode::StreamClosed => 0x5,
+            ErrorCode::FrameSizeError => 0x6,
+            ErrorCode::RefusedStream => 0x7,
+            ErrorCode::Cancel => 0x8,
+            ErrorCode::CompressionError => 0x9,
+            ErrorCode::ConnectError => 0xa,
+            ErrorCode::EnhanceYourCalm => 0xb,
+            ErrorCode::InadequateSecurity => 0xc,
+            ErrorCode::Http11Required => 0xd,
+            ErrorCode::Unknown(other) => *other,
+        }
+    }
+}
+
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ErrorCode::NoError => "NO_ERROR",
+            ErrorCode::ProtocolError => "PROTOCOL_ERROR",
+            ErrorCode::InternalError => "INTERNAL_ERROR",
+            ErrorCode::FlowControlError => "FLOW_CONTROL_ERROR",
+            ErrorCode::SettingsTimeout => "SETTINGS_TIMEOUT",
+            ErrorCode::StreamClosed => "STREAM_CLOSED",
+            ErrorCode::FrameSizeError => "FRAME_SIZE_ERROR",
+            ErrorCode::RefusedStream => "REFUSED_STREAM",
+            ErrorCode::Cancel => "CANCEL",
+            ErrorCode::CompressionError => "COMPRESSION_ERROR",
+            ErrorCode::ConnectError => "CONNECT_ERROR",
+            ErrorCode::EnhanceYourCalm => "ENHANCE_YOUR_CALM",
+            ErrorCode::InadequateSecurity => "INADEQUATE_SECURITY",
+            ErrorCode::Http11Required => "HTTP_1_1_REQUIRED",
+            ErrorCode::Unknown(code) => return write!(f, "UNKNOWN({:#x})", code),
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub kind: FrameType,
+    pub flags: u8,
+    pub stream_id: u32,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new(kind: FrameType, flags: u8, stream_id: u32, payload: Vec<u8>) -> Frame {
+        Frame {
+            kind,
+            flags,
+            stream_id,
+            payload,
+        }
+    }
+
+    pub fn settings(settings: &[(u16, u32)]) -> Frame {
+        let mut payload = Vec::with_capacity(settings.len() * 6);
+        for (id, value) in settings {
+            payload.extend_from_slice(&id.to_be_bytes());
+            payload.extend_from_slice(&value.to_be_bytes());
+        }
+        Frame::new(FrameType::Settings, 0, 0, payload)
+    }
+
+    pub fn settings_ack() -> Frame {
+        Frame::new(FrameType::Settings, FLAG_ACK, 0, Vec::new())
+    }
+
+    pub fn ping_ack(data: &[u8]) -> Frame {
+        Frame::new(FrameType::Ping, FLAG_ACK, 0, data.to_vec())
+    }
+
+    pub fn data(stream_id: u32, data: &[u8], end_stream: bool) -> Frame {
+        let flags = if end_stream { FLAG_END_STREAM } else { 0 };
+        Frame::new(FrameType::Data, flags, stream_id, data.to_vec())
+    }
+
+    pub fn window_update(stream_id: u32, increment: u32) -> Frame {
+        Frame::new(
+            FrameType::WindowUpdate,
+            0,
+            stream_id,
+            increment.to_be_bytes().to_vec(),
+        )
+    }
+
+    pub fn rst_stream(stream_id: u32, error: ErrorCode) -> Frame {
+        Frame::new(
+            FrameType::RstStream,
+            0,
+            stream_id,
+            error.code().to_be_bytes().to_vec(),
+        )
+    }
+
+    pub fn goaway(last_stream_id: u32, error: ErrorCode, debug_data: &str) -> Frame {
+        let mut payload = Vec::with_capacity(8 + debug_data.len());
+        payload.extend_from_slice(&last_stream_id.to_be_bytes());
+        payload.extend_from_slice(&error.code().to_be_bytes());
+        payload.extend_from_slice(debug_data.as_bytes());
+        Frame::new(FrameType::GoAway, 0, 0, payload)
+    }
+
+    /// Splits an encoded header block into a HEADERS frame and as many
+    /// CONTINUATION frames as needed for the maximum frame size.
+    pub fn headers(stream_id: u32, block: &[u8], end_stream: bool, max_size: usize) -> Vec<Frame> {
+        let mut frames: Vec<Frame> = block
+            .chunks(max_size.max(1))
+            .enumerate()
+            .map(|(idx, chunk)| match idx {
+                0 => Frame::new(FrameType::Headers, 0, stream_id, chunk.to_vec()),
+                _ => Frame::new(FrameType::Continuation, 0, stream_id, chunk.to_vec()),
+            })
+            .collect();
+        if frames.is_empty() {
+            frames.push(Frame::new(FrameType::Headers, 0, stream_id, Vec::new()));
+        }
+        if end_stream {
+            frames[0].flags |= FLAG_END_STREAM;
+        }
+        if let Some(last) = frames.last_mut() {
+            last.flags |= FLAG_END_HEADERS;
+        }
+        frames
+    }
+
+    pub fn has_flag(&self, flag: u8) -> bool {
+        self.flags & flag != 0
+    }
+
+    /// Reads the next frame. Returns None if the stream ends before a frame was
+    /// started. A frame longer than `max_size` fails with `ErrorKind::InvalidData`,
+    /// without reading its payload.
+    pub fn read_from<R: Read>(reader: &mut R, max_size: u32) -> io::Result<Option<Frame>> {
+        let mut header = [0u8; FRAME_HEADER_LEN];
+        match reader.read(&mut header[..1]) {
+            Ok(0) => return Ok(None),
+            Ok(_) => (),
+            Err(e) => return Err(e),
+        }
+        reader.read_exact(&mut header[1..])?;
+        let len = u32::from_be_bytes([0, header[0], header[1], header[2]]);
+        if len > max_size {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("frame of {} bytes exceeds the maximum frame size", len),
+            ));
+        }
+        let mut payload = vec![0u8; len as usize];
+        reader.read_exact(&mut payload)?;
+        Ok(Some(Frame {
+            kind: FrameType::from(header[3]),
+            flags: header[4],
+            // the reserved bit is ignored:
+            stream_id: u32::from_be_bytes([header[5], header[6], header[7], header[8]])
+                & MAX_WINDOW_SIZE,
+            payload,
+        }))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let len = (self.payload.len() as u32).to_be_bytes();
+        let mut bytes = Vec::with_capacity(FRAME_HEADER_LEN + self.payload.len());
+        bytes.extend_from_slice(&len[1..]);
+        bytes.push(self.kind.code());
+        bytes.push(self.flags);
+        bytes.extend_from_slice(&self.stream_id.to_be_bytes());
+        bytes.extend_from_slice(&self.payload);
+        bytes
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<usize> {
+        let bytes = self.to_bytes();
+        writer.write_all(&bytes)?;
+        Ok(bytes.len())
+    }
+
+    /// The payload of a DATA or HEADERS frame without the padding (and for
+    /// HEADERS, without the priority fields). None if the padding is invalid.
+    pub fn content(&self) -> Option<&[u8]> {
+        let mut payload = &self.payload[..];
+        let mut pad_len = 0;
+        if self.has_flag(FLAG_PADDED) {
+            pad_len = *payload.first()? as usize;
+            payload = &payload[1..];
+        }
+        if self.kind == FrameType::Headers && self.has_flag(FLAG_PRIORITY) {
+            payload = payload.get(5..)?;
+        }
+        // the padding must be shorter than the remaining payload:
+        if pad_len > payload.len() {
+            return None;
+        }
+        Some(&payload[..payload.len() - pad_len])
+    }
+}
+
+/// Parses the payload of a SETTINGS frame (or of the `HTTP2-Settings` header)
+/// into (identifier, value) pairs. None if the length is not a multiple of 6.
+pub fn parse_settings(payload: &[u8]) -> Option<Vec<(u16, u32)>> {
+    if !payload.len().is_multiple_of(6) {
+        return None;
+    }
+    Some(
+        payload
+            .chunks(6)
+            .map(|s| {
+                (
+                    u16::from_be_bytes([s[0], s[1]]),
+                    u32::from_be_bytes([s[2], s[3], s[4], s[5]]),
+                )
+            })
+            .collect(),
+    )
+}
@@ -0,0 +1,316 @@
+//! HPACK header compression (RFC 7541): the decoder keeps the dynamic table
+//! in sync with the client's encoder, the encoder of the responses uses the
+//! static table and Huffman coding only.
+
+use std::collections::VecDeque;
+
+use super::huffman;
+
+/// The static table (RFC 7541, Appendix A), index 1 to 61.
+const STATIC_TABLE: [(&str, &str); 61] = [
+    (":authority", ""),
+    (":method", "GET"),
+    (":method", "POST"),
+    (":path", "/"),
+    (":path", "/index.html"),
+    (":scheme", "http"),
+    (":scheme", "https"),
+    (":status", "200"),
+    (":status", "204"),
+    (":status", "206"),
+    (":status", "304"),
+    (":status", "400"),
+    (":status", "404"),
+    (":status", "500"),
+    ("accept-charset", ""),
+    ("accept-encoding", "gzip, deflate"),
+    ("accept-language", ""),
+    ("accept-ranges", ""),
+    ("accept", ""),
+    ("access-control-allow-origin", ""),
+    ("age", ""),
+    ("allow", ""),
+    ("authorization", ""),
+    ("cache-control", ""),
+    ("content-disposition", ""),
+    ("content-encoding", ""),
+    ("content-language", ""),
+    ("content-length", ""),
+    ("content-location", ""),
+    ("content-range", ""),
+    ("content-type", ""),
+    ("cookie", ""),
+    ("date", ""),
+    ("etag", ""),
+    ("expect", ""),
+    ("expires", ""),
+    ("from", ""),
+    ("host", ""),
+    ("if-match", ""),
+    ("if-modified-since", ""),
+    ("if-none-match", ""),
+    ("if-range", ""),
+    ("if-unmodified-since", ""),
+    ("last-modified", ""),
+    ("link", ""),
+    ("location", ""),
+    ("max-forwards", ""),
+    ("proxy-authenticate", ""),
+    ("proxy-authorization", ""),
+    ("range", ""),
+    ("referer", ""),
+    ("refresh", ""),
+    ("retry-after", ""),
+    ("server", ""),
+    ("set-cookie", ""),
+    ("strict-transport-security", ""),
+    ("transfer-encoding", ""),
+    ("user-agent", ""),
+    ("vary", ""),
+    ("via", ""),
+    ("www-authenticate", ""),
+];
+
+/// Default size of the dynamic table, as long as no other size is announced
+/// with SETTINGS_HEADER_TABLE_SIZE.
+pub const DEFAULT_TABLE_SIZE: usize = 4096;
+
+/// Size of a header in the dynamic table and in the header list (RFC 7541, 4.1).
+pub fn entry_size(name: &str, value: &str) -> usize {
+    name.len() + value.len() + 32
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// the header block is invalid: a connection error of type COMPRESSION_ERROR
+    Compression(String),
+    /// the decoded header list exceeds the limit. The block was still processed
+    /// completely, so the dynamic table is intact and only the stream fails.
+    ListTooLarge,
+}
+
+pub struct Decoder {
+    table: VecDeque<(String, String)>,
+    table_size: usize,
+    /// current maximum table size, set by the encoder with a size update
+    max_table_size: usize,
+    /// upper limit for the size updates, our SETTINGS_HEADER_TABLE_SIZE
+    settings_table_size: usize,
+    max_list_size: usize,
+}
+
+impl Decoder {
+    pub fn new(settings_table_size: usize, max_list_size: usize) -> Decoder {
+        Decoder {
+            table: VecDeque::new(),
+            table_size: 0,
+            max_table_size: settings_table_size,
+            settings_table_size,
+            max_list_size,
+        }
+    }
+
+    /// Decodes a complete header block (the fragments of HEADERS and CONTINUATION
+    /// frames joined) into the list of header names and values.
+    pub fn decode(&mut self, block: &[u8]) -> Result<Vec<(String, String)>, DecodeError> {
+        let mut headers = Vec::new();
+        let mut list_size = 0;
+        let mut pos = 0;
+        while pos < block.len() {
+            let byte = block[pos];
+            let (name, value) = if byte & 0x80 != 0 {
+                // indexed header field
+                let index = decode_int(block, &mut pos, 7)?;
+                self.entry(index)?
+            } else if byte & 0x40 != 0 {
+                // literal with incremental indexing
+                let (name, value) = self.decode_literal(block, &mut pos, 6)?;
+                self.insert(name.clone(), value.clone());
+                (name, value)
+            } else if byte & 0x20 != 0 {
+                // dynamic table size update, only allowed at the start of a block
+                if !headers.is_empty() || list_size > 0 {
+                    return Err(compression_error("table size update after a header"));
+                }
+                let size = decode_int(block, &mut pos, 5)?;
+                if size > self.settings_table_size {
+                    return Err(compression_error("table size update above the limit"));
+                }
+                self.max_table_size = size;
+                self.evict(0);
+                continue;
+            } else {
+                // literal without indexing (0000) or never indexed (0001)
+                self.decode_literal(block, &mut pos, 4)?
+            };
+            list_size += entry_size(&name, &value);
+            if list_size <= self.max_list_size {
+                headers.push((name, value));
+            }
+        }
+        match list_size <= self.max_list_size {
+            true => Ok(headers),
+            false => Err(DecodeError::ListTooLarge),
+        }
+    }
+
+    fn decode_literal(
+        &self,
+        block: &[u8],
+        pos: &mut usize,
+        prefix_bits: u8,
+    ) -> Result<(String, String), DecodeError> {
+        let name = match decode_int(block, pos, prefix_bits)? {
+            0 => decode_string(block, pos)?,
+            index => self.entry(index)?.0,
+        };
+        let value = decode_string(block, pos)?;
+        Ok((name, value))
+    }
+
+    fn entry(&self, index: usize) -> Result<(String, String), DecodeError> {
+        match index {
+            0 => Err(compression_error("index 0")),
+            1..=61 => {
+                let (name, value) = STATIC_TABLE[index - 1];
+                Ok((String::from(name), String::from(value)))
+            }
+            _ => self
+                .table
+                .get(index - 62)
+                .cloned()
+                .ok_or_else(|| compression_error("index beyond the dynamic table")),
+        }
+    }
+
+    /// Adds an entry, evicting the oldest ones to make room. An entry larger than
+    /// the table just empties it (RFC 7541, 4.4).
+    fn insert(&mut self, name: String, value: String) {
+        let size = entry_size(&name, &value);
+        self.evict(size);
+        if size <= self.max_table_size {
+            self.table_size += size;
+            self.table.push_front((name, value));
+        }
+    }
+
+    /// Evicts the oldest entries until `room` bytes are free.
+    fn evict(&mut self, room: usize) {
+        while self.table_size + room > self.max_table_size {
+            match self.table.pop_back() {
+                Some((name, value)) => self.table_size -= entry_size(&name, &value),
+                None => break,
+            }
+        }
+    }
+}
+
+/// Encodes the response headers. Only the static table is used (no dynamic
+/// table state to keep in sync), the strings are Huffman coded where shorter.
+pub struct Encoder;
+
+impl Encoder {
+    pub fn new() -> Encoder {
+        Encoder
+    }
+
+    /// Encodes the headers, the names must be lower case.
+    pub fn encode<'a, I>(&self, headers: I) -> Vec<u8>
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        let mut block = Vec::new();
+        for (name, value) in headers {
+            let full_match = STATIC_TABLE.iter().position(|e| *e == (name, value));
+            if let Some(idx) = full_match {
+                encode_int(&mut block, 0x80, 7, idx + 1);
+                continue;
+            }
+            // literal without indexing:
+            match STATIC_TABLE.iter().position(|(n, _)| *n == name) {
+                Some(idx) => encode_int(&mut block, 0x00, 4, idx + 1),
+                None => {
+                    block.push(0x00);
+                    encode_string(&mut block, name.as_bytes());
+                }
+            }
+            encode_string(&mut block, value.as_bytes());
+        }
+        block
+    }
+}
+
+impl Default for Encoder {
+    fn default() -> Self {
+        Encoder::new()
+    }
+}
+
+/// Decodes an integer with an n-bit prefix (RFC 7541, 5.1), moving the position
+/// behind it.
+pub fn decode_int(block: &[u8], pos: &mut usize, prefix_bits: u8) -> Result<usize, DecodeError> {
+    let truncated = || compression_error("truncated integer");
+    let max_prefix = (1usize << prefix_bits) - 1;
+    let mut value = *block.get(*pos).ok_or_else(truncated)? as usize & max_prefix;
+    *pos += 1;
+    if value < max_prefix {
+        return Ok(value);
+    }
+    let mut shift = 0;
+    loop {
+        let byte = *block.get(*pos).ok_or_else(truncated)?;
+        *pos += 1;
+        if shift > 28 {
+            return Err(compression_error("integer overflow"));
+        }
+        value += ((byte & 0x7f) as usize) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+}
+
+/// Encodes an integer with an n-bit prefix, the first byte starting with the given flags.
+pub fn encode_int(out: &mut Vec<u8>, flags: u8, prefix_bits: u8, value: usize) {
+    let max_prefix = (1usize << prefix_bits) - 1;
+    if value < max_prefix {
+        out.push(flags | value as u8);
+        return;
+    }
+    out.push(flags | max_prefix as u8);
+    let mut rest = value - max_prefix;
+    while rest >= 0x80 {
+        out.push((rest & 0x7f) as u8 | 0x80);
+        rest >>= 7;
+    }
+    out.push(rest as u8);
+}
+
+fn decode_string(block: &[u8], pos: &mut usize) -> Result<String, DecodeError> {
+    let huffman_coded = block.get(*pos).is_some_and(|b| b & 0x80 != 0);
+    let len = decode_int(block, pos, 7)?;
+    let bytes = block
+        .get(*pos..*pos + len)
+        .ok_or_else(|| compression_error("truncated string"))?;
+    *pos += len;
+    let bytes = match huffman_coded {
+        true => huffman::decode(bytes).map_err(DecodeError::Compression)?,
+        false => bytes.to_vec(),
+    };
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn encode_string(out: &mut Vec<u8>, bytes: &[u8]) {
+    if huffman::encoded_len(bytes) < bytes.len() {
+        encode_int(out, 0x80, 7, huffman::encoded_len(bytes));
+        out.extend(huffman::encode(bytes));
+    } else {
+        encode_int(out, 0x00, 7, bytes.len());
+        out.extend_from_slice(bytes);
+    }
+}
+
+fn compression_error(msg: &str) -> DecodeError {
+    DecodeError::Compression(String::from(msg))
+}
@@ -0,0 +1,137 @@
+//! The static Huffman code of HPACK (RFC 7541, Appendix B). The code is
+//! canonical: within each code length the codes are consecutive, ordered by
+//! symbol, so the code lengths are enough to rebuild the whole table.
+
+use std::sync::OnceLock;
+
+/// Code length in bits of each symbol, the bytes 0..=255 and EOS (256).
+#[rustfmt::skip]
+const CODE_LENGTHS: [u8; 257] = [
+    13, 23, 28, 28, 28, 28, 28, 28, 28, 24, 30, 28, 28, 30, 28, 28,
+    28, 28, 28, 28, 28, 28, 30, 28, 28, 28, 28, 28, 28, 28, 28, 28,
+     6, 10, 10, 12, 13,  6,  8, 11, 10, 10,  8, 11,  8,  6,  6,  6,
+     5,  5,  5,  6,  6,  6,  6,  6,  6,  6,  7,  8, 15,  6, 12, 10,
+    13,  6,  7,  7,  7,  7,  7,  7,  7,  7,  7,  7,  7,  7,  7,  7,
+     7,  7,  7,  7,  7,  7,  7,  7,  8,  7,  8, 13, 19, 13, 14,  6,
+    15,  5,  6,  5,  6,  5,  6,  6,  6,  5,  7,  7,  6,  6,  6,  5,
+     6,  7,  6,  5,  5,  6,  7,  7,  7,  7,  7, 15, 11, 14, 13, 28,
+    20, 22, 20, 20, 22, 22, 22, 23, 22, 23, 23, 23, 23, 23, 24, 23,
+    24, 24, 22, 23, 24, 23, 23, 23, 23, 21, 22, 23, 22, 23, 23, 24,
+    22, 21, 20, 22, 22, 23, 23, 21, 23, 22, 22, 24, 21, 22, 23, 23,
+    21, 21, 22, 21, 23, 22, 23, 23, 20, 22, 22, 22, 23, 22, 22, 23,
+    26, 26, 20, 19, 22, 23, 22, 25, 26, 26, 26, 27, 27, 26, 24, 25,
+    19, 21, 26, 27, 27, 26, 27, 24, 21, 21, 26, 26, 28, 27, 27, 27,
+    20, 24, 20, 21, 22, 21, 21, 23, 22, 22, 25, 25, 24, 24, 26, 23,
+    26, 27, 26, 26, 27, 27, 27, 27, 27, 28, 27, 27, 27, 27, 27, 26,
+    30,
+];
+
+const EOS: u16 = 256;
+const MAX_CODE_LENGTH: usize = 30;
+
+struct Table {
+    /// code of each symbol, right aligned
+    codes: [u32; 257],
+    /// symbols ordered by (code length, symbol)
+    symbols: Vec<u16>,
+    /// number of codes of each length
+    counts: [u32; MAX_CODE_LENGTH + 1],
+    /// first code of each length
+    first_codes: [u32; MAX_CODE_LENGTH + 1],
+    /// index in `symbols` of the first code of each length
+    offsets: [usize; MAX_CODE_LENGTH + 1],
+}
+
+fn table() -> &'static Table {
+    static TABLE: OnceLock<Table> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut symbols: Vec<u16> = (0..=EOS).collect();
+        symbols.sort_by_key(|s| (CODE_LENGTHS[*s as usize], *s));
+
+        let mut table = Table {
+            codes: [0; 257],
+            symbols,
+            counts: [0; MAX_CODE_LENGTH + 1],
+            first_codes: [0; MAX_CODE_LENGTH + 1],
+            offsets: [0; MAX_CODE_LENGTH + 1],
+        };
+        let mut code = 0u32;
+        let mut length = 0usize;
+        for (idx, symbol) in table.symbols.iter().enumerate() {
+            let symbol_length = CODE_LENGTHS[*symbol as usize] as usize;
+            if idx > 0 {
+                code += 1;
+            }
+            if symbol_length != length {
+                code <<= symbol_length - length;
+                length = symbol_length;
+                table.first_codes[length] = code;
+                table.offsets[length] = idx;
+            }
+            table.codes[*symbol as usize] = code;
+            table.counts[length] += 1;
+        }
+        table
+    })
+}
+
+/// Number of bytes the Huffman encoded input takes.
+pub fn encoded_len(input: &[u8]) -> usize {
+    let bits: usize = input
+        .iter()
+        .map(|b| CODE_LENGTHS[*b as usize] as usize)
+        .sum();
+    bits.div_ceil(8)
+}
+
+pub fn encode(input: &[u8]) -> Vec<u8> {
+    let table = table();
+    let mut out = Vec::with_capacity(encoded_len(input));
+    let mut bits = 0u64;
+    let mut bit_count = 0;
+    for byte in input {
+        bits = bits << CODE_LENGTHS[*byte as usize] | table.codes[*byte as usize] as u64;
+        bit_count += CODE_LENGTHS[*byte as usize];
+        while bit_count >= 8 {
+            bit_count -= 8;
+            out.push((bits >> bit_count) as u8);
+        }
+    }
+    if bit_count > 0 {
+        // padded with the most significant bits of EOS, i.e. ones:
+        let padding = 8 - bit_count;
+        out.push((bits << padding | ((1 << padding) - 1)) as u8);
+    }
+    out
+}
+
+/// Decodes a Huffman encoded string. Fails if it contains EOS, or if the
+/// padding is longer than 7 bits or not the start of EOS (RFC 7541, 5.2).
+pub fn decode(input: &[u8]) -> Result<Vec<u8>, String> {
+    let table = table();
+    let mut out = Vec::with_capacity(input.len() * 8 / 5);
+    let mut code = 0u32;
+    let mut length = 0;
+    for byte in input {
+        for shift in (0..8).rev() {
+            code = code << 1 | (*byte as u32 >> shift & 1);
+            length += 1;
+            let idx = code.wrapping_sub(table.first_codes[length]);
+            if code >= table.first_codes[length] && idx < table.counts[length] {
+                let symbol = table.symbols[table.offsets[length] + idx as usize];
+                if symbol == EOS {
+                    return Err(String::from("EOS in Huffman encoded string"));
+                }
+                out.push(symbol as u8);
+                code = 0;
+                length = 0;
+            } else if length == MAX_CODE_LENGTH {
+                return Err(String::from("invalid Huffman code"));
+            }
+        }
+    }
+    if length > 7 || code != (1 << length) - 1 {
+        return Err(String::from("invalid Huffman padding"));
+    }
+    Ok(out)
+}
@@ -0,0 +1,466 @@
+#[cfg(test)]
+mod http2_test {
+    use super::super::http2::frame::*;
+    use super::super::http2::hpack::{Decoder, Encoder};
+    use super::super::http2::*;
+    use super::super::{
+        HTTPStatusCode, HttpMetrics, HttpVerb, IpFilter, Request, RequestHead, Response, Router,
+        VirtualHosts,
+    };
+    use std::io::Cursor;
+    use std::sync::{Arc, RwLock};
+
+    fn hosts() -> VirtualHosts {
+        let mut router = Router::new();
+        router.route(HttpVerb::GET, "/hello", |_| {
+            Response::text(HTTPStatusCode::Success(200), "Hello")
+        });
+        router.route(HttpVerb::POST, "/echo", |request| {
+            let body = request.body.as_deref().unwrap_or_default();
+            Response::text(HTTPStatusCode::Success(200), body)
+                .with_header("X-Host", &request.host().unwrap_or_default())
+        });
+        VirtualHosts::new(router)
+    }
+
+    fn client_input(frames: &[Frame]) -> Vec<u8> {
+        let mut input = PREFACE.to_vec();
+        for frame in frames {
+            input.extend(frame.to_bytes());
+        }
+        input
+    }
+
+    fn read_frames(bytes: &[u8]) -> Vec<Frame> {
+        let mut reader = Cursor::new(bytes);
+        let mut frames = Vec::new();
+        while let Some(frame) = Frame::read_from(&mut reader, 0xff_ffff).unwrap() {
+            frames.push(frame);
+        }
+        frames
+    }
+
+    /// Runs a prior knowledge connection with the given client frames,
+    /// returns the frames sent by the server.
+    fn exchange(frames: &[Frame]) -> Vec<Frame> {
+        exchange_bytes(client_input(frames))
+    }
+
+    fn exchange_bytes(input: Vec<u8>) -> Vec<Frame> {
+        let (hosts, metrics) = (hosts(), HttpMetrics::new());
+        let mut output = Vec::new();
+        Http2Connection::new(Cursor::new(input), &mut output, &hosts, &metrics)
+            .serve()
+            .unwrap();
+        read_frames(&output)
+    }
+
+    fn headers(id: u32, fields: &[(&str, &str)], end_stream: bool) -> Frame {
+        let block = Encoder::new().encode(fields.iter().copied());
+        Frame::headers(id, &block, end_stream, 16_384).remove(0)
+    }
+
+    fn get(id: u32, path: &str) -> Frame {
+        let fields = [(":method", "GET"), (":scheme", "http"), (":path", path)];
+        headers(id, &fields, true)
+    }
+
+    fn decode(frame: &Frame) -> Vec<(String, String)> {
+        Decoder::new(4096, usize::MAX)
+            .decode(&frame.payload)
+            .unwrap()
+    }
+
+    fn field(frame: &Frame, name: &str) -> Option<String> {
+        decode(frame)
+            .into_iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v)
+    }
+
+    /// The frames other than SETTINGS and WINDOW_UPDATE.
+    fn stream_frames(frames: Vec<Frame>) -> Vec<Frame> {
+        frames
+            .into_iter()
+            .filter(|f| f.kind != FrameType::Settings && f.kind != FrameType::WindowUpdate)
+            .collect()
+    }
+
+    fn goaway_error(frames: &[Frame]) -> Option<ErrorCode> {
+        let goaway = frames.iter().find(|f| f.kind == FrameType::GoAway)?;
+        let code = u32::from_be_bytes(goaway.payload[4..8].try_into().unwrap());
+        Some(ErrorCode::from(code))
+    }
+
+    #[test]
+    fn test_frame_round_trip() {
+        let frame = Frame::new(FrameType::Headers, FLAG_END_HEADERS, 3, b"block".to_vec());
+        let bytes = frame.to_bytes();
+        assert_eq!(&bytes[..9], &[0, 0, 5, 1, 4, 0, 0, 0, 3]);
+        assert_eq!(read_frames(&bytes), vec![frame]);
+
+        let too_long = Frame::data(1, &[0u8; 100], false).to_bytes();
+        let err = Frame::read_from(&mut Cursor::new(too_long), 50).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        let split = Frame::headers(1, &[1u8; 25], true, 10);
+        let kinds: Vec<_> = split.iter().map(|f| (f.kind, f.flags)).collect();
+        assert_eq!(
+            kinds,
+            [
+                (FrameType::Headers, FLAG_END_STREAM),
+                (FrameType::Continuation, 0),
+                (FrameType::Continuation, FLAG_END_HEADERS),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_padding() {
+        let mut padded = Frame::new(FrameType::Data, FLAG_PADDED, 1, vec![2, b'a', b'b', 0, 0]);
+        assert_eq!(padded.content(), Some(&b"ab"[..]));
+        padded.payload = vec![5, b'a', 0];
+        assert_eq!(padded.content(), None);
+
+        let mut prioritized = Frame::new(FrameType::Headers, FLAG_PRIORITY, 1, vec![0; 5]);
+        prioritized.payload.extend(b"block");
+        assert_eq!(prioritized.content(), Some(&b"block"[..]));
+    }
+
+    #[test]
+    fn test_get() {
+        let frames = exchange(&[Frame::settings(&[]), get(1, "/hello")]);
+        assert_eq!(frames[0].kind, FrameType::Settings);
+        assert!(frames.contains(&Frame::settings_ack()));
+
+        let frames = stream_frames(frames);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(
+            (frames[0].kind, frames[0].stream_id),
+            (FrameType::Headers, 1)
+        );
+        assert!(frames[0].has_flag(FLAG_END_HEADERS));
+        assert_eq!(field(&frames[0], ":status").as_deref(), Some("200"));
+        assert_eq!(field(&frames[0], "content-length").as_deref(), Some("5"));
+        assert_eq!(frames[1], Frame::data(1, b"Hello", true));
+    }
+
+    #[test]
+    fn test_multiple_streams() {
+        let frames = exchange(&[
+            Frame::settings(&[]),
+            get(1, "/hello"),
+            get(3, "/missing"),
+            get(5, "/hello"),
+        ]);
+        let statuses: Vec<_> = stream_frames(frames)
+            .iter()
+            .filter(|f| f.kind == FrameType::Headers)
+            .map(|f| (f.stream_id, field(f, ":status").unwrap()))
+            .collect();
+        assert_eq!(
+            statuses,
+            [(1, "200".into()), (3, "404".into()), (5, "200".into())]
+        );
+    }
+
+    #[test]
+    fn test_post_with_continuation_and_data() {
+        let fields = [
+            (":method", "POST"),
+            (":scheme", "http"),
+            (":path", "/echo"),
+            (":authority", "example.com:3000"),
+            ("content-length", "6"),
+        ];
+        let block = Encoder::new().encode(fields);
+        let mut client = vec![Frame::settings(&[])];
+        client.extend(Frame::headers(1, &block, false, 10));
+        client.push(Frame::data(1, b"abc", false));
+        client.push(Frame::data(1, b"def", true));
+
+        let frames = exchange(&client);
+        // the receive windows are opened again:
+        assert!(frames.contains(&Frame::window_update(0, 3)));
+        assert!(frames.contains(&Frame::window_update(1, 3)));
+        let frames = stream_frames(frames);
+        // :authority is the Host:
+        assert_eq!(field(&frames[0], "x-host").as_deref(), Some("example.com"));
+        assert_eq!(frames[1], Frame::data(1, b"abcdef", true));
+    }
+
+    #[test]
+    fn test_body_size_limit() {
+        let fields = [(":method", "POST"), (":scheme", "http"), (":path", "/echo")];
+        let client = client_input(&[
+            Frame::settings(&[]),
+            headers(1, &fields, false),
+            Frame::data(1, b"abc", false),
+            Frame::data(1, b"def", false),
+            Frame::data(1, b"g", true),
+        ]);
+        let (hosts, metrics) = (hosts(), HttpMetrics::new());
+        let mut output = Vec::new();
+        Http2Connection::new(Cursor::new(client), &mut output, &hosts, &metrics)
+            .max_body_size(4)
+            .serve()
+            .unwrap();
+        let frames = stream_frames(read_frames(&output));
+        assert_eq!(field(&frames[0], ":status").as_deref(), Some("413"));
+        // the stream is closed by the response, the rest of the body is refused:
+        assert!(frames.contains(&Frame::rst_stream(1, ErrorCode::StreamClosed)));
+    }
+
+    /// Behind a trusted proxy, the streams of a connection come from different
+    /// clients: each one is checked by the IP filter.
+    #[test]
+    fn test_ip_filter_per_stream() {
+        let forwarded = |id: u32, client: &str| {
+            let fields = [
+                (":method", "GET"),
+                (":scheme", "http"),
+                (":path", "/hello"),
+                ("x-forwarded-for", client),
+            ];
+            headers(id, &fields, true)
+        };
+        let client = client_input(&[
+            Frame::settings(&[]),
+            forwarded(1, "10.0.0.1"),
+            forwarded(3, "10.0.13.7"),
+        ]);
+        let filter = IpFilter::parse("deny 10.0.13.0/24\ntrusted-proxy 127.0.0.1").unwrap();
+        let (hosts, metrics) = (hosts(), HttpMetrics::new());
+        let mut output = Vec::new();
+        Http2Connection::new(Cursor::new(client), &mut output, &hosts, &metrics)
+            .peer_addr(Some("127.0.0.1:40000".parse().unwrap()))
+            .ip_filter(Some(Arc::new(RwLock::new(filter))))
+            .serve()
+            .unwrap();
+        let frames = stream_frames(read_frames(&output));
+        let status = |id: u32| {
+            let headers = frames
+                .iter()
+                .find(|f| f.kind == FrameType::Headers && f.stream_id == id)
+                .unwrap();
+            field(headers, ":status")
+        };
+        assert_eq!(status(1).as_deref(), Some("200"));
+        assert_eq!(status(3).as_deref(), Some("403"));
+    }
+
+    #[test]
+    fn test_flow_control() {
+        let frames = exchange(&[
+            Frame::settings(&[(SETTINGS_INITIAL_WINDOW_SIZE, 3)]),
+            get(1, "/hello"),
+            Frame::window_update(1, 10),
+        ]);
+        let frames = stream_frames(frames);
+        assert_eq!(frames[1], Frame::data(1, b"Hel", false));
+        assert_eq!(frames[2], Frame::data(1, b"lo", true));
+
+        // without a window update, the rest of the body is never sent:
+        let frames = exchange(&[
+            Frame::settings(&[(SETTINGS_INITIAL_WINDOW_SIZE, 3)]),
+            get(1, "/hello"),
+        ]);
+        assert_eq!(stream_frames(frames)[1..], [Frame::data(1, b"Hel", false)]);
+    }
+
+    #[test]
+    fn test_ping_and_unknown_frames() {
+        let frames = exchange(&[
+            Frame::settings(&[]),
+            Frame::new(FrameType::Unknown(0xfa), 0, 0, b"ignored".to_vec()),
+            Frame::new(FrameType::Ping, 0, 0, b"12345678".to_vec()),
+        ]);
+        assert!(frames.contains(&Frame::ping_ack(b"12345678")));
+        assert_eq!(goaway_error(&frames), None);
+    }
+
+    #[test]
+    fn test_malformed_request_resets_stream() {
+        let frames = exchange(&[
+            Frame::settings(&[]),
+            headers(1, &[(":method", "GET"), (":path", "/hello")], true),
+            headers(
+                3,
+                &[
+                    (":method", "GET"),
+                    (":scheme", "http"),
+                    (":path", "/hello"),
+                    ("X-Upper", "1"),
+                ],
+                true,
+            ),
+            headers(
+                5,
+                &[
+                    (":method", "GET"),
+                    (":scheme", "http"),
+                    (":path", "/hello"),
+                    ("connection", "close"),
+                ],
+                true,
+            ),
+            get(7, "/hello"),
+        ]);
+        let frames = stream_frames(frames);
+        for (idx, id) in [1, 3, 5].into_iter().enumerate() {
+            assert_eq!(frames[idx], Frame::rst_stream(id, ErrorCode::ProtocolError));
+        }
+        assert_eq!(field(&frames[3], ":status").as_deref(), Some("200"));
+        assert_eq!(goaway_error(&frames), None);
+    }
+
+    #[test]
+    fn test_connection_errors() {
+        let mut input = b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n".to_vec();
+        input.extend(Frame::settings(&[]).to_bytes());
+        assert_eq!(
+            goaway_error(&exchange_bytes(input)),
+            Some(ErrorCode::ProtocolError)
+        );
+
+        let cases = [
+            // the preface must be followed by SETTINGS:
+            (vec![get(1, "/hello")], ErrorCode::ProtocolError),
+            // streams of clients have odd ids:
+            (
+                vec![Frame::settings(&[]), get(2, "/hello")],
+                ErrorCode::ProtocolError,
+            ),
+            // a header block must not be interrupted:
+            (
+                vec![
+                    Frame::settings(&[]),
+                    Frame::new(FrameType::Headers, 0, 1, vec![0x82]),
+                    Frame::new(FrameType::Ping, 0, 0, vec![0; 8]),
+                ],
+                ErrorCode::ProtocolError,
+            ),
+            (
+                vec![
+                    Frame::settings(&[]),
+                    Frame::new(FrameType::Headers, FLAG_END_HEADERS, 1, vec![0x80]),
+                ],
+                ErrorCode::CompressionError,
+            ),
+            (
+                vec![Frame::settings(&[(SETTINGS_MAX_FRAME_SIZE, 100)])],
+                ErrorCode::ProtocolError,
+            ),
+            (
+                vec![Frame::new(FrameType::Settings, 0, 0, vec![0; 5])],
+                ErrorCode::FrameSizeError,
+            ),
+            (
+                vec![
+                    Frame::settings(&[]),
+                    Frame::window_update(0, MAX_WINDOW_SIZE),
+                ],
+                ErrorCode::FlowControlError,
+            ),
+            (
+                vec![Frame::settings(&[]), Frame::data(1, b"data", true)],
+                ErrorCode::ProtocolError,
+            ),
+            (
+                vec![Frame::settings(&[]), get(1, "/hello"), get(1, "/hello")],
+                ErrorCode::StreamClosed,
+            ),
+            (
+                vec![Frame::settings(&[]), Frame::data(0, &[0; 20_000], false)],
+                ErrorCode::FrameSizeError,
+            ),
+            // a flood of empty CONTINUATION frames:
+            (
+                [
+                    vec![
+                        Frame::settings(&[]),
+                        Frame::new(FrameType::Headers, 0, 1, vec![0x82]),
+                    ],
+                    vec![Frame::new(FrameType::Continuation, 0, 1, Vec::new()); 100],
+                ]
+                .concat(),
+                ErrorCode::EnhanceYourCalm,
+            ),
+        ];
+        for (client, expected) in cases {
+            assert_eq!(
+                goaway_error(&exchange(&client)),
+                Some(expected),
+                "{:?}",
+                client
+            );
+        }
+    }
+
+    #[test]
+    fn test_goaway_from_client() {
+        let frames = exchange(&[
+            Frame::settings(&[]),
+            get(1, "/hello"),
+            Frame::goaway(0, ErrorCode::NoError, ""),
+            // not read anymore:
+            Frame::new(FrameType::Ping, 0, 0, b"12345678".to_vec()),
+        ]);
+        assert!(!frames.iter().any(|f| f.kind == FrameType::Ping));
+    }
+
+    fn request(request_line: &str, headers: &[&str]) -> Request {
+        let lines: Vec<String> = headers.iter().map(|h| String::from(*h)).collect();
+        let head = RequestHead::parse(request_line, &lines).unwrap();
+        Request::from_head(head, None, None)
+    }
+
+    #[test]
+    fn test_detect_h2c() {
+        assert!(is_preface(&request("PRI * HTTP/2.0", &[])));
+        assert!(!is_preface(&request("PRI * HTTP/1.1", &["Host: a"])));
+
+        let upgrade = [
+            "Host: example.com",
+            "Connection: Upgrade, HTTP2-Settings",
+            "Upgrade: h2c",
+            "HTTP2-Settings: AAMAAABkAAQCAAAAAAIAAAAA",
+        ];
+        assert_eq!(
+            upgrade_settings(&request("GET / HTTP/1.1", &upgrade)),
+            Some(vec![(3, 100), (4, 0x200_0000), (2, 0)])
+        );
+        // h2 (TLS) is not upgraded, the settings are required:
+        let mut tls = upgrade;
+        tls[2] = "Upgrade: h2";
+        assert_eq!(upgrade_settings(&request("GET / HTTP/1.1", &tls)), None);
+        assert_eq!(
+            upgrade_settings(&request("GET / HTTP/1.1", &upgrade[..3])),
+            None
+        );
+        assert_eq!(upgrade_settings(&request("GET / HTTP/1.0", &upgrade)), None);
+    }
+
+    #[test]
+    fn test_serve_upgrade() {
+        let mut upgrade = request("POST /echo HTTP/1.1", &["Host: example.com"]);
+        upgrade.body = Some(String::from("upgraded"));
+        let (hosts, metrics) = (hosts(), HttpMetrics::new());
+        let mut output = Vec::new();
+        Http2Connection::new(
+            Cursor::new(client_input(&[Frame::settings(&[])])),
+            &mut output,
+            &hosts,
+            &metrics,
+        )
+        .serve_upgrade(upgrade, &[(SETTINGS_INITIAL_WINDOW_SIZE, 4)])
+        .unwrap();
+
+        let frames = stream_frames(read_frames(&output));
+        assert_eq!(field(&frames[0], ":status").as_deref(), Some("200"));
+        assert_eq!(frames[0].stream_id, 1);
+        // the initial window of the upgrade settings applies:
+        assert_eq!(frames[1], Frame::data(1, b"upgr", false));
+        assert_eq!(frames.len(), 2);
+    }
+}
@@ -1,3 +1,4 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum HTTPStatusCode {
     Info(usize),
 	Success(usize),
@@ -7,6 +8,17 @@ pub enum HTTPStatusCode {
 }
 
 impl HTTPStatusCode {
+	/// Maps a numeric status code to its class.
+	pub fn from_code(code: usize) -> HTTPStatusCode {
+		match code {
+			100..=199 => Self::Info(code),
+			200..=299 => Self::Success(code),
+			300..=399 => Self::Redirect(code),
+			400..=499 => Self::ClientError(code),
+			_ => Self::ServerError(code),
+		}
+	}
+
 	pub fn code(&self) -> usize {
 		match self {
 			Self::Info(code) => *code,
@@ -20,13 +32,51 @@ impl HTTPStatusCode {
     pub fn message(&self) -> &'static str {
         match self {
             Self::Info(100) => "Continue",
-			Self::Success(200) => "Success",
+			Self::Info(101) => "Switching Protocols",
+			Self::Success(200) => "OK",
+			Self::Success(201) => "Created",
+			Self::Success(202) => "Accepted",
+			Self::Success(203) => "Non-Authoritative Information",
+			Self::Success(204) => "No Content",
+			Self::Success(205) => "Reset Content",
+			Self::Success(206) => "Partial Content",
+			Self::Redirect(300) => "Multiple Choices",
+			Self::Redirect(301) => "Moved Permanently",
+			Self::Redirect(302) => "Found",
+			Self::Redirect(303) => "See Other",
+			Self::Redirect(304) => "Not Modified",
+			Self::Redirect(307) => "Temporary Redirect",
+			Self::Redirect(308) => "Permanent Redirect",
 			Self::ClientError(400) => "Bad Request",
 			Self::ClientError(401) => "Unauthorized",
 			Self::ClientError(402) => "Payment Required",
 			Self::ClientError(403) => "Forbidden",
 			Self::ClientError(404) => "Not Found",
+			Self::ClientError(405) => "Method Not Allowed",
+			Self::ClientError(406) => "Not Acceptable",
+			Self::ClientError(407) => "Proxy Authentication Required",
+			Self::ClientError(408) => "Request Timeout",
+			Self::ClientError(409) => "Conflict",
+			Self::ClientError(410) => "Gone",
+			Self::ClientError(411) => "Length Required",
+			Self::ClientError(412) => "Precondition Failed",
 			Self::ClientError(413) => "Payload Too Large",
+			Self::ClientError(414) => "URI Too Long",
+			Self::ClientError(415) => "Unsupported Media Type",
+			Self::ClientError(416) => "Range Not Satisfiable",
+			Self::ClientError(417) => "Expectation Failed",
+			Self::ClientError(421) => "Misdirected Request",
+			Self::ClientError(422) => "Unprocessable Content",
+			Self::ClientError(426) => "Upgrade Required",
+			Self::ClientError(428) => "Precondition Required",
+			Self::ClientError(429) => "Too Many Requests",
+			Self::ClientError(431) => "Request Header Fields Too Large",
+			Self::ServerError(500) => "Internal Server Error",
+			Self::ServerError(501) => "Not Implemented",
+			Self::ServerError(502) => "Bad Gateway",
+			Self::ServerError(503) => "Service Unavailable",
+			Self::ServerError(504) => "Gateway Timeout",
+			Self::ServerError(505) => "HTTP Version Not Supported",
 
             _ => "Unknown Error",
         }
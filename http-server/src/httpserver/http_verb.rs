@@ -0,0 +1,83 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::httpserver::HTTPStatusCode;
+
+/// The request method. Methods are case-sensitive (RFC 9110, 9.1): `GET` is
+/// the standard GET method, while `get` is an (unregistered) extension method.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HttpVerb {
+    GET,
+    HEAD,
+    POST,
+    PUT,
+    DELETE,
+    CONNECT,
+    OPTIONS,
+    TRACE,
+    PATCH,
+    /// any other valid method token, e.g. the WebDAV methods `PROPFIND`, `MKCOL` or `LOCK`
+    Extension(String),
+    /// not a valid method token
+    UNKNOWN,
+}
+
+impl HttpVerb {
+    /// Parses a method, mapping invalid method tokens to [HttpVerb::UNKNOWN].
+    pub fn from(string: &str) -> HttpVerb {
+        string.parse().unwrap_or(HttpVerb::UNKNOWN)
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            HttpVerb::GET => "GET",
+            HttpVerb::HEAD => "HEAD",
+            HttpVerb::POST => "POST",
+            HttpVerb::PUT => "PUT",
+            HttpVerb::DELETE => "DELETE",
+            HttpVerb::CONNECT => "CONNECT",
+            HttpVerb::OPTIONS => "OPTIONS",
+            HttpVerb::TRACE => "TRACE",
+            HttpVerb::PATCH => "PATCH",
+            HttpVerb::Extension(method) => method,
+            HttpVerb::UNKNOWN => "UNKNOWN",
+        }
+    }
+}
+
+impl FromStr for HttpVerb {
+    type Err = HTTPStatusCode;
+
+    /// Parses a method token, failing with 400 Bad Request for invalid tokens.
+    fn from_str(string: &str) -> Result<HttpVerb, HTTPStatusCode> {
+        Ok(match string {
+            "GET" => HttpVerb::GET,
+            "HEAD" => HttpVerb::HEAD,
+            "POST" => HttpVerb::POST,
+            "PUT" => HttpVerb::PUT,
+            "DELETE" => HttpVerb::DELETE,
+            "CONNECT" => HttpVerb::CONNECT,
+            "OPTIONS" => HttpVerb::OPTIONS,
+            "TRACE" => HttpVerb::TRACE,
+            "PATCH" => HttpVerb::PATCH,
+            s if is_token(s) => HttpVerb::Extension(String::from(s)),
+            _ => return Err(HTTPStatusCode::ClientError(400)),
+        })
+    }
+}
+
+impl Display for HttpVerb {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Checks if the string is a valid token (RFC 9110, 5.6.2), as used for
+/// method and header names.
+pub fn is_token(string: &str) -> bool {
+    !string.is_empty()
+        && string.bytes().all(|c| {
+            c.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&c)
+        })
+}
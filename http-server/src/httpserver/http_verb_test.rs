@@ -0,0 +1,41 @@
+#[cfg(test)]
+mod http_verb_test {
+    use super::super::{HTTPStatusCode, HttpVerb};
+
+    #[test]
+    fn test_standard_methods() {
+        assert_eq!("GET".parse(), Ok(HttpVerb::GET));
+        assert_eq!("OPTIONS".parse(), Ok(HttpVerb::OPTIONS));
+        assert_eq!(HttpVerb::from("PATCH"), HttpVerb::PATCH);
+    }
+
+    #[test]
+    fn test_methods_are_case_sensitive() {
+        assert_eq!(HttpVerb::from("get"), HttpVerb::Extension(String::from("get")));
+        assert_ne!(HttpVerb::from("Post"), HttpVerb::POST);
+    }
+
+    #[test]
+    fn test_extension_methods() {
+        assert_eq!(
+            "PROPFIND".parse(),
+            Ok(HttpVerb::Extension(String::from("PROPFIND")))
+        );
+        assert_eq!(HttpVerb::from("MKCOL").to_string(), "MKCOL");
+        assert_eq!(HttpVerb::from("LOCK").as_str(), "LOCK");
+    }
+
+    #[test]
+    fn test_invalid_tokens() {
+        assert_eq!("".parse::<HttpVerb>(), Err(HTTPStatusCode::ClientError(400)));
+        assert_eq!("GE T".parse::<HttpVerb>(), Err(HTTPStatusCode::ClientError(400)));
+        assert_eq!("GET(".parse::<HttpVerb>(), Err(HTTPStatusCode::ClientError(400)));
+        assert_eq!(HttpVerb::from("BRÜH"), HttpVerb::UNKNOWN);
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(HttpVerb::DELETE.to_string(), "DELETE");
+        assert_eq!(format!("{}", HttpVerb::from("PROPPATCH")), "PROPPATCH");
+    }
+}
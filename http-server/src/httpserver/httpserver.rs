@@ -1,61 +1,571 @@
-use crate::httpserver::{HTTPStatusCode, Request};
-use http_server::utils::logging::LogSeverity;
-use http_server::utils::threadpool::ThreadPool;
+use crate::httpserver::http2;
+use crate::httpserver::lifecycle::Lifecycle;
+use crate::httpserver::reload::{ConfigLoader, Reloader, SharedHosts};
+use crate::httpserver::{
+    ConnectionLimits, FilterAction, HTTPStatusCode, Health, HttpMetrics, HttpVerb, Middleware,
+    Problem, ReloadHandle, Request, Response, Router, ServerConfig, SharedIpFilter, ShutdownHandle,
+    VirtualHosts,
+};
+use crate::httpserver::test_client::{EphemeralServer, TestClient};
+use crate::utils::logging::LogSeverity;
+use crate::utils::net::{Listener, Stream};
+use crate::utils::systemd;
+use crate::utils::threadpool::ThreadPool;
 
 use std::error::Error as StdError;
-use std::fmt::{Error, Formatter};
+use std::io::{self, Read};
 use std::result::Result as StdResult;
-use std::{
-    fmt::Display,
-    io::Write,
-    net::{TcpListener, TcpStream},
-};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use std::net::{IpAddr, Shutdown, TcpListener};
+use std::path::{Path, PathBuf};
+
+/// How long the input of a rejected connection is drained before it is closed.
+const REJECT_LINGER: Duration = Duration::from_millis(500);
+
+/// Rejected connections drained at most at the same time, each by a thread of its own.
+const MAX_LINGERING: usize = 16;
+
+static LINGERING: AtomicUsize = AtomicUsize::new(0);
+
+/// How the server handles the client connections.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IoMode {
+    /// each connection is read, handled and written by a worker thread
+    #[default]
+    Blocking,
+    /// a reactor thread multiplexes all connections non-blocking, the worker
+    /// threads only handle complete requests; supports keep-alive
+    #[cfg(feature = "event-loop")]
+    EventLoop,
+}
 
 pub struct HttpServer {
     bind_addr: String,
     thread_pool: ThreadPool,
+    router: Router,
+    /// the middlewares and routes (metrics, health) for all hosts
+    shared: Router,
+    virtual_hosts: Vec<(String, Router)>,
+    limits: ConnectionLimits,
+    metrics: Arc<HttpMetrics>,
+    lifecycle: Lifecycle,
+    health: Arc<Health>,
+    ip_filter: Option<SharedIpFilter>,
+    io_mode: IoMode,
+    h2c: bool,
+    unix_sockets: Vec<PathBuf>,
+    unix_socket_mode: u32,
+    inherited_listeners: Vec<Listener>,
+    systemd_notify: bool,
+    config_loader: Option<Arc<ConfigLoader>>,
+    reload_handle: ReloadHandle,
 }
 
 impl HttpServer {
     pub fn new(bind_addr: &str) -> HttpServer {
         let tpool = ThreadPool::builder(5);
         eprintln!("Started 5 request handling threads");
+        let limits = ConnectionLimits::default();
+        let lifecycle = Lifecycle::new();
+        let health = Health::new(
+            lifecycle.clone(),
+            tpool.status(),
+            limits.max_pending_connections / 2,
+        );
         HttpServer {
             bind_addr: String::from(bind_addr),
             thread_pool: tpool,
+            router: Router::new(),
+            shared: Router::new(),
+            virtual_hosts: Vec::new(),
+            limits,
+            metrics: Arc::new(HttpMetrics::new()),
+            lifecycle,
+            health: Arc::new(health),
+            ip_filter: None,
+            io_mode: IoMode::default(),
+            h2c: false,
+            unix_sockets: Vec::new(),
+            unix_socket_mode: 0o660,
+            inherited_listeners: Vec::new(),
+            systemd_notify: false,
+            config_loader: None,
+            reload_handle: ReloadHandle::default(),
         }
     }
 
-    pub fn start(&self) -> StdResult<(), Box<dyn StdError>> {
-        let tcp_listener = TcpListener::bind(&self.bind_addr)?;
+    /// Sets the timeouts and queue limits for the client connections.
+    pub fn connection_limits(&mut self, limits: ConnectionLimits) {
+        self.limits = limits;
+    }
+
+    /// Registers a handler for the given method and path, see [Router].
+    pub fn route<F>(&mut self, method: HttpVerb, path: &str, handler: F)
+    where
+        F: Fn(&Request) -> Response + Send + Sync + 'static,
+    {
+        self.router.route(method, path, handler);
+    }
+
+    /// Serves the requests for the given host name with their own router, see
+    /// [VirtualHosts]. The routes registered on the server itself are the default
+    /// for all other hosts. The server's middlewares, its metrics and health
+    /// routes and the TRACE setting apply to all hosts.
+    ///
+    /// ```ignore
+    /// let mut api = Router::new();
+    /// api.route(HttpVerb::GET, "/users", list_users);
+    /// server.virtual_host("api.example.com", api);
+    /// server.virtual_host("*.example.com", tenant_router);
+    /// ```
+    pub fn virtual_host(&mut self, host: &str, router: Router) {
+        self.virtual_hosts.push((String::from(host), router));
+    }
+
+    /// Registers a middleware for all requests below the given path prefix, on
+    /// all virtual hosts. It runs before the middlewares of the host's router.
+    pub fn middleware<M>(&mut self, prefix: &str, middleware: M)
+    where
+        M: Middleware + 'static,
+    {
+        self.shared.middleware(prefix, middleware);
+    }
+
+    /// Exposes the server metrics in the Prometheus text format on the given path,
+    /// e.g. `/metrics`, on all virtual hosts.
+    pub fn metrics_route(&mut self, path: &str) {
+        let metrics = Arc::clone(&self.metrics);
+        let pool = self.thread_pool.status();
+        self.shared.route(HttpVerb::GET, path, move |_| {
+            Response::new(HTTPStatusCode::Success(200))
+                .with_header("Content-Type", "text/plain; version=0.0.4; charset=utf-8")
+                .with_body(metrics.render(&pool))
+        });
+    }
+
+    /// Registers the liveness and readiness routes (e.g. `/healthz` and `/readyz`),
+    /// answering with a JSON report of all checks, see [Health]. They are served
+    /// on all virtual hosts.
+    pub fn health_routes(&mut self, liveness_path: &str, readiness_path: &str) {
+        let health = Arc::clone(&self.health);
+        self.shared
+            .route(HttpVerb::GET, liveness_path, move |_| health.liveness());
+        let health = Arc::clone(&self.health);
+        self.shared
+            .route(HttpVerb::GET, readiness_path, move |_| health.readiness());
+    }
+
+    /// Adds a named check to the liveness report.
+    pub fn liveness_check<F>(&mut self, name: &str, check: F)
+    where
+        F: Fn() -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.health.add_liveness_check(name, Arc::new(check));
+    }
+
+    /// Adds a named check to the readiness report.
+    pub fn readiness_check<F>(&mut self, name: &str, check: F)
+    where
+        F: Fn() -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.health.add_readiness_check(name, Arc::new(check));
+    }
+
+    /// The server reports not-ready while more connections than this wait for a
+    /// worker thread.
+    pub fn readiness_queue_threshold(&mut self, max_queued_jobs: usize) {
+        self.health.set_max_queued_jobs(max_queued_jobs);
+    }
+
+    /// Returns a handle to shut down the running server from another thread.
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.lifecycle.shutdown_handle()
+    }
+
+    /// Filters all connections by the client address, see [IpFilter](crate::httpserver::IpFilter).
+    /// Connections are checked right after accept; for connections from trusted
+    /// proxies, the check happens after reading the `X-Forwarded-For` header.
+    pub fn ip_filter(&mut self, filter: SharedIpFilter) {
+        self.ip_filter = Some(filter);
+    }
+
+    /// Selects the connection handling, see [IoMode].
+    pub fn io_mode(&mut self, mode: IoMode) {
+        self.io_mode = mode;
+    }
 
-        eprintln!("Server started on {}", self.bind_addr);
-        for stream in tcp_listener.incoming() {
-            let stream = match stream {
-                Ok(s) => s,
+    /// Enables HTTP/2 over cleartext connections (off by default), with prior
+    /// knowledge or by `Upgrade: h2c`, see [http2](crate::httpserver::http2).
+    /// Only in the blocking [IoMode]: the worker thread serves the connection
+    /// until the client closes it, or it is idle for the header timeout.
+    pub fn enable_h2c(&mut self, enabled: bool) {
+        self.h2c = enabled;
+    }
+
+    /// Also listens on a Unix domain socket at the given path, e.g. for a reverse
+    /// proxy on the same host. To listen on a Unix domain socket only, pass `unix:`
+    /// and the path as bind address to [HttpServer::new] instead. A socket file
+    /// left over by a crashed server is removed at startup, and the file is
+    /// removed when the server stops. Only in the blocking [IoMode].
+    ///
+    /// Requests on a Unix domain socket have no `peer_addr`: the IP filter and
+    /// per-IP rate limiting need the proxy's `X-Forwarded-For` header (or a key header).
+    pub fn unix_socket(&mut self, path: &str) {
+        self.unix_sockets.push(PathBuf::from(path));
+    }
+
+    /// Sets the file permissions of the Unix domain sockets, `0o660` (owner
+    /// and group) by default.
+    pub fn unix_socket_mode(&mut self, mode: u32) {
+        self.unix_socket_mode = mode;
+    }
+
+    /// Serves on the listening sockets passed by systemd instead of binding the
+    /// bind address and the Unix domain sockets, for on-demand start and
+    /// restarts without refused connections. The sockets are taken with
+    /// [systemd::listen_fds] at the start of `main`, before
+    /// [HttpServer::new] starts the thread pool. Binds as usual if there are
+    /// none, i.e. the server is not socket-activated.
+    pub fn socket_activation(&mut self, listeners: Vec<Listener>) {
+        self.inherited_listeners = listeners;
+    }
+
+    /// Notifies systemd (`NOTIFY_SOCKET`, for `Type=notify` services) when the
+    /// server is ready to accept connections, and when it stops. Off by default.
+    pub fn systemd_notify(&mut self, enabled: bool) {
+        self.systemd_notify = enabled;
+    }
+
+    /// Loads a part of the configuration (routes, virtual hosts, worker count)
+    /// with the given loader, typically from a file: at start, and again on
+    /// SIGHUP or [ReloadHandle::reload]. The new configuration is swapped in
+    /// atomically; the listening sockets stay open, and requests in progress
+    /// finish with the previous one. If loading fails, the previous configuration
+    /// stays active and the error is logged (at start, the server does not start).
+    ///
+    /// ```ignore
+    /// server.reloadable_config(|| {
+    ///     let config = SiteConfig::load("site.conf")?;
+    ///     let mut router = Router::new();
+    ///     router.middleware("/admin", AuthMiddleware::new("admin").htpasswd_file(&config.htpasswd)?);
+    ///     router.route(HttpVerb::GET, "/*", files(config.static_root));
+    ///     Ok(ServerConfig::new(router).workers(config.workers))
+    /// });
+    /// ```
+    ///
+    /// Middleware state (e.g. of the rate limiter) starts over with each reload.
+    pub fn reloadable_config<F>(&mut self, loader: F)
+    where
+        F: Fn() -> Result<ServerConfig, String> + Send + Sync + 'static,
+    {
+        self.config_loader = Some(Arc::new(loader));
+    }
+
+    /// Returns a handle to reload the configuration from another thread, see
+    /// [HttpServer::reloadable_config].
+    pub fn reload_handle(&self) -> ReloadHandle {
+        self.reload_handle.clone()
+    }
+
+    /// Enables the answering of TRACE requests (off by default), on all virtual hosts.
+    pub fn enable_trace(&mut self, enabled: bool) {
+        self.shared.trace(enabled);
+    }
+
+    /// Accepts and handles connections until the server is shut down
+    /// (see [HttpServer::shutdown_handle]). Returns after the requests in
+    /// progress are done.
+    pub fn start(&mut self) -> StdResult<(), Box<dyn StdError>> {
+        let listeners = match self.inherited_listeners.is_empty() {
+            true => self.bind()?,
+            false => {
+                let inherited = std::mem::take(&mut self.inherited_listeners);
+                eprintln!("Using {} socket(s) passed by systemd", inherited.len());
+                inherited
+            }
+        };
+        self.serve(listeners)
+    }
+
+    /// Starts the server on a free port of the loopback interface (ignoring the
+    /// bind address), in a background thread, e.g. for end-to-end tests. The
+    /// server runs until the returned [EphemeralServer] is dropped.
+    ///
+    /// ```ignore
+    /// let server = server.spawn_ephemeral()?;
+    /// let mut stream = TcpStream::connect(server.addr())?;
+    /// ```
+    pub fn spawn_ephemeral(mut self) -> io::Result<EphemeralServer> {
+        let tcp_listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = tcp_listener.local_addr()?;
+        self.bind_addr = addr.to_string();
+        let shutdown = self.shutdown_handle();
+        let thread = thread::spawn(move || {
+            if let Err(e) = self.serve(vec![Listener::Tcp(tcp_listener)]) {
+                Self::log(&e.to_string(), LogSeverity::ERROR);
+            }
+        });
+        Ok(EphemeralServer::new(addr, shutdown, thread))
+    }
+
+    /// A client running requests in-process through the routes, virtual hosts
+    /// and middlewares registered so far (and the loaded configuration, see
+    /// [HttpServer::reloadable_config]), see [TestClient].
+    pub fn test_client(&self) -> TestClient {
+        let hosts = SharedHosts::new(self.virtual_hosts());
+        if let Some(reloader) = self.reloader(&hosts) {
+            match reloader.load() {
+                Ok((loaded, _)) => return TestClient::with_hosts(loaded),
+                Err(e) => Self::log(&e, LogSeverity::ERROR),
+            }
+        }
+        TestClient::with_hosts(self.virtual_hosts())
+    }
+
+    fn reloader(&self, hosts: &SharedHosts) -> Option<Reloader> {
+        let loader = self.config_loader.as_ref()?;
+        Some(Reloader {
+            loader: Arc::clone(loader),
+            shared: self.shared.clone(),
+            base_router: self.router.clone(),
+            base_hosts: self.virtual_hosts.clone(),
+            hosts: hosts.clone(),
+            handle: self.reload_handle.clone(),
+        })
+    }
+
+    fn virtual_hosts(&self) -> VirtualHosts {
+        let mut hosts = VirtualHosts::with_shared(self.shared.clone(), self.router.clone());
+        for (host, router) in self.virtual_hosts.iter() {
+            hosts.host(host, router.clone());
+        }
+        hosts
+    }
+
+    /// Binds the bind address (TCP, or `unix:` and a path) and the additional
+    /// Unix domain sockets.
+    fn bind(&self) -> io::Result<Vec<Listener>> {
+        let mut paths: Vec<&Path> = self.unix_sockets.iter().map(PathBuf::as_path).collect();
+        let tcp_addr = match self.bind_addr.strip_prefix("unix:") {
+            Some(path) => {
+                paths.insert(0, Path::new(path));
+                None
+            }
+            None => Some(&self.bind_addr),
+        };
+        #[cfg(feature = "event-loop")]
+        if self.io_mode == IoMode::EventLoop && !paths.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the event loop does not serve Unix domain sockets",
+            ));
+        }
+
+        let mut listeners = Vec::new();
+        if let Some(addr) = tcp_addr {
+            listeners.push(Listener::Tcp(TcpListener::bind(addr)?));
+        }
+        for path in paths {
+            match Listener::bind_unix(path, self.unix_socket_mode) {
+                Ok(listener) => listeners.push(listener),
                 Err(e) => {
-                    eprintln!("Cannot read incoming stream: {}", e);
-                    continue;
+                    listeners.iter().for_each(Listener::cleanup);
+                    return Err(e);
                 }
+            }
+        }
+        Ok(listeners)
+    }
+
+    fn serve(&mut self, listeners: Vec<Listener>) -> StdResult<(), Box<dyn StdError>> {
+        let mut addrs = Vec::new();
+        for listener in &listeners {
+            let addr = listener.local_addr()?;
+            addrs.push(addr.to_string());
+            self.lifecycle.add_local_addr(addr);
+        }
+        let hosts = SharedHosts::new(self.virtual_hosts());
+        let reloader = self.reloader(&hosts);
+        if let Some(reloader) = &reloader {
+            if let Err(e) = reloader.reload(&self.thread_pool) {
+                listeners.iter().for_each(Listener::cleanup);
+                return Err(format!("Cannot load the configuration: {}", e).into());
+            }
+        }
+        self.notify_systemd("READY=1");
+
+        #[cfg(feature = "event-loop")]
+        if self.io_mode == IoMode::EventLoop {
+            let tcp_listener = match listeners.into_iter().next() {
+                Some(Listener::Tcp(listener)) => listener,
+                _ => return Err("the event loop serves a single TCP listener".into()),
+            };
+            eprintln!("Server started on {} (event loop)", self.bind_addr);
+            let event_loop = crate::httpserver::event_loop::EventLoop {
+                hosts,
+                limits: self.limits,
+                metrics: Arc::clone(&self.metrics),
+                lifecycle: self.lifecycle.clone(),
+                ip_filter: self.ip_filter.clone(),
             };
-            self.handle_incoming_stream(stream);
+            let server = &*self;
+            let result = thread::scope(|scope| {
+                if let Some(reloader) = &reloader {
+                    scope.spawn(|| reloader.watch(&server.thread_pool, &server.lifecycle));
+                }
+                event_loop.run(tcp_listener, &server.thread_pool)
+            });
+            self.notify_systemd("STOPPING=1");
+            result?;
+            self.thread_pool.shutdown();
+            return Ok(());
         }
+
+        eprintln!("Server started on {}", addrs.join(", "));
+        // one acceptor loop per listener:
+        let server = &*self;
+        thread::scope(|scope| {
+            if let Some(reloader) = &reloader {
+                scope.spawn(|| reloader.watch(&server.thread_pool, &server.lifecycle));
+            }
+            for listener in listeners.iter().skip(1) {
+                scope.spawn(|| server.accept_loop(listener, &hosts));
+            }
+            if let Some(listener) = listeners.first() {
+                server.accept_loop(listener, &hosts);
+            }
+        });
+        listeners.iter().for_each(Listener::cleanup);
+
+        self.notify_systemd("STOPPING=1");
+        eprintln!("Server stopped accepting connections, waiting for running requests");
+        self.thread_pool.shutdown();
         Ok(())
     }
 
-    fn handle_incoming_stream(&self, stream: TcpStream) {
+    fn notify_systemd(&self, state: &str) {
+        if !self.systemd_notify {
+            return;
+        }
+        if let Err(e) = systemd::notify(state) {
+            Self::log(
+                &format!("Cannot notify systemd: {}", e),
+                LogSeverity::WARNING,
+            );
+        }
+    }
+
+    /// Accepts and handles the connections of a listener until the server is stopped.
+    fn accept_loop(&self, listener: &Listener, hosts: &SharedHosts) {
+        loop {
+            let stream = listener.accept();
+            if self.lifecycle.is_stopped() {
+                break;
+            }
+            match stream {
+                // the configuration at the time of the connection:
+                Ok(stream) => self.handle_incoming_stream(stream, hosts.current()),
+                Err(e) => eprintln!("Cannot read incoming stream: {}", e),
+            }
+        }
+    }
+
+    fn handle_incoming_stream(&self, stream: Stream, hosts: Arc<VirtualHosts>) {
+        let ip_filter = match (&self.ip_filter, stream.peer_addr()) {
+            (Some(filter), Some(peer)) => {
+                let f = filter.read().unwrap();
+                if f.is_trusted_proxy(peer.ip()) {
+                    // decided per request, by X-Forwarded-For:
+                    Some(Arc::clone(filter))
+                } else {
+                    if !f.is_allowed(peer.ip()) {
+                        Self::reject_filtered(stream, f.get_action(), &peer.ip().to_string());
+                        return;
+                    }
+                    None
+                }
+            }
+            _ => None,
+        };
+        if self.thread_pool.queued_jobs() >= self.limits.max_pending_connections {
+            Self::reject_overloaded(stream);
+            return;
+        }
+        if let Err(e) = stream.set_write_timeout(Some(self.limits.write_timeout)) {
+            Self::log(&e.to_string(), LogSeverity::ERROR);
+        }
+
+        let limits = self.limits;
+        let h2c = self.h2c;
+        let metrics = Arc::clone(&self.metrics);
+        let lifecycle = self.lifecycle.clone();
+        metrics.connection_opened();
         self.thread_pool.execute(move |thread_id| {
-            eprintln!("Thread {} handles the Request", thread_id);
+            eprintln!(
+                "Thread {} handles a connection from {}",
+                thread_id,
+                stream.peer_name()
+            );
+            let start = Instant::now();
 
             // let handler = RequestHander::from_tcp_stream(&stream);
 
-            let request = Request::from_tcp_stream(stream);
+            // keep a handle to the stream, to be able to answer a failed request:
+            let error_stream = stream.try_clone();
+            let request = Request::from_stream(stream, &limits);
+            if let Ok(request) = &request {
+                if let Some(client) = Self::denied_client(&ip_filter, request) {
+                    if let (Ok(stream), Some(filter)) = (error_stream, &ip_filter) {
+                        let action = filter.read().unwrap().get_action();
+                        Self::reject_filtered(stream, action, &client.to_string());
+                    }
+                    metrics.connection_closed();
+                    return;
+                }
+            }
             match request {
-                Ok(mut request) => request.handle(),
+                Ok(request) if h2c && Self::is_h2c(&request) => {
+                    if let Err(e) = http2::serve_h2c(request, &hosts, &metrics, &limits, ip_filter) {
+                        Self::log(&e.to_string(), LogSeverity::ERROR);
+                    }
+                }
+                Ok(mut request) => {
+                    let router = hosts.select(request.host().as_deref());
+                    let (status, bytes_written) = request.handle_in(router, &lifecycle);
+                    metrics.observe_request(
+                        request.method.as_str(),
+                        &router.route_label(&request.method, &request.url),
+                        status.code(),
+                        start.elapsed(),
+                        request.bytes_read(),
+                        bytes_written as u64,
+                    );
+                }
                 Err(e) => {
-                    Self::log(e.message(), LogSeverity::ERROR)
+                    Self::log(e.message(), LogSeverity::ERROR);
+                    let mut bytes_written = 0;
+                    if let Ok(mut stream) = error_stream {
+                        let response =
+                            Response::from(Problem::new(e)).with_header("Connection", "close");
+                        match response.write_to(&mut stream) {
+                            Ok(bytes) => bytes_written = bytes,
+                            Err(e) => Self::log(&e.to_string(), LogSeverity::ERROR),
+                        }
+                    }
+                    metrics.observe_request(
+                        HttpVerb::UNKNOWN.as_str(),
+                        "unmatched",
+                        e.code(),
+                        start.elapsed(),
+                        0,
+                        bytes_written as u64,
+                    );
                 }
             }
+            metrics.connection_closed();
 
             // from_tcp_stream takes ownership of the stream, while handle() gives it
             // back after its work is done:
@@ -70,6 +580,87 @@ impl HttpServer {
         });
     }
 
+    /// All worker threads are busy and the queue is full: instead of queueing the
+    /// connection without limit, it is answered with 503 right away and closed.
+    fn reject_overloaded(stream: Stream) {
+        Self::log(
+            "Too many pending connections, rejecting the connection",
+            LogSeverity::WARNING,
+        );
+        let response = Response::from(Problem::new(HTTPStatusCode::ServerError(503)))
+            .with_header("Retry-After", "1");
+        Self::close_rejected(stream, response);
+    }
+
+    /// The request starts an HTTP/2 connection: it is the preface (prior knowledge),
+    /// or an upgrade request.
+    fn is_h2c(request: &Request) -> bool {
+        http2::is_preface(request) || http2::upgrade_settings(request).is_some()
+    }
+
+    /// Checks the client address of a request from a trusted proxy, returns
+    /// the address if it is denied.
+    fn denied_client(ip_filter: &Option<SharedIpFilter>, request: &Request) -> Option<IpAddr> {
+        ip_filter.as_ref()?.read().unwrap().denied_client(request)
+    }
+
+    /// The client is denied by the IP filter: the connection is answered with 403,
+    /// or closed right away.
+    fn reject_filtered(stream: Stream, action: FilterAction, client: &str) {
+        Self::log(
+            &format!("Connection denied by the IP filter: {}", client),
+            LogSeverity::WARNING,
+        );
+        if action == FilterAction::Forbidden {
+            let response = Problem::new(HTTPStatusCode::ClientError(403)).into();
+            Self::close_rejected(stream, response);
+        }
+    }
+
+    /// Answers a rejected connection and closes it. Closing with an unread
+    /// request makes the client's side reset the connection, possibly before
+    /// the response is read: so the write side is shut down after the response,
+    /// and the input is drained until the client closes, for [REJECT_LINGER]
+    /// at most. This happens in a thread of its own, as the acceptor thread must
+    /// not block on a slow client; with too many of them, the response is
+    /// written non-blocking and the connection closed right away.
+    fn close_rejected(mut stream: Stream, response: Response) {
+        let response = response.with_header("Connection", "close");
+        if LINGERING.fetch_add(1, Ordering::SeqCst) >= MAX_LINGERING {
+            LINGERING.fetch_sub(1, Ordering::SeqCst);
+            if stream.set_nonblocking(true).is_ok() {
+                let _ = response.write_to(&mut stream);
+            }
+            return;
+        }
+        thread::spawn(move || {
+            let deadline = Instant::now() + REJECT_LINGER;
+            let written = stream.set_nonblocking(false).is_ok()
+                && stream.set_write_timeout(Some(REJECT_LINGER)).is_ok()
+                && response.write_to(&mut stream).is_ok()
+                && stream.shutdown(Shutdown::Write).is_ok();
+            if written {
+                Self::drain(&mut stream, deadline);
+            }
+            LINGERING.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+
+    /// Reads and discards the input until the client closes the connection,
+    /// or the deadline has passed.
+    fn drain(stream: &mut Stream, deadline: Instant) {
+        let mut buf = [0u8; 4096];
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() || stream.set_read_timeout(Some(left)).is_err() {
+                return;
+            }
+            if !matches!(stream.read(&mut buf), Ok(n) if n > 0) {
+                return;
+            }
+        }
+    }
+
     fn log(msg: &str, severity: LogSeverity) {
         eprintln!("{}: {}\n", severity, msg);
     }
@@ -0,0 +1,213 @@
+use std::fs;
+use std::io;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock, Weak};
+use std::thread;
+use std::time::Duration;
+
+use crate::httpserver::Request;
+use crate::utils::cidr::Cidr;
+use crate::utils::logging::LogSeverity;
+use crate::utils::signal;
+
+/// An IP filter shared between the server threads, replaced on reload.
+pub type SharedIpFilter = Arc<RwLock<IpFilter>>;
+
+/// What happens to a connection from a denied address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterAction {
+    /// answer with 403 Forbidden
+    Forbidden,
+    /// close the connection without a response
+    Drop,
+}
+
+/// CIDR based allow / deny lists. A client is allowed if it is not in a deny
+/// range, and - if any allow ranges are given - in one of the allow ranges.
+///
+/// The client address is the peer address of the connection, or, if the peer
+/// is a trusted proxy, the last untrusted address in `X-Forwarded-For`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IpFilter {
+    allow: Vec<Cidr>,
+    deny: Vec<Cidr>,
+    trusted_proxies: Vec<Cidr>,
+    action: FilterAction,
+}
+
+impl Default for IpFilter {
+    fn default() -> Self {
+        IpFilter::new()
+    }
+}
+
+impl IpFilter {
+    /// Creates a filter allowing everyone, answering denied clients with 403.
+    pub fn new() -> IpFilter {
+        IpFilter {
+            allow: Vec::new(),
+            deny: Vec::new(),
+            trusted_proxies: Vec::new(),
+            action: FilterAction::Forbidden,
+        }
+    }
+
+    pub fn allow(mut self, cidr: Cidr) -> IpFilter {
+        self.allow.push(cidr);
+        self
+    }
+
+    pub fn deny(mut self, cidr: Cidr) -> IpFilter {
+        self.deny.push(cidr);
+        self
+    }
+
+    /// Trusts the `X-Forwarded-For` header of requests from these addresses.
+    pub fn trusted_proxy(mut self, cidr: Cidr) -> IpFilter {
+        self.trusted_proxies.push(cidr);
+        self
+    }
+
+    pub fn action(mut self, action: FilterAction) -> IpFilter {
+        self.action = action;
+        self
+    }
+
+    pub fn get_action(&self) -> FilterAction {
+        self.action
+    }
+
+    /// Parses a filter definition, one rule per line, `#` starts a comment:
+    ///
+    /// ```text
+    /// allow 10.0.0.0/8
+    /// allow fd00::/8
+    /// deny 10.0.13.0/24
+    /// trusted-proxy 127.0.0.1
+    /// action drop          # or: action 403
+    /// ```
+    pub fn parse(config: &str) -> Result<IpFilter, String> {
+        let mut filter = IpFilter::new();
+        for (nr, line) in config.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (keyword, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let value = value.trim();
+            let err = |msg: String| format!("line {}: {}", nr + 1, msg);
+            filter = match keyword {
+                "allow" => filter.allow(value.parse().map_err(err)?),
+                "deny" => filter.deny(value.parse().map_err(err)?),
+                "trusted-proxy" => filter.trusted_proxy(value.parse().map_err(err)?),
+                "action" => filter.action(match value {
+                    "403" | "forbidden" => FilterAction::Forbidden,
+                    "drop" => FilterAction::Drop,
+                    _ => return Err(err(format!("unknown action: {}", value))),
+                }),
+                _ => return Err(err(format!("unknown rule: {}", keyword))),
+            };
+        }
+        Ok(filter)
+    }
+
+    /// Reads the filter definition from a file, see [IpFilter::parse].
+    pub fn load(path: &Path) -> io::Result<IpFilter> {
+        let content = fs::read_to_string(path)?;
+        IpFilter::parse(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Loads the filter from the file, and reloads it whenever the process receives
+    /// SIGHUP. If the changed file is invalid, the previous filter stays active.
+    pub fn watch_file(path: &Path) -> io::Result<SharedIpFilter> {
+        let filter = Arc::new(RwLock::new(IpFilter::load(path)?));
+        signal::watch_sighup();
+        IpFilter::start_reloader(Arc::downgrade(&filter), path.to_path_buf());
+        Ok(filter)
+    }
+
+    fn start_reloader(filter: Weak<RwLock<IpFilter>>, path: PathBuf) {
+        let mut seen = signal::sighup_count();
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_millis(500));
+            let filter = match filter.upgrade() {
+                Some(f) => f,
+                None => break,
+            };
+            let count = signal::sighup_count();
+            if count == seen {
+                continue;
+            }
+            seen = count;
+            match IpFilter::load(&path) {
+                Ok(reloaded) => {
+                    *filter.write().unwrap() = reloaded;
+                    log(
+                        &format!("Reloaded IP filter from {}", path.display()),
+                        LogSeverity::INFO,
+                    );
+                }
+                Err(e) => log(
+                    &format!(
+                        "Cannot reload IP filter from {}, keeping the previous one: {}",
+                        path.display(),
+                        e
+                    ),
+                    LogSeverity::ERROR,
+                ),
+            }
+        });
+    }
+
+    pub fn is_allowed(&self, addr: IpAddr) -> bool {
+        if self.deny.iter().any(|c| c.contains(addr)) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(|c| c.contains(addr))
+    }
+
+    pub fn is_trusted_proxy(&self, addr: IpAddr) -> bool {
+        self.trusted_proxies.iter().any(|c| c.contains(addr))
+    }
+
+    /// Checks the client address of a request (see [IpFilter::client_ip]),
+    /// returns the address if it is denied.
+    pub fn denied_client(&self, request: &Request) -> Option<IpAddr> {
+        let forwarded_for = request.headers.get("x-forwarded-for");
+        let client = self.client_ip(request.peer_addr?.ip(), forwarded_for.as_deref());
+        if self.is_allowed(client) {
+            None
+        } else {
+            Some(client)
+        }
+    }
+
+    /// Determines the client address: `X-Forwarded-For` is only honoured if the
+    /// peer is a trusted proxy, and is read from the right (the entries added by
+    /// our proxies) up to the first untrusted address.
+    pub fn client_ip(&self, peer: IpAddr, forwarded_for: Option<&str>) -> IpAddr {
+        let forwarded_for = match forwarded_for {
+            Some(f) if self.is_trusted_proxy(peer) => f,
+            _ => return peer,
+        };
+        let mut client = peer;
+        for entry in forwarded_for.rsplit(',') {
+            match entry.trim().parse::<IpAddr>() {
+                Ok(addr) => {
+                    client = addr;
+                    if !self.is_trusted_proxy(addr) {
+                        break;
+                    }
+                }
+                // a garbled entry cannot be trusted any further:
+                Err(_) => break,
+            }
+        }
+        client
+    }
+}
+
+fn log(msg: &str, severity: LogSeverity) {
+    eprintln!("{}: {}\n", severity, msg);
+}
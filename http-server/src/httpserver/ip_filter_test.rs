@@ -0,0 +1,92 @@
+#[cfg(test)]
+mod ip_filter_test {
+    use super::super::{FilterAction, HttpServer, IpFilter};
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::{IpAddr, TcpStream};
+    use std::sync::{Arc, RwLock};
+    use std::thread;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    const CONFIG: &str = "
+        # internal networks only
+        allow 10.0.0.0/8
+        allow fd00::/8
+        deny 10.0.13.0/24   # guest wifi
+        trusted-proxy 127.0.0.1
+        trusted-proxy 10.0.0.2
+        action drop
+    ";
+
+    #[test]
+    fn test_allow_deny() {
+        let filter = IpFilter::parse(CONFIG).unwrap();
+        assert_eq!(filter.get_action(), FilterAction::Drop);
+        assert!(filter.is_allowed(ip("10.1.2.3")));
+        assert!(filter.is_allowed(ip("fd12::1")));
+        assert!(!filter.is_allowed(ip("10.0.13.7")));
+        assert!(!filter.is_allowed(ip("8.8.8.8")));
+
+        // no allow rules: everyone not denied is allowed
+        let filter = IpFilter::parse("deny 192.168.0.0/16").unwrap();
+        assert!(filter.is_allowed(ip("8.8.8.8")));
+        assert!(!filter.is_allowed(ip("192.168.1.1")));
+    }
+
+    #[test]
+    fn test_client_ip() {
+        let filter = IpFilter::parse(CONFIG).unwrap();
+        // untrusted peers cannot spoof their address:
+        assert_eq!(
+            filter.client_ip(ip("8.8.8.8"), Some("10.1.1.1")),
+            ip("8.8.8.8")
+        );
+        assert_eq!(
+            filter.client_ip(ip("127.0.0.1"), Some("10.1.1.1")),
+            ip("10.1.1.1")
+        );
+        assert_eq!(filter.client_ip(ip("127.0.0.1"), None), ip("127.0.0.1"));
+        // the spoofed left-most entry is ignored, the chain ends at the first untrusted hop:
+        assert_eq!(
+            filter.client_ip(ip("127.0.0.1"), Some("10.1.1.1, 8.8.8.8, 10.0.0.2")),
+            ip("8.8.8.8")
+        );
+        assert_eq!(
+            filter.client_ip(ip("127.0.0.1"), Some("garbage, 10.0.0.2")),
+            ip("10.0.0.2")
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            IpFilter::parse("allow 10.0.0.0/8\nallow nope").unwrap_err(),
+            "line 2: invalid IP address: nope"
+        );
+        assert!(IpFilter::parse("permit 10.0.0.1").is_err());
+        assert!(IpFilter::parse("action reset").is_err());
+    }
+
+    #[test]
+    fn test_forbidden_with_unread_request() {
+        let mut server = HttpServer::new("127.0.0.1:0");
+        let filter = IpFilter::parse("deny 127.0.0.1\naction 403").unwrap();
+        server.ip_filter(Arc::new(RwLock::new(filter)));
+        let server = server.spawn_ephemeral().unwrap();
+
+        let stream = TcpStream::connect(server.addr()).unwrap();
+        let mut writer = stream.try_clone().unwrap();
+        // the request is still arriving when the connection is rejected:
+        let upload = thread::spawn(move || {
+            let _ =
+                writer.write_all(b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 262144\r\n\r\n");
+            let _ = writer.write_all(&[b'x'; 262144]);
+        });
+        let mut status_line = String::new();
+        BufReader::new(stream).read_line(&mut status_line).unwrap();
+        assert_eq!(status_line, "HTTP/1.1 403 Forbidden\r\n");
+        upload.join().unwrap();
+    }
+}
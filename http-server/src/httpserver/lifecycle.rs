@@ -0,0 +1,128 @@
+use std::net::{Shutdown, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::Duration;
+
+use crate::utils::net::{ListenAddr, Stream};
+
+#[derive(Default)]
+struct LifecycleState {
+    draining: AtomicBool,
+    stopped: AtomicBool,
+    local_addrs: Mutex<Vec<ListenAddr>>,
+    streams: Mutex<Vec<Weak<OpenStream>>>,
+}
+
+/// A streaming response in progress, e.g. an
+/// [EventStream](crate::httpserver::EventStream): stopped when the server
+/// stops, so that the worker thread running it is released.
+pub(crate) struct OpenStream {
+    stopped: Arc<AtomicBool>,
+    socket: Option<Stream>,
+}
+
+impl OpenStream {
+    /// The flag the producer checks, set when the stream is stopped.
+    pub(crate) fn stopped(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.stopped)
+    }
+
+    /// Sets the flag and shuts the socket down, so that a blocked write fails.
+    fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        if let Some(socket) = &self.socket {
+            let _ = socket.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+/// Shared state of a running server: used by the acceptor loop, the
+/// readiness check and the [ShutdownHandle].
+#[derive(Clone, Default)]
+pub struct Lifecycle {
+    state: Arc<LifecycleState>,
+}
+
+impl Lifecycle {
+    pub fn new() -> Lifecycle {
+        Lifecycle::default()
+    }
+
+    /// The server is shutting down: it still answers requests, but reports not-ready.
+    pub fn is_draining(&self) -> bool {
+        self.state.draining.load(Ordering::SeqCst)
+    }
+
+    /// The server does not accept new connections anymore.
+    pub fn is_stopped(&self) -> bool {
+        self.state.stopped.load(Ordering::SeqCst)
+    }
+
+    /// Registers an address the server listens on, to wake up its acceptor
+    /// loop on shutdown.
+    pub fn add_local_addr(&self, addr: ListenAddr) {
+        self.state.local_addrs.lock().unwrap().push(addr);
+    }
+
+    /// Registers a streaming response on the socket, to be stopped when the
+    /// server stops. It is unregistered when the returned handle is dropped.
+    pub(crate) fn open_stream(&self, socket: Option<Stream>) -> Arc<OpenStream> {
+        let stream = Arc::new(OpenStream {
+            stopped: Arc::new(AtomicBool::new(false)),
+            socket,
+        });
+        let mut streams = self.state.streams.lock().unwrap();
+        streams.retain(|s| s.strong_count() > 0);
+        streams.push(Arc::downgrade(&stream));
+        drop(streams);
+        // the server may have stopped before the stream was registered:
+        if self.is_stopped() {
+            stream.stop();
+        }
+        stream
+    }
+
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        ShutdownHandle {
+            lifecycle: self.clone(),
+        }
+    }
+}
+
+/// Stops a running server gracefully, from another thread.
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    lifecycle: Lifecycle,
+}
+
+impl ShutdownHandle {
+    /// Starts the shutdown: the server reports not-ready (so a load balancer
+    /// stops sending traffic) but keeps accepting connections for the drain period.
+    /// After that, it stops accepting and ends the open streaming responses, and
+    /// `HttpServer::start` returns as soon as all requests in progress are done.
+    /// Does not block.
+    pub fn shutdown(&self, drain_period: Duration) {
+        let state = Arc::clone(&self.lifecycle.state);
+        state.draining.store(true, Ordering::SeqCst);
+
+        thread::spawn(move || {
+            thread::sleep(drain_period);
+            state.stopped.store(true, Ordering::SeqCst);
+            let streams = std::mem::take(&mut *state.streams.lock().unwrap());
+            for stream in streams.iter().filter_map(Weak::upgrade) {
+                stream.stop();
+            }
+            // wake up the acceptor loops, which block in accept():
+            for addr in state.local_addrs.lock().unwrap().iter() {
+                match addr {
+                    ListenAddr::Tcp(addr) => drop(TcpStream::connect(addr)),
+                    #[cfg(unix)]
+                    ListenAddr::Unix(path) => drop(std::os::unix::net::UnixStream::connect(path)),
+                    #[cfg(not(unix))]
+                    ListenAddr::Unix(_) => (),
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,141 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::utils::prometheus::{render_single, CounterVec, HistogramVec};
+use crate::utils::threadpool::ThreadPoolStatus;
+
+const DURATION_BUCKETS: [f64; 11] = [
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 10.0,
+];
+
+/// The server's request and connection metrics, exposed in the Prometheus
+/// text format on the route configured with `HttpServer::metrics_route`.
+///
+/// Requests are labelled with the matched route pattern (not the full URL),
+/// and extension methods with `other`, to keep the number of time series bounded.
+pub struct HttpMetrics {
+    requests: CounterVec,
+    durations: HistogramVec,
+    bytes_received: CounterVec,
+    bytes_sent: CounterVec,
+    active_connections: AtomicUsize,
+}
+
+impl Default for HttpMetrics {
+    fn default() -> Self {
+        HttpMetrics::new()
+    }
+}
+
+impl HttpMetrics {
+    pub fn new() -> HttpMetrics {
+        HttpMetrics {
+            requests: CounterVec::new(
+                "http_requests_total",
+                "Number of handled HTTP requests.",
+                &["method", "route", "status"],
+            ),
+            durations: HistogramVec::new(
+                "http_request_duration_seconds",
+                "Time from reading the request until the response is written.",
+                &["method", "route"],
+                &DURATION_BUCKETS,
+            ),
+            bytes_received: CounterVec::new(
+                "http_request_bytes_total",
+                "Bytes received from the clients.",
+                &["method", "route"],
+            ),
+            bytes_sent: CounterVec::new(
+                "http_response_bytes_total",
+                "Bytes sent to the clients.",
+                &["method", "route"],
+            ),
+            active_connections: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn observe_request(
+        &self,
+        method: &str,
+        route: &str,
+        status: usize,
+        duration: Duration,
+        bytes_in: u64,
+        bytes_out: u64,
+    ) {
+        let method = method_label(method);
+        self.requests
+            .inc_by(&[method, route, &status.to_string()], 1);
+        self.durations
+            .observe(&[method, route], duration.as_secs_f64());
+        self.bytes_received.inc_by(&[method, route], bytes_in);
+        self.bytes_sent.inc_by(&[method, route], bytes_out);
+    }
+
+    pub fn requests_total(&self, method: &str, route: &str, status: usize) -> u64 {
+        self.requests
+            .get(&[method_label(method), route, &status.to_string()])
+    }
+
+    pub fn connection_opened(&self) {
+        self.active_connections.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn connection_closed(&self) {
+        self.active_connections.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    pub fn active_connections(&self) -> usize {
+        self.active_connections.load(Ordering::SeqCst)
+    }
+
+    pub fn render(&self, pool: &ThreadPoolStatus) -> String {
+        let mut out = String::new();
+        self.requests.render(&mut out);
+        self.durations.render(&mut out);
+        self.bytes_received.render(&mut out);
+        self.bytes_sent.render(&mut out);
+        render_single(
+            &mut out,
+            "http_active_connections",
+            "Accepted connections that are queued or being handled.",
+            "gauge",
+            self.active_connections() as f64,
+        );
+        render_single(
+            &mut out,
+            "threadpool_workers",
+            "Number of request handling threads.",
+            "gauge",
+            pool.nr_of_threads() as f64,
+        );
+        render_single(
+            &mut out,
+            "threadpool_busy_workers",
+            "Request handling threads currently busy.",
+            "gauge",
+            pool.busy_workers() as f64,
+        );
+        render_single(
+            &mut out,
+            "threadpool_queued_jobs",
+            "Connections waiting for a free request handling thread.",
+            "gauge",
+            pool.queued_jobs() as f64,
+        );
+        out
+    }
+}
+
+/// The label of a request method: the standard methods by name, all others
+/// as `other`, as a client could otherwise create a new time series with
+/// each request.
+fn method_label(method: &str) -> &str {
+    match method {
+        "GET" | "HEAD" | "POST" | "PUT" | "DELETE" | "CONNECT" | "OPTIONS" | "TRACE" | "PATCH" => {
+            method
+        }
+        _ => "other",
+    }
+}
@@ -0,0 +1,24 @@
+#[cfg(test)]
+mod metrics_test {
+    use super::super::HttpMetrics;
+    use crate::utils::threadpool::ThreadPool;
+    use std::time::Duration;
+
+    #[test]
+    fn test_extension_methods_are_other() {
+        let metrics = HttpMetrics::new();
+        for method in ["GET", "PROPFIND", "get", "X-RANDOM-1"] {
+            metrics.observe_request(method, "/", 200, Duration::ZERO, 0, 0);
+        }
+        assert_eq!(metrics.requests_total("GET", "/", 200), 1);
+        assert_eq!(metrics.requests_total("other", "/", 200), 3);
+        assert_eq!(metrics.requests_total("MKCOL", "/", 200), 3);
+
+        let mut pool = ThreadPool::builder(1);
+        let rendered = metrics.render(&pool.status());
+        pool.shutdown();
+        assert!(rendered.contains("method=\"other\""));
+        assert!(!rendered.contains("PROPFIND"));
+        assert!(!rendered.contains("X-RANDOM-1"));
+    }
+}
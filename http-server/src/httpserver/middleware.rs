@@ -0,0 +1,49 @@
+use crate::httpserver::{Request, Response};
+
+pub mod auth;
+pub mod cache;
+pub mod cors;
+pub mod etag;
+pub mod ip_filter;
+pub mod rate_limit;
+
+pub use auth::AuthMiddleware;
+pub use cache::{CacheMiddleware, ResponseCache};
+pub use cors::CorsMiddleware;
+pub use etag::EtagMiddleware;
+pub use ip_filter::IpFilterMiddleware;
+pub use rate_limit::RateLimitMiddleware;
+
+/// A middleware wraps the request handling: it can inspect the request
+/// and answer it directly (e.g. deny access), or pass it on to the next
+/// middleware / the route handler and alter the response afterwards.
+pub trait Middleware: Send + Sync {
+    fn handle(&self, request: &Request, next: Next) -> Response;
+}
+
+/// The remaining chain of middlewares, ending with the route handler.
+pub struct Next<'a> {
+    middlewares: &'a [&'a dyn Middleware],
+    endpoint: &'a dyn Fn(&Request) -> Response,
+}
+
+impl<'a> Next<'a> {
+    pub fn new(
+        middlewares: &'a [&'a dyn Middleware],
+        endpoint: &'a dyn Fn(&Request) -> Response,
+    ) -> Next<'a> {
+        Next {
+            middlewares,
+            endpoint,
+        }
+    }
+
+    /// Passes the request on to the next middleware in the chain,
+    /// or finally to the route handler.
+    pub fn run(self, request: &Request) -> Response {
+        match self.middlewares.split_first() {
+            Some((middleware, rest)) => middleware.handle(request, Next::new(rest, self.endpoint)),
+            None => (self.endpoint)(request),
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Result;
+use std::path::Path;
+
+use crate::httpserver::{HTTPStatusCode, Middleware, Next, Problem, Request, Response};
+use crate::utils::logging::LogSeverity;
+use crate::utils::{base64, sha1};
+
+/// Protects routes with HTTP Basic authentication (against htpasswd entries)
+/// and / or static Bearer tokens. Register it for a route prefix:
+///
+/// ```ignore
+/// let auth = AuthMiddleware::new("internal")
+///     .htpasswd_file("/etc/http-server/htpasswd")?
+///     .bearer_tokens_file("/etc/http-server/tokens")?;
+/// server.middleware("/internal", auth);
+/// ```
+///
+/// Supported htpasswd hashes are `{SHA}` (base64 encoded SHA-1) and, with the
+/// `bcrypt` feature enabled, bcrypt (`$2y$`, `$2a$`, `$2b$`).
+pub struct AuthMiddleware {
+    /// the realm as quoted-string, for the challenges
+    realm: String,
+    users: HashMap<String, String>,
+    bearer_tokens: Vec<String>,
+}
+
+enum Credentials {
+    Basic(String, String),
+    Bearer(String),
+    Invalid,
+}
+
+impl AuthMiddleware {
+    /// Panics if the realm contains control characters (other than tab), e.g.
+    /// CR or LF, which cannot be sent in the `WWW-Authenticate` header.
+    pub fn new(realm: &str) -> AuthMiddleware {
+        assert!(
+            !realm.chars().any(|c| c.is_ascii_control() && c != '\t'),
+            "invalid realm, control characters are not allowed: {:?}",
+            realm
+        );
+        AuthMiddleware {
+            realm: quoted_string(realm),
+            users: HashMap::new(),
+            bearer_tokens: Vec::new(),
+        }
+    }
+
+    /// Adds the users of an htpasswd file (`user:hash` per line).
+    pub fn htpasswd_file<P: AsRef<Path>>(self, path: P) -> Result<AuthMiddleware> {
+        let content = fs::read_to_string(path)?;
+        Ok(self.htpasswd(&content))
+    }
+
+    /// Adds the users from htpasswd formatted content (`user:hash` per line).
+    pub fn htpasswd(mut self, content: &str) -> AuthMiddleware {
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match line.split_once(':') {
+                Some((user, hash)) => {
+                    if !is_supported_hash(hash) {
+                        log(
+                            &format!("htpasswd: unsupported hash format for user '{}'", user),
+                            LogSeverity::WARNING,
+                        );
+                    }
+                    self.users.insert(String::from(user), String::from(hash));
+                }
+                None => log("htpasswd: ignoring invalid line", LogSeverity::WARNING),
+            }
+        }
+        self
+    }
+
+    /// Adds the Bearer tokens of a token file (one token per line).
+    pub fn bearer_tokens_file<P: AsRef<Path>>(mut self, path: P) -> Result<AuthMiddleware> {
+        let content = fs::read_to_string(path)?;
+        for line in content.lines().map(str::trim) {
+            if !line.is_empty() && !line.starts_with('#') {
+                self = self.bearer_token(line);
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn bearer_token(mut self, token: &str) -> AuthMiddleware {
+        self.bearer_tokens.push(String::from(token));
+        self
+    }
+
+    /// Checks the value of an Authorization header against the configured
+    /// users and tokens. A password is verified for unknown users, too, so that
+    /// the response time does not tell which users exist.
+    pub fn is_authorized(&self, authorization: &str) -> bool {
+        match parse_authorization(authorization) {
+            Credentials::Basic(user, password) => match self.users.get(&user) {
+                Some(hash) => verify_password(hash, &password),
+                None => {
+                    // the same work (e.g. bcrypt cost) as for a known user, the result is ignored:
+                    if let Some(hash) = self.users.values().next() {
+                        verify_password(hash, &password);
+                    }
+                    false
+                }
+            },
+            Credentials::Bearer(token) => self
+                .bearer_tokens
+                .iter()
+                .any(|t| constant_time_eq(t.as_bytes(), token.as_bytes())),
+            Credentials::Invalid => false,
+        }
+    }
+
+    fn unauthorized(&self, bearer_rejected: bool) -> Response {
+        let mut response = Response::from(Problem::new(HTTPStatusCode::ClientError(401)));
+        if !self.users.is_empty() {
+            response.headers.append(
+                "WWW-Authenticate",
+                &format!("Basic realm={}, charset=\"UTF-8\"", self.realm),
+            );
+        }
+        if !self.bearer_tokens.is_empty() {
+            let challenge = match bearer_rejected {
+                true => format!("Bearer realm={}, error=\"invalid_token\"", self.realm),
+                false => format!("Bearer realm={}", self.realm),
+            };
+            response.headers.append("WWW-Authenticate", &challenge);
+        }
+        response
+    }
+}
+
+impl Middleware for AuthMiddleware {
+    fn handle(&self, request: &Request, next: Next) -> Response {
+        match request.headers.get("Authorization") {
+            Some(authorization) if self.is_authorized(&authorization) => next.run(request),
+            Some(authorization) => {
+                log(
+                    &format!("Authentication failed for {}", request.url),
+                    LogSeverity::INFO,
+                );
+                let is_bearer = matches!(
+                    parse_authorization(&authorization),
+                    Credentials::Bearer(_)
+                );
+                self.unauthorized(is_bearer)
+            }
+            None => self.unauthorized(false),
+        }
+    }
+}
+
+fn parse_authorization(authorization: &str) -> Credentials {
+    let (scheme, value) = match authorization.trim().split_once(' ') {
+        Some((scheme, value)) => (scheme, value.trim()),
+        None => return Credentials::Invalid,
+    };
+
+    if scheme.eq_ignore_ascii_case("basic") {
+        let decoded = base64::decode(value).and_then(|bytes| String::from_utf8(bytes).ok());
+        match decoded.as_deref().and_then(|d| d.split_once(':')) {
+            Some((user, password)) => {
+                Credentials::Basic(String::from(user), String::from(password))
+            }
+            None => Credentials::Invalid,
+        }
+    } else if scheme.eq_ignore_ascii_case("bearer") {
+        Credentials::Bearer(String::from(value))
+    } else {
+        Credentials::Invalid
+    }
+}
+
+/// Quotes a parameter value as quoted-string (RFC 9110, 5.6.4).
+fn quoted_string(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn is_supported_hash(hash: &str) -> bool {
+    hash.starts_with("{SHA}") || (cfg!(feature = "bcrypt") && is_bcrypt_hash(hash))
+}
+
+fn is_bcrypt_hash(hash: &str) -> bool {
+    hash.starts_with("$2y$") || hash.starts_with("$2a$") || hash.starts_with("$2b$")
+}
+
+/// Verifies a password against an htpasswd hash.
+pub fn verify_password(hash: &str, password: &str) -> bool {
+    if let Some(sha) = hash.strip_prefix("{SHA}") {
+        let expected = base64::encode(&sha1::digest(password.as_bytes()));
+        return constant_time_eq(sha.as_bytes(), expected.as_bytes());
+    }
+    if is_bcrypt_hash(hash) {
+        return verify_bcrypt(hash, password);
+    }
+    false
+}
+
+#[cfg(feature = "bcrypt")]
+fn verify_bcrypt(hash: &str, password: &str) -> bool {
+    bcrypt::verify(password, hash).unwrap_or(false)
+}
+
+#[cfg(not(feature = "bcrypt"))]
+fn verify_bcrypt(_hash: &str, _password: &str) -> bool {
+    false
+}
+
+/// Compares two byte strings without leaking the position of the first difference via timing.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn log(msg: &str, severity: LogSeverity) {
+    eprintln!("{}: {}\n", severity, msg);
+}
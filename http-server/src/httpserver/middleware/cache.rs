@@ -0,0 +1,321 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+use crate::httpserver::router::path_has_prefix;
+use crate::httpserver::{HTTPStatusCode, HeaderMap, HttpVerb, Middleware, Next, Request, Response};
+
+/// Status codes which may be stored (the "heuristically cacheable" ones of
+/// RFC 9110, 15.1, without the ones this server never produces).
+const CACHEABLE_STATUS: [usize; 6] = [200, 203, 301, 404, 410, 501];
+
+/// Name of the response header reporting `HIT` or `MISS`.
+const CACHE_STATUS_HEADER: &str = "X-Cache";
+
+/// A stored response, together with the request header values it was
+/// selected by (`Vary`).
+struct Entry {
+    status: HTTPStatusCode,
+    headers: HeaderMap,
+    body: Vec<u8>,
+    vary: Vec<(String, Option<String>)>,
+    stored: Instant,
+    expires: Instant,
+    size: usize,
+    /// LRU clock value of the last access; atomic, so hits only need the read lock
+    last_used: AtomicU64,
+    /// the clock value the entry is listed under in the LRU order, unique
+    /// among the entries (it may be older than `last_used`)
+    listed: u64,
+}
+
+impl Entry {
+    fn matches(&self, request_headers: &HeaderMap) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, value)| request_headers.get(name) == *value)
+    }
+}
+
+#[derive(Default)]
+struct Entries {
+    map: HashMap<String, Vec<Entry>>,
+    /// the keys of the entries by their `listed` clock value; entries used
+    /// since they were listed are moved on when they come first
+    lru: BTreeMap<u64, String>,
+    size: usize,
+}
+
+impl Entries {
+    /// Removes the entries of the key for which `remove` returns true.
+    fn remove_where<F: Fn(&Entry) -> bool>(&mut self, key: &str, remove: F) {
+        let list = match self.map.get_mut(key) {
+            Some(list) => list,
+            None => return,
+        };
+        list.retain(|e| {
+            if !remove(e) {
+                return true;
+            }
+            self.size -= e.size;
+            self.lru.remove(&e.listed);
+            false
+        });
+        if list.is_empty() {
+            self.map.remove(key);
+        }
+    }
+
+    /// Removes the least recently used entry, returns false if there is none.
+    fn evict_lru(&mut self) -> bool {
+        while let Some((listed, key)) = self.lru.pop_first() {
+            let entry = self
+                .map
+                .get_mut(&key)
+                .and_then(|list| list.iter_mut().find(|e| e.listed == listed));
+            let entry = match entry {
+                Some(entry) => entry,
+                None => continue,
+            };
+            let last_used = entry.last_used.load(Ordering::Relaxed);
+            if last_used > listed {
+                // used since it was listed: list it again, by its last use
+                entry.listed = last_used;
+                self.lru.insert(last_used, key);
+                continue;
+            }
+            self.remove_where(&key, |e| e.listed == listed);
+            return true;
+        }
+        false
+    }
+}
+
+/// Thread-safe in-memory response store with LRU eviction within a memory
+/// budget. Entries are keyed by a primary key (method, host and URL) and the
+/// values of the request headers named in the response's `Vary` header.
+pub struct ResponseCache {
+    entries: RwLock<Entries>,
+    max_size: usize,
+    clock: AtomicU64,
+}
+
+impl ResponseCache {
+    /// Creates a cache holding at most `max_size` bytes (bodies, headers and keys).
+    pub fn new(max_size: usize) -> ResponseCache {
+        ResponseCache {
+            entries: RwLock::new(Entries::default()),
+            max_size,
+            clock: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns a fresh stored response for the key matching the request headers,
+    /// with an `Age` header.
+    pub fn lookup(&self, key: &str, request_headers: &HeaderMap) -> Option<Response> {
+        let entries = self.entries.read().unwrap();
+        let now = Instant::now();
+        let entry = entries
+            .map
+            .get(key)?
+            .iter()
+            .find(|e| e.expires > now && e.matches(request_headers))?;
+        entry.last_used.fetch_max(self.tick(), Ordering::Relaxed);
+
+        let mut response = Response::new(entry.status).with_body(entry.body.clone());
+        response.headers = entry.headers.clone();
+        let age = now.duration_since(entry.stored).as_secs();
+        response.headers.set("Age", &age.to_string());
+        Some(response)
+    }
+
+    /// Stores the response for `ttl`, replacing an entry with the same
+    /// `Vary` values. Least recently used entries are evicted to stay within
+    /// the memory budget. Returns false if the response cannot be stored
+    /// (`Vary: *`, or larger than the whole budget).
+    pub fn store(
+        &self,
+        key: &str,
+        request_headers: &HeaderMap,
+        response: &Response,
+        ttl: Duration,
+    ) -> bool {
+        let vary_names: Vec<String> = response
+            .headers
+            .get("vary")
+            .map(|v| v.split(',').map(|n| n.trim().to_lowercase()).collect())
+            .unwrap_or_default();
+        if vary_names.iter().any(|n| n == "*") {
+            return false;
+        }
+        let vary: Vec<(String, Option<String>)> = vary_names
+            .into_iter()
+            .filter(|n| !n.is_empty())
+            .map(|n| {
+                let value = request_headers.get(&n);
+                (n, value)
+            })
+            .collect();
+
+        let size = key.len()
+            + response.body.len()
+            + response
+                .headers
+                .iter()
+                .map(|(k, v)| k.len() + v.len())
+                .sum::<usize>();
+        if size > self.max_size {
+            return false;
+        }
+
+        let tick = self.tick();
+        let mut entries = self.entries.write().unwrap();
+        let now = Instant::now();
+        let entry = Entry {
+            status: response.status,
+            headers: response.headers.clone(),
+            body: response.body.clone(),
+            vary,
+            stored: now,
+            expires: now + ttl,
+            size,
+            last_used: AtomicU64::new(tick),
+            listed: tick,
+        };
+
+        entries.remove_where(key, |e| e.vary == entry.vary || e.expires <= now);
+        while entries.size + size > self.max_size && entries.evict_lru() {}
+        entries.size += size;
+        entries.lru.insert(entry.listed, String::from(key));
+        entries
+            .map
+            .entry(String::from(key))
+            .or_default()
+            .push(entry);
+        true
+    }
+
+    /// Total size of the stored entries, in bytes.
+    pub fn size(&self) -> usize {
+        self.entries.read().unwrap().size
+    }
+
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed) + 1
+    }
+}
+
+/// Caches responses of GET / HEAD requests in memory, according to their
+/// `Cache-Control` header (per host): `no-store` and `private` responses are never stored,
+/// `s-maxage` / `max-age` define the time to live. Per-route TTLs override the
+/// `max-age` of the responses below a path prefix. Requests with `Authorization`
+/// or `Cache-Control: no-cache` / `no-store` bypass the cache.
+///
+/// The `X-Cache` response header reports `HIT` or `MISS`.
+///
+/// ```ignore
+/// let cache = CacheMiddleware::new(16 * 1024 * 1024)
+///     .route_ttl("/reports", Duration::from_secs(300));
+/// server.middleware("/", cache);
+/// ```
+pub struct CacheMiddleware {
+    cache: ResponseCache,
+    route_ttls: Vec<(String, Duration)>,
+}
+
+impl CacheMiddleware {
+    /// Creates a cache using at most `max_size` bytes of memory.
+    pub fn new(max_size: usize) -> CacheMiddleware {
+        CacheMiddleware {
+            cache: ResponseCache::new(max_size),
+            route_ttls: Vec::new(),
+        }
+    }
+
+    /// Stores the responses below the path prefix for the given time, regardless
+    /// of their `max-age`. The longest matching prefix wins.
+    pub fn route_ttl(mut self, prefix: &str, ttl: Duration) -> CacheMiddleware {
+        self.route_ttls.push((String::from(prefix), ttl));
+        self
+    }
+
+    fn ttl_for(&self, path: &str, response: &Response) -> Option<Duration> {
+        let route_ttl = self
+            .route_ttls
+            .iter()
+            .filter(|(prefix, _)| path_has_prefix(path, prefix))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, ttl)| *ttl);
+        response_ttl(&response.headers, route_ttl)
+    }
+}
+
+/// Determines how long a response may be stored by a shared cache, or None if
+/// it must not be stored. A route TTL replaces the response's `max-age`, but
+/// `no-store`, `no-cache` and `private` are always honoured.
+pub fn response_ttl(headers: &HeaderMap, route_ttl: Option<Duration>) -> Option<Duration> {
+    if headers.contains("set-cookie") {
+        return None;
+    }
+    let cache_control = headers.get("cache-control").unwrap_or_default();
+    let mut max_age = None;
+    let mut s_maxage = None;
+    for directive in cache_control.split(',') {
+        let (name, value) = match directive.split_once('=') {
+            Some((n, v)) => (n.trim(), Some(v.trim().trim_matches('"'))),
+            None => (directive.trim(), None),
+        };
+        match name.to_lowercase().as_str() {
+            "no-store" | "no-cache" | "private" => return None,
+            "max-age" => max_age = value.and_then(|v| v.parse::<u64>().ok()),
+            "s-maxage" => s_maxage = value.and_then(|v| v.parse::<u64>().ok()),
+            _ => (),
+        }
+    }
+    route_ttl
+        .or(s_maxage.or(max_age).map(Duration::from_secs))
+        .filter(|ttl| !ttl.is_zero())
+}
+
+/// Whether the request asks to bypass stored responses.
+fn bypasses_cache(request: &Request) -> bool {
+    if request.headers.contains("authorization") {
+        return true;
+    }
+    let cache_control = request.headers.get("cache-control").unwrap_or_default();
+    cache_control.split(',').any(|d| {
+        matches!(
+            d.trim().to_lowercase().as_str(),
+            "no-cache" | "no-store" | "max-age=0"
+        )
+    })
+}
+
+impl Middleware for CacheMiddleware {
+    fn handle(&self, request: &Request, next: Next) -> Response {
+        if !matches!(request.method, HttpVerb::GET | HttpVerb::HEAD) || bypasses_cache(request) {
+            return next.run(request);
+        }
+        // HEAD is answered from the GET response, the body is never written for it:
+        let key = format!(
+            "GET {} {}",
+            request.host().unwrap_or_default(),
+            request.full_url
+        );
+        if let Some(response) = self.cache.lookup(&key, &request.headers) {
+            return response.with_header(CACHE_STATUS_HEADER, "HIT");
+        }
+
+        let response = next.run(request);
+        if request.method == HttpVerb::GET
+            && CACHEABLE_STATUS.contains(&response.status.code())
+            && !response.is_streaming()
+        {
+            if let Some(ttl) = self.ttl_for(&request.url, &response) {
+                self.cache.store(&key, &request.headers, &response, ttl);
+            }
+        }
+        response.with_header(CACHE_STATUS_HEADER, "MISS")
+    }
+}
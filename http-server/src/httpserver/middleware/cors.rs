@@ -0,0 +1,260 @@
+use crate::httpserver::{HTTPStatusCode, HttpVerb, Middleware, Next, Problem, Request, Response};
+use crate::utils::wildcard;
+
+/// An allowed CORS origin.
+pub enum Origin {
+    /// any origin (`*`)
+    Any,
+    /// exact match, e.g. `https://app.example.com`
+    Exact(String),
+    /// wildcard pattern, e.g. `https://*.example.com`
+    Wildcard(String),
+    /// regular expression matching the whole origin, e.g. `http://localhost:\d+`
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+}
+
+impl Origin {
+    /// Parses an origin definition: `*` allows all origins, a definition containing
+    /// a `*` is a wildcard pattern, everything else must match exactly.
+    pub fn parse(origin: &str) -> Origin {
+        match origin {
+            "*" => Origin::Any,
+            o if o.contains('*') => Origin::Wildcard(String::from(o)),
+            o => Origin::Exact(String::from(o)),
+        }
+    }
+
+    fn matches(&self, origin: &str) -> bool {
+        match self {
+            Origin::Any => true,
+            Origin::Exact(o) => o.eq_ignore_ascii_case(origin),
+            Origin::Wildcard(pattern) => wildcard::matches(pattern, origin),
+            #[cfg(feature = "regex")]
+            Origin::Regex(re) => re.is_match(origin),
+        }
+    }
+}
+
+/// Cross-Origin Resource Sharing: answers preflight (OPTIONS) requests and adds
+/// the `Access-Control-*` headers to the responses for allowed origins.
+///
+/// ```ignore
+/// let cors = CorsMiddleware::new()
+///     .allow_origin("http://localhost:8080")
+///     .allow_origin("https://*.example.com")
+///     .allow_methods(&[HttpVerb::GET, HttpVerb::POST, HttpVerb::DELETE])
+///     .allow_headers(&["Content-Type", "Authorization"])
+///     .allow_credentials(true)
+///     .max_age(600);
+/// server.middleware("/api", cors);
+/// ```
+pub struct CorsMiddleware {
+    origins: Vec<Origin>,
+    methods: Vec<HttpVerb>,
+    allowed_headers: Vec<String>,
+    exposed_headers: Vec<String>,
+    allow_credentials: bool,
+    max_age: Option<u64>,
+}
+
+impl Default for CorsMiddleware {
+    fn default() -> Self {
+        CorsMiddleware::new()
+    }
+}
+
+impl CorsMiddleware {
+    /// Creates a policy allowing no origins, and the CORS-safelisted methods
+    /// GET, HEAD and POST.
+    pub fn new() -> CorsMiddleware {
+        CorsMiddleware {
+            origins: Vec::new(),
+            methods: vec![HttpVerb::GET, HttpVerb::HEAD, HttpVerb::POST],
+            allowed_headers: Vec::new(),
+            exposed_headers: Vec::new(),
+            allow_credentials: false,
+            max_age: None,
+        }
+    }
+
+    /// Allows an origin, see [Origin::parse] for the format.
+    pub fn allow_origin(mut self, origin: &str) -> CorsMiddleware {
+        self.origins.push(Origin::parse(origin));
+        self
+    }
+
+    /// Allows the origins matching the regular expression. It must match the
+    /// whole origin: `https://.*\.example\.com` does not allow
+    /// `https://a.example.com.evil.org`.
+    #[cfg(feature = "regex")]
+    pub fn allow_origin_regex(mut self, pattern: &str) -> Result<CorsMiddleware, regex::Error> {
+        let anchored = format!("^(?:{})$", pattern);
+        self.origins.push(Origin::Regex(regex::Regex::new(&anchored)?));
+        Ok(self)
+    }
+
+    pub fn allow_methods(mut self, methods: &[HttpVerb]) -> CorsMiddleware {
+        self.methods = methods.to_vec();
+        self
+    }
+
+    /// Request headers the client may send. `*` allows all headers.
+    pub fn allow_headers(mut self, headers: &[&str]) -> CorsMiddleware {
+        self.allowed_headers = headers.iter().map(|h| String::from(*h)).collect();
+        self
+    }
+
+    /// Response headers the browser may expose to the client script.
+    pub fn expose_headers(mut self, headers: &[&str]) -> CorsMiddleware {
+        self.exposed_headers = headers.iter().map(|h| String::from(*h)).collect();
+        self
+    }
+
+    /// Allows requests with credentials (cookies, `Authorization`) from the
+    /// explicitly listed origins. The `*` origin never gets credentials: it is
+    /// answered with `*` and without `Access-Control-Allow-Credentials`, as
+    /// reflecting any origin would let every site read the user's data.
+    pub fn allow_credentials(mut self, allow: bool) -> CorsMiddleware {
+        self.allow_credentials = allow;
+        self
+    }
+
+    /// How long (in seconds) the browser may cache a preflight result.
+    pub fn max_age(mut self, seconds: u64) -> CorsMiddleware {
+        self.max_age = Some(seconds);
+        self
+    }
+
+    pub fn is_origin_allowed(&self, origin: &str) -> bool {
+        self.origins.iter().any(|o| o.matches(origin))
+    }
+
+    /// Builds the answer to a preflight request. A preflight for a disallowed origin,
+    /// method or header is answered with 403 and no CORS headers, so the browser
+    /// blocks the actual request.
+    pub fn preflight_response(
+        &self,
+        origin: &str,
+        request_method: &str,
+        request_headers: Option<&str>,
+    ) -> Response {
+        let mut response = Response::new(HTTPStatusCode::Success(204));
+        response.add_vary("Origin");
+        response.add_vary("Access-Control-Request-Method");
+        response.add_vary("Access-Control-Request-Headers");
+
+        let requested_headers: Vec<&str> = request_headers
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|h| !h.is_empty())
+            .collect();
+
+        if !self.is_origin_allowed(origin)
+            || !self.is_method_allowed(request_method)
+            || !requested_headers.iter().all(|h| self.is_header_allowed(h))
+        {
+            let mut denied = Response::from(
+                Problem::new(HTTPStatusCode::ClientError(403))
+                    .with_detail("CORS preflight request denied"),
+            );
+            denied.headers.set("Vary", &response.headers.get("Vary").unwrap_or_default());
+            return denied;
+        }
+
+        self.add_origin_headers(&mut response, origin);
+        let methods: Vec<&str> = self.methods.iter().map(HttpVerb::as_str).collect();
+        response
+            .headers
+            .set("Access-Control-Allow-Methods", &methods.join(", "));
+        if !requested_headers.is_empty() {
+            response
+                .headers
+                .set("Access-Control-Allow-Headers", &requested_headers.join(", "));
+        }
+        if let Some(max_age) = self.max_age {
+            response
+                .headers
+                .set("Access-Control-Max-Age", &max_age.to_string());
+        }
+        response
+    }
+
+    fn is_method_allowed(&self, method: &str) -> bool {
+        self.methods.iter().any(|m| m.as_str() == method)
+    }
+
+    fn is_header_allowed(&self, header: &str) -> bool {
+        self.allowed_headers
+            .iter()
+            .any(|h| h == "*" || h.eq_ignore_ascii_case(header))
+    }
+
+    fn allows_any_origin(&self) -> bool {
+        self.origins.iter().any(|o| matches!(o, Origin::Any))
+    }
+
+    /// Whether requests from the origin may carry credentials: only for origins
+    /// listed explicitly, not by `*`.
+    fn allows_credentials_for(&self, origin: &str) -> bool {
+        self.allow_credentials
+            && self
+                .origins
+                .iter()
+                .any(|o| !matches!(o, Origin::Any) && o.matches(origin))
+    }
+
+    /// The response varies by origin, except if every origin gets the same `*` answer.
+    fn varies_by_origin(&self) -> bool {
+        !self.allows_any_origin()
+            || (self.allow_credentials && self.origins.iter().any(|o| !matches!(o, Origin::Any)))
+    }
+
+    fn add_origin_headers(&self, response: &mut Response, origin: &str) {
+        // credentialed requests must not be answered with a wildcard origin:
+        let credentials = self.allows_credentials_for(origin);
+        let allowed_origin = match credentials || !self.allows_any_origin() {
+            true => origin,
+            false => "*",
+        };
+        response
+            .headers
+            .set("Access-Control-Allow-Origin", allowed_origin);
+        if credentials {
+            response
+                .headers
+                .set("Access-Control-Allow-Credentials", "true");
+        }
+    }
+}
+
+impl Middleware for CorsMiddleware {
+    fn handle(&self, request: &Request, next: Next) -> Response {
+        let origin = request.headers.get("Origin");
+        let preflight_method = request.headers.get("Access-Control-Request-Method");
+
+        if let (HttpVerb::OPTIONS, Some(origin), Some(method)) =
+            (&request.method, &origin, &preflight_method)
+        {
+            let headers = request.headers.get("Access-Control-Request-Headers");
+            return self.preflight_response(origin, method, headers.as_deref());
+        }
+
+        let mut response = next.run(request);
+        if self.varies_by_origin() {
+            response.add_vary("Origin");
+        }
+        if let Some(origin) = origin {
+            if self.is_origin_allowed(&origin) {
+                self.add_origin_headers(&mut response, &origin);
+                if !self.exposed_headers.is_empty() {
+                    response
+                        .headers
+                        .set("Access-Control-Expose-Headers", &self.exposed_headers.join(", "));
+                }
+            }
+        }
+        response
+    }
+}
@@ -0,0 +1,75 @@
+use crate::httpserver::conditional::{
+    etag, evaluate_preconditions, not_modified, precondition_failed, Precondition,
+};
+use crate::httpserver::{HttpVerb, Middleware, Next, Request, Response};
+use crate::utils::http_date;
+
+/// Adds an `ETag` computed from the body to successful GET / HEAD responses,
+/// and answers conditional requests (`If-None-Match`, `If-Modified-Since`,
+/// `If-Match`, `If-Unmodified-Since`) with 304 Not Modified or
+/// 412 Precondition Failed.
+///
+/// An `ETag` or `Last-Modified` header set by the handler is used as-is.
+/// The response is still generated by the handler, the middleware only saves
+/// the bandwidth. Handlers changing resources (PUT, DELETE, ...) have to check
+/// the preconditions *before* the change, using
+/// [`check_preconditions`](crate::httpserver::check_preconditions).
+///
+/// ```ignore
+/// server.middleware("/", EtagMiddleware::new());
+/// ```
+pub struct EtagMiddleware {
+    weak: bool,
+}
+
+impl Default for EtagMiddleware {
+    fn default() -> Self {
+        EtagMiddleware::new()
+    }
+}
+
+impl EtagMiddleware {
+    /// Creates a middleware generating strong ETags.
+    pub fn new() -> EtagMiddleware {
+        EtagMiddleware { weak: false }
+    }
+
+    /// Generates weak ETags (`W/"..."`), e.g. if a later stage may alter
+    /// the body bytes without changing its meaning (compression).
+    pub fn weak(mut self, weak: bool) -> EtagMiddleware {
+        self.weak = weak;
+        self
+    }
+}
+
+impl Middleware for EtagMiddleware {
+    fn handle(&self, request: &Request, next: Next) -> Response {
+        let mut response = next.run(request);
+        if !matches!(request.method, HttpVerb::GET | HttpVerb::HEAD)
+            || response.status.code() != 200
+            || response.is_streaming()
+        {
+            return response;
+        }
+
+        let tag = match response.headers.get("etag") {
+            Some(tag) => tag,
+            None => {
+                let tag = etag(&response.body, self.weak);
+                response.headers.set("ETag", &tag);
+                tag
+            }
+        };
+        let last_modified = response
+            .headers
+            .get("last-modified")
+            .and_then(|date| http_date::parse(&date));
+
+        match evaluate_preconditions(&request.method, &request.headers, Some(&tag), last_modified)
+        {
+            Precondition::Passed => response,
+            Precondition::NotModified => not_modified(&response),
+            Precondition::Failed => precondition_failed(&request.url),
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use std::sync::{Arc, RwLock};
+
+use crate::httpserver::{
+    HTTPStatusCode, IpFilter, Middleware, Next, Problem, Request, Response, SharedIpFilter,
+};
+
+/// Restricts the routes below its prefix to the clients allowed by an
+/// [IpFilter], e.g. admin routes to internal networks. The client address is
+/// taken from `X-Forwarded-For` if the peer is a trusted proxy. Denied requests
+/// are answered with 403 Forbidden, as the request was already read.
+///
+/// ```ignore
+/// let filter = IpFilter::watch_file(Path::new("/etc/myserver/admin-ips.conf"))?;
+/// server.middleware("/admin", IpFilterMiddleware::new(filter));
+/// ```
+pub struct IpFilterMiddleware {
+    filter: SharedIpFilter,
+}
+
+impl IpFilterMiddleware {
+    pub fn new(filter: SharedIpFilter) -> IpFilterMiddleware {
+        IpFilterMiddleware { filter }
+    }
+}
+
+impl From<IpFilter> for IpFilterMiddleware {
+    fn from(filter: IpFilter) -> Self {
+        IpFilterMiddleware::new(Arc::new(RwLock::new(filter)))
+    }
+}
+
+impl Middleware for IpFilterMiddleware {
+    fn handle(&self, request: &Request, next: Next) -> Response {
+        let allowed = request.peer_addr.is_some()
+            && self.filter.read().unwrap().denied_client(request).is_none();
+        if allowed {
+            next.run(request)
+        } else {
+            Problem::new(HTTPStatusCode::ClientError(403))
+                .with_detail("Access from this address is not allowed.")
+                .with_instance(&request.url)
+                .into()
+        }
+    }
+}
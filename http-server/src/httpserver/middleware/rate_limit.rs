@@ -0,0 +1,258 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::httpserver::{HTTPStatusCode, Middleware, Next, Problem, Request, Response};
+
+/// A token bucket: holds up to `capacity` tokens, refilled continuously at
+/// `capacity` tokens per `period`. Each request takes one token.
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    capacity: u32,
+    period: Duration,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    /// Creates a full bucket.
+    pub fn new(capacity: u32, period: Duration, now: Instant) -> TokenBucket {
+        TokenBucket {
+            capacity,
+            period,
+            tokens: capacity as f64,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        let rate = self.capacity as f64 / self.period.as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(self.capacity as f64);
+        self.updated = now;
+    }
+
+    /// Time until `tokens` tokens are available again.
+    fn time_until(&self, tokens: f64) -> Duration {
+        let missing = (tokens - self.tokens).max(0.0);
+        Duration::from_secs_f64(missing * self.period.as_secs_f64() / self.capacity as f64)
+    }
+
+    /// Takes a token: returns the remaining number of tokens, or the time to
+    /// wait until the next token is available.
+    pub fn try_acquire(&mut self, now: Instant) -> Result<u32, Duration> {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(self.tokens as u32)
+        } else {
+            Err(self.time_until(1.0))
+        }
+    }
+
+    /// Time until the bucket is full again.
+    pub fn reset_after(&self) -> Duration {
+        self.time_until(self.capacity as f64)
+    }
+
+    /// A bucket which is full again is in the same state as a new one,
+    /// so it can be dropped.
+    pub fn is_full(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.tokens >= self.capacity as f64
+    }
+}
+
+/// The buckets of the tracked clients, with the order of their last use.
+#[derive(Default)]
+struct Buckets {
+    /// the bucket of each client, and the LRU clock value of its last use
+    buckets: HashMap<String, (TokenBucket, u64)>,
+    /// the clients by their last use, least recently used first
+    lru: BTreeMap<u64, String>,
+    clock: u64,
+}
+
+impl Buckets {
+    /// The bucket of the client, a new one if it is not tracked yet. When
+    /// `max_clients` are tracked, the least recently used client is dropped.
+    fn get(
+        &mut self,
+        key: &str,
+        max_clients: usize,
+        new: impl FnOnce() -> TokenBucket,
+    ) -> &mut TokenBucket {
+        self.clock += 1;
+        let tick = self.clock;
+        if let Some((_, last_used)) = self.buckets.get(key) {
+            self.lru.remove(last_used);
+        } else {
+            while self.buckets.len() >= max_clients {
+                match self.lru.pop_first() {
+                    Some((_, lru_key)) => self.buckets.remove(&lru_key),
+                    None => break,
+                };
+            }
+        }
+        self.lru.insert(tick, String::from(key));
+        let (bucket, last_used) = self
+            .buckets
+            .entry(String::from(key))
+            .or_insert_with(|| (new(), tick));
+        *last_used = tick;
+        bucket
+    }
+
+    /// Drops the buckets which are full again.
+    fn remove_idle(&mut self, now: Instant) {
+        let lru = &mut self.lru;
+        self.buckets.retain(|_, (bucket, last_used)| {
+            let idle = bucket.is_full(now);
+            if idle {
+                lru.remove(last_used);
+            }
+            !idle
+        });
+    }
+}
+
+type Validator = Box<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// Limits the request rate per client with a token bucket: each client may
+/// send `capacity` requests in a burst, refilled at `capacity` per `period`.
+/// Clients are identified by their IP address, or by a request header (e.g.
+/// an API key) with `key_header`. As the client chooses the header value, it
+/// only counts once the validator has accepted it: otherwise, a client could
+/// send a new value with each request.
+///
+/// At most `max_clients` buckets are kept: for a new client beyond that, the
+/// least recently used bucket is dropped.
+///
+/// Limited requests are answered with 429 Too Many Requests and `Retry-After`,
+/// all responses get `RateLimit-Limit`, `RateLimit-Remaining` and `RateLimit-Reset`
+/// headers. Register it for a route prefix to limit that route only; each
+/// middleware instance counts separately. Idle buckets are removed by a
+/// background thread.
+///
+/// ```ignore
+/// server.middleware("/", RateLimitMiddleware::new(100, Duration::from_secs(60)));
+/// server.middleware(
+///     "/api/search",
+///     RateLimitMiddleware::new(10, Duration::from_secs(60))
+///         .key_header("X-Api-Key", move |key| api_keys.contains(key)),
+/// );
+/// ```
+pub struct RateLimitMiddleware {
+    capacity: u32,
+    period: Duration,
+    key_header: Option<(String, Validator)>,
+    max_clients: usize,
+    buckets: Arc<Mutex<Buckets>>,
+}
+
+impl RateLimitMiddleware {
+    /// Allows `capacity` requests per `period` and client. Starts the cleanup
+    /// thread, which ends when the middleware is dropped.
+    pub fn new(capacity: u32, period: Duration) -> RateLimitMiddleware {
+        let capacity = capacity.max(1);
+        let buckets = Arc::new(Mutex::new(Buckets::default()));
+        RateLimitMiddleware::start_cleanup(Arc::downgrade(&buckets), period);
+        RateLimitMiddleware {
+            capacity,
+            period,
+            key_header: None,
+            max_clients: 100_000,
+            buckets,
+        }
+    }
+
+    /// Identifies the clients by the value of the given request header, if
+    /// `is_valid` accepts it: the same key shares one limit from all addresses,
+    /// and clients behind the same address (e.g. a NAT gateway) are limited
+    /// separately. Requests without a valid key are limited by IP address.
+    pub fn key_header<F>(mut self, name: &str, is_valid: F) -> RateLimitMiddleware
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        self.key_header = Some((String::from(name), Box::new(is_valid)));
+        self
+    }
+
+    /// The maximum number of clients tracked, 100000 by default.
+    pub fn max_clients(mut self, max_clients: usize) -> RateLimitMiddleware {
+        self.max_clients = max_clients.max(1);
+        self
+    }
+
+    fn client_key(&self, request: &Request) -> String {
+        if let Some((name, is_valid)) = &self.key_header {
+            if let Some(value) = request.headers.get(name).filter(|v| is_valid(v)) {
+                return format!("key:{}", value);
+            }
+        }
+        match request.peer_addr {
+            Some(addr) => format!("ip:{}", addr.ip()),
+            None => String::from("unknown"),
+        }
+    }
+
+    fn start_cleanup(buckets: Weak<Mutex<Buckets>>, period: Duration) {
+        let interval = period.clamp(Duration::from_secs(1), Duration::from_secs(60));
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            let buckets = match buckets.upgrade() {
+                Some(b) => b,
+                None => break,
+            };
+            let now = Instant::now();
+            buckets.lock().unwrap().remove_idle(now);
+        });
+    }
+
+    /// Number of clients currently tracked.
+    pub fn tracked_clients(&self) -> usize {
+        self.buckets.lock().unwrap().buckets.len()
+    }
+}
+
+impl Middleware for RateLimitMiddleware {
+    fn handle(&self, request: &Request, next: Next) -> Response {
+        let key = self.client_key(request);
+        let now = Instant::now();
+        let (result, reset_after) = {
+            let mut buckets = self.buckets.lock().unwrap();
+            let bucket = buckets.get(&key, self.max_clients, || {
+                TokenBucket::new(self.capacity, self.period, now)
+            });
+            (bucket.try_acquire(now), bucket.reset_after())
+        };
+
+        let (response, remaining) = match result {
+            Ok(remaining) => (next.run(request), remaining),
+            Err(retry_after) => {
+                let response: Response = Problem::new(HTTPStatusCode::ClientError(429))
+                    .with_detail("Request rate limit exceeded, try again later.")
+                    .with_instance(&request.url)
+                    .into();
+                (
+                    response.with_header("Retry-After", &ceil_secs(retry_after).to_string()),
+                    0,
+                )
+            }
+        };
+        response
+            .with_header("RateLimit-Limit", &self.capacity.to_string())
+            .with_header("RateLimit-Remaining", &remaining.to_string())
+            .with_header("RateLimit-Reset", &ceil_secs(reset_after).to_string())
+    }
+}
+
+fn ceil_secs(duration: Duration) -> u64 {
+    let secs = duration.as_secs();
+    if duration.subsec_nanos() > 0 {
+        secs + 1
+    } else {
+        secs
+    }
+}
@@ -0,0 +1,157 @@
+use crate::httpserver::{HTTPStatusCode, Problem, Request, Response};
+
+/// A media range of an Accept header, e.g. `text/*;q=0.8`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaRange {
+    pub main_type: String,
+    pub sub_type: String,
+    pub params: Vec<(String, String)>,
+    pub quality: f32,
+}
+
+impl MediaRange {
+    /// Parses a media range or media type; returns None if it is malformed.
+    pub fn parse(range: &str) -> Option<MediaRange> {
+        let mut parts = range.split(';').map(str::trim);
+        let (main_type, sub_type) = parts.next()?.split_once('/')?;
+        if main_type.is_empty() || sub_type.is_empty() || (main_type == "*" && sub_type != "*") {
+            return None;
+        }
+
+        let mut media_range = MediaRange {
+            main_type: main_type.to_ascii_lowercase(),
+            sub_type: sub_type.to_ascii_lowercase(),
+            params: Vec::new(),
+            quality: 1.0,
+        };
+        for param in parts.filter(|p| !p.is_empty()) {
+            let (key, value) = param.split_once('=')?;
+            let key = key.trim().to_ascii_lowercase();
+            let value = value.trim().trim_matches('"');
+            if key == "q" {
+                media_range.quality = value.parse::<f32>().ok()?.clamp(0.0, 1.0);
+            } else {
+                media_range.params.push((key, String::from(value)));
+            }
+        }
+        Some(media_range)
+    }
+
+    /// Returns how specifically this range matches the media type (None if not at all):
+    /// `*/*` < `text/*` < `text/html` < `text/html;level=1`.
+    fn specificity(&self, media_type: &MediaRange) -> Option<usize> {
+        let matches = (self.main_type == "*" || self.main_type == media_type.main_type)
+            && (self.sub_type == "*" || self.sub_type == media_type.sub_type)
+            && self
+                .params
+                .iter()
+                .all(|param| media_type.params.contains(param));
+        if !matches {
+            return None;
+        }
+        Some(match (self.main_type.as_str(), self.sub_type.as_str()) {
+            ("*", _) => 0,
+            (_, "*") => 1,
+            _ => 2 + self.params.len(),
+        })
+    }
+}
+
+/// Parses an Accept header into its media ranges, skipping malformed ones.
+pub fn parse_accept(accept: &str) -> Vec<MediaRange> {
+    accept
+        .split(',')
+        .filter(|range| !range.trim().is_empty())
+        .filter_map(MediaRange::parse)
+        .collect()
+}
+
+/// Picks the best of the offered media types for the given Accept header value
+/// (RFC 9110, 12.5.1). Each offer gets the quality of the most specific matching
+/// media range; the offer with the highest quality wins, on a tie the one offered
+/// first. Without Accept header, the first offer is used. Returns None if no
+/// offer is acceptable.
+pub fn best_match<'a>(accept: Option<&str>, offered: &[&'a str]) -> Option<&'a str> {
+    let ranges = match accept {
+        Some(accept) if !accept.trim().is_empty() => parse_accept(accept),
+        _ => return offered.first().copied(),
+    };
+
+    let mut best: Option<(&str, f32)> = None;
+    for offer in offered {
+        let media_type = match MediaRange::parse(offer) {
+            Some(media_type) => media_type,
+            None => continue,
+        };
+        let quality = ranges
+            .iter()
+            .filter_map(|range| range.specificity(&media_type).map(|s| (s, range.quality)))
+            .max_by_key(|(specificity, _)| *specificity)
+            .map(|(_, quality)| quality)
+            .unwrap_or(0.0);
+
+        if quality > 0.0 && best.is_none_or(|(_, best_quality)| quality > best_quality) {
+            best = Some((offer, quality));
+        }
+    }
+    best.map(|(offer, _)| offer)
+}
+
+type Render<'a> = Box<dyn FnOnce() -> Response + 'a>;
+
+/// Lets a handler offer several representations of a resource, and answers
+/// with the one that fits the request's Accept header best:
+///
+/// ```ignore
+/// Representations::new()
+///     .offer("application/json", || Response::json(&users))
+///     .offer("text/csv", || Response::new(HTTPStatusCode::Success(200)).with_body(csv(&users)))
+///     .respond(request)
+/// ```
+///
+/// The response gets `Vary: Accept`, and the chosen Content-Type if the
+/// representation did not set one. If nothing is acceptable, the answer
+/// is 406 Not Acceptable.
+#[derive(Default)]
+pub struct Representations<'a> {
+    offers: Vec<(&'a str, Render<'a>)>,
+}
+
+impl<'a> Representations<'a> {
+    pub fn new() -> Representations<'a> {
+        Representations { offers: Vec::new() }
+    }
+
+    pub fn offer<F>(mut self, media_type: &'a str, render: F) -> Representations<'a>
+    where
+        F: FnOnce() -> Response + 'a,
+    {
+        self.offers.push((media_type, Box::new(render)));
+        self
+    }
+
+    pub fn respond(self, request: &Request) -> Response {
+        let offered: Vec<&str> = self.offers.iter().map(|(media_type, _)| *media_type).collect();
+        let chosen = best_match(request.headers.get("Accept").as_deref(), &offered);
+
+        let mut response = match chosen {
+            Some(chosen) => {
+                let (media_type, render) = self
+                    .offers
+                    .into_iter()
+                    .find(|(media_type, _)| *media_type == chosen)
+                    .unwrap();
+                let mut response = render();
+                if !response.headers.contains("Content-Type") {
+                    response.headers.set("Content-Type", media_type);
+                }
+                response
+            }
+            None => Problem::new(HTTPStatusCode::ClientError(406))
+                .with_detail(&format!("Available representations: {}", offered.join(", ")))
+                .into(),
+        };
+        response.add_vary("Accept");
+        response
+    }
+}
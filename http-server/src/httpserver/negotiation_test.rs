@@ -0,0 +1,101 @@
+#[cfg(test)]
+mod negotiation_test {
+    use super::super::{
+        best_match, parse_accept, HTTPStatusCode, HttpVerb, MediaRange, Representations, Response,
+        Router, TestClient,
+    };
+
+    const OFFERS: [&str; 3] = ["application/json", "text/html", "text/csv"];
+
+    #[test]
+    fn test_parse_accept() {
+        let ranges = parse_accept("text/html;level=1, text/*;q=0.5, bogus, */*;q=0.1");
+        assert_eq!(ranges.len(), 3);
+        assert_eq!(ranges[0].params, vec![(String::from("level"), String::from("1"))]);
+        assert_eq!(ranges[1].quality, 0.5);
+        assert_eq!(ranges[2].main_type, "*");
+        assert_eq!(MediaRange::parse("*/html"), None);
+    }
+
+    #[test]
+    fn test_best_match_by_quality() {
+        assert_eq!(best_match(Some("text/csv"), &OFFERS), Some("text/csv"));
+        assert_eq!(
+            best_match(Some("text/html;q=0.9, application/json;q=0.4"), &OFFERS),
+            Some("text/html")
+        );
+        // on equal quality, the server's order wins:
+        assert_eq!(best_match(Some("text/*"), &OFFERS), Some("text/html"));
+        assert_eq!(best_match(Some("*/*"), &OFFERS), Some("application/json"));
+        assert_eq!(best_match(None, &OFFERS), Some("application/json"));
+    }
+
+    #[test]
+    fn test_most_specific_range_wins() {
+        // text/html is explicitly excluded, even though text/* would accept it:
+        assert_eq!(best_match(Some("text/*, text/html;q=0"), &OFFERS), Some("text/csv"));
+        assert_eq!(
+            best_match(Some("*/*;q=0.1, application/json;q=0"), &OFFERS),
+            Some("text/html")
+        );
+    }
+
+    #[test]
+    fn test_no_acceptable_offer() {
+        assert_eq!(best_match(Some("image/png"), &OFFERS), None);
+        assert_eq!(best_match(Some("*/*;q=0"), &OFFERS), None);
+    }
+
+    fn client() -> TestClient {
+        let mut router = Router::new();
+        router.route(HttpVerb::GET, "/users", |request| {
+            Representations::new()
+                .offer("application/json", || {
+                    Response::new(HTTPStatusCode::Success(200)).with_body("[\"alex\"]")
+                })
+                .offer("text/html", || {
+                    Response::new(HTTPStatusCode::Success(200))
+                        .with_header("Content-Type", "text/html; charset=utf-8")
+                        .with_body("<li>alex</li>")
+                })
+                .respond(request)
+        });
+        TestClient::new(router)
+    }
+
+    #[test]
+    fn test_respond_with_chosen_representation() {
+        let client = client();
+        let response = client.get("/users").header("Accept", "text/*").send();
+        assert_eq!(response.status.code(), 200);
+        assert_eq!(response.text(), "<li>alex</li>");
+        // the representation's own Content-Type is kept:
+        assert_eq!(
+            response.headers.get("Content-Type").as_deref(),
+            Some("text/html; charset=utf-8")
+        );
+        assert_eq!(response.headers.get("Vary").as_deref(), Some("Accept"));
+
+        let response = client.get("/users").send();
+        assert_eq!(response.text(), "[\"alex\"]");
+        assert_eq!(
+            response.headers.get("Content-Type").as_deref(),
+            Some("application/json")
+        );
+        assert_eq!(response.headers.get("Vary").as_deref(), Some("Accept"));
+    }
+
+    #[test]
+    fn test_respond_not_acceptable() {
+        let response = client().get("/users").header("Accept", "image/png").send();
+        assert_eq!(response.status.code(), 406);
+        assert_eq!(
+            response.headers.get("Content-Type").as_deref(),
+            Some("application/problem+json")
+        );
+        assert_eq!(response.headers.get("Vary").as_deref(), Some("Accept"));
+        assert!(response
+            .text()
+            .contains("Available representations: application/json, text/html"));
+    }
+}
@@ -0,0 +1,73 @@
+use crate::httpserver::{HTTPStatusCode, Response};
+use crate::utils::json;
+
+/// Problem details for HTTP APIs (RFC 7807), sent as `application/problem+json`.
+/// The server uses it for its own error responses; handlers can use it, too:
+///
+/// ```ignore
+/// return Problem::new(HTTPStatusCode::ClientError(409))
+///     .with_detail("The user name is already taken")
+///     .into();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    pub status: HTTPStatusCode,
+    /// URI identifying the problem type, `about:blank` if there is nothing more
+    /// specific than the status code.
+    pub problem_type: String,
+    pub title: String,
+    pub detail: Option<String>,
+    pub instance: Option<String>,
+}
+
+impl Problem {
+    /// A problem of type `about:blank`, titled with the status message.
+    pub fn new(status: HTTPStatusCode) -> Problem {
+        Problem {
+            status,
+            problem_type: String::from("about:blank"),
+            title: String::from(status.message()),
+            detail: None,
+            instance: None,
+        }
+    }
+
+    pub fn with_type(mut self, problem_type: &str, title: &str) -> Problem {
+        self.problem_type = String::from(problem_type);
+        self.title = String::from(title);
+        self
+    }
+
+    pub fn with_detail(mut self, detail: &str) -> Problem {
+        self.detail = Some(String::from(detail));
+        self
+    }
+
+    pub fn with_instance(mut self, instance: &str) -> Problem {
+        self.instance = Some(String::from(instance));
+        self
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut members = vec![
+            format!("\"type\":{}", json::quote(&self.problem_type)),
+            format!("\"title\":{}", json::quote(&self.title)),
+            format!("\"status\":{}", self.status.code()),
+        ];
+        if let Some(detail) = &self.detail {
+            members.push(format!("\"detail\":{}", json::quote(detail)));
+        }
+        if let Some(instance) = &self.instance {
+            members.push(format!("\"instance\":{}", json::quote(instance)));
+        }
+        format!("{{{}}}", members.join(","))
+    }
+}
+
+impl From<Problem> for Response {
+    fn from(problem: Problem) -> Response {
+        Response::new(problem.status)
+            .with_header("Content-Type", "application/problem+json")
+            .with_body(problem.to_json())
+    }
+}
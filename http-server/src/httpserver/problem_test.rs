@@ -0,0 +1,49 @@
+#[cfg(test)]
+mod problem_test {
+    use super::super::{HTTPStatusCode, Problem, Response};
+
+    #[test]
+    fn test_problem_defaults() {
+        let problem = Problem::new(HTTPStatusCode::ClientError(404));
+        assert_eq!(
+            problem.to_json(),
+            r#"{"type":"about:blank","title":"Not Found","status":404}"#
+        );
+    }
+
+    #[test]
+    fn test_problem_response() {
+        let response: Response = Problem::new(HTTPStatusCode::ClientError(409))
+            .with_type("https://example.com/problems/taken", "Name taken")
+            .with_detail("The name \"alex\" is already taken")
+            .with_instance("/users/alex")
+            .into();
+        assert_eq!(response.status, HTTPStatusCode::ClientError(409));
+        assert_eq!(
+            response.headers.get("Content-Type").as_deref(),
+            Some("application/problem+json")
+        );
+        assert_eq!(
+            String::from_utf8(response.body).unwrap(),
+            r#"{"type":"https://example.com/problems/taken","title":"Name taken","status":409,"detail":"The name \"alex\" is already taken","instance":"/users/alex"}"#
+        );
+    }
+
+    #[test]
+    fn test_status_from_code() {
+        assert_eq!(HTTPStatusCode::from_code(204), HTTPStatusCode::Success(204));
+        assert_eq!(HTTPStatusCode::from_code(422), HTTPStatusCode::ClientError(422));
+        assert_eq!(HTTPStatusCode::from_code(422).message(), "Unprocessable Content");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_response() {
+        let mut value = std::collections::BTreeMap::new();
+        value.insert("name", "alex");
+        let response = Response::json(&value);
+        assert_eq!(response.status, HTTPStatusCode::Success(200));
+        assert_eq!(response.headers.get("Content-Type").as_deref(), Some("application/json"));
+        assert_eq!(response.body, br#"{"name":"alex"}"#);
+    }
+}
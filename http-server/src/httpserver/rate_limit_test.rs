@@ -0,0 +1,94 @@
+#[cfg(test)]
+mod rate_limit_test {
+    use super::super::middleware::rate_limit::*;
+    use super::super::{HTTPStatusCode, HttpVerb, Response, Router, TestClient};
+    use std::net::SocketAddr;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_burst_and_refill() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(3, Duration::from_secs(3), start);
+        assert_eq!(bucket.try_acquire(start), Ok(2));
+        assert_eq!(bucket.try_acquire(start), Ok(1));
+        assert_eq!(bucket.try_acquire(start), Ok(0));
+        assert_eq!(bucket.try_acquire(start), Err(Duration::from_secs(1)));
+
+        // one token per second is refilled:
+        let later = start + Duration::from_millis(1500);
+        assert_eq!(bucket.try_acquire(later), Ok(0));
+        assert_eq!(bucket.try_acquire(later), Err(Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn test_full_bucket() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(2, Duration::from_secs(10), start);
+        assert!(bucket.is_full(start));
+        bucket.try_acquire(start).unwrap();
+        assert!(!bucket.is_full(start));
+        assert_eq!(bucket.reset_after(), Duration::from_secs(5));
+        assert!(bucket.is_full(start + Duration::from_secs(5)));
+    }
+
+    fn status(client: &TestClient, key: &str) -> usize {
+        client
+            .get("/")
+            .header("X-Api-Key", key)
+            .send()
+            .status
+            .code()
+    }
+
+    fn router(limit: RateLimitMiddleware) -> Router {
+        let mut router = Router::new();
+        router.middleware("/", limit);
+        router.route(HttpVerb::GET, "/", |_| {
+            Response::text(HTTPStatusCode::Success(200), "ok")
+        });
+        router
+    }
+
+    fn client_from(router: &Router, ip: &str) -> TestClient {
+        let addr: SocketAddr = format!("{}:40000", ip).parse().unwrap();
+        TestClient::new(router.clone()).peer_addr(addr)
+    }
+
+    #[test]
+    fn test_key_header() {
+        let router = router(
+            RateLimitMiddleware::new(1, Duration::from_secs(60))
+                .key_header("X-Api-Key", |key| key.starts_with("valid-")),
+        );
+        let client = client_from(&router, "192.0.2.1");
+        let other_client = client_from(&router, "192.0.2.2");
+
+        // the same key from two addresses shares one limit:
+        assert_eq!(status(&client, "valid-a"), 200);
+        assert_eq!(status(&other_client, "valid-a"), 429);
+        assert_eq!(status(&client, "valid-b"), 200);
+
+        // invalid keys are not counted, the address is:
+        assert_eq!(status(&other_client, "junk-1"), 200);
+        assert_eq!(status(&other_client, "junk-2"), 429);
+        assert_eq!(status(&other_client, "junk-3"), 429);
+        assert_eq!(other_client.get("/").send().status.code(), 429);
+    }
+
+    #[test]
+    fn test_max_clients() {
+        let limit = RateLimitMiddleware::new(1, Duration::from_secs(60)).max_clients(2);
+        let router = router(limit);
+        let get = |ip: &str| client_from(&router, ip).get("/").send().status.code();
+
+        assert_eq!(get("192.0.2.1"), 200);
+        assert_eq!(get("192.0.2.2"), 200);
+        assert_eq!(get("192.0.2.1"), 429);
+        // a full table drops the least recently used client, new clients get a bucket of their own:
+        for ip in ["192.0.2.3", "192.0.2.4", "192.0.2.5"] {
+            assert_eq!(get(ip), 200);
+        }
+        assert_eq!(get("192.0.2.5"), 429);
+        assert_eq!(get("192.0.2.1"), 200);
+    }
+}
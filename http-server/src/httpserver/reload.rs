@@ -0,0 +1,168 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::Duration;
+
+use crate::httpserver::lifecycle::Lifecycle;
+use crate::httpserver::{Router, VirtualHosts};
+use crate::utils::logging::LogSeverity;
+use crate::utils::signal;
+use crate::utils::threadpool::ThreadPool;
+
+/// The part of the server configuration that can be replaced while the server
+/// runs: the routes with their middlewares (authentication, CORS, rate limits,
+/// ...), the virtual hosts and the number of worker threads. Built by the loader
+/// passed to [HttpServer::reloadable_config](crate::httpserver::HttpServer::reloadable_config),
+/// typically from a configuration file.
+///
+/// The routes are added to the ones registered on the server itself (e.g. the
+/// metrics and health routes), which stay the same.
+#[derive(Clone, Default)]
+pub struct ServerConfig {
+    pub router: Router,
+    pub virtual_hosts: Vec<(String, Router)>,
+    /// number of worker threads, None keeps the current number
+    pub workers: Option<usize>,
+}
+
+impl ServerConfig {
+    pub fn new(router: Router) -> ServerConfig {
+        ServerConfig {
+            router,
+            ..Default::default()
+        }
+    }
+
+    /// Serves the given host name with its own router, see [VirtualHosts].
+    pub fn virtual_host(mut self, host: &str, router: Router) -> ServerConfig {
+        self.virtual_hosts.push((String::from(host), router));
+        self
+    }
+
+    pub fn workers(mut self, nr_of_threads: usize) -> ServerConfig {
+        self.workers = Some(nr_of_threads);
+        self
+    }
+}
+
+/// Builds the configuration, or describes why it cannot (e.g. the file and line
+/// of an invalid setting).
+pub type ConfigLoader = dyn Fn() -> Result<ServerConfig, String> + Send + Sync;
+
+/// The routing of a running server: replaced as a whole on a reload. Each
+/// connection takes the current one, so requests in progress are finished
+/// with the configuration they started with.
+#[derive(Clone)]
+pub struct SharedHosts(Arc<RwLock<Arc<VirtualHosts>>>);
+
+impl SharedHosts {
+    pub fn new(hosts: VirtualHosts) -> SharedHosts {
+        SharedHosts(Arc::new(RwLock::new(Arc::new(hosts))))
+    }
+
+    pub fn current(&self) -> Arc<VirtualHosts> {
+        Arc::clone(&self.0.read().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    pub fn replace(&self, hosts: VirtualHosts) {
+        *self.0.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(hosts);
+    }
+}
+
+/// Requests a configuration reload of a running server from another thread,
+/// like SIGHUP does (e.g. from a file watcher, or an admin endpoint).
+#[derive(Clone, Default)]
+pub struct ReloadHandle {
+    requests: Arc<AtomicU64>,
+}
+
+impl ReloadHandle {
+    /// Reloads the configuration shortly. Does not block; the outcome is logged.
+    pub fn reload(&self) {
+        self.requests.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn requests(&self) -> u64 {
+        self.requests.load(Ordering::SeqCst)
+    }
+}
+
+/// Loads the configuration and applies it to the running server.
+pub struct Reloader {
+    pub loader: Arc<ConfigLoader>,
+    /// the middlewares and routes of the server itself, for all hosts
+    pub shared: Router,
+    /// the routes and virtual hosts registered on the server itself
+    pub base_router: Router,
+    pub base_hosts: Vec<(String, Router)>,
+    pub hosts: SharedHosts,
+    pub handle: ReloadHandle,
+}
+
+impl Reloader {
+    /// Runs the loader and builds the routing from its configuration. A panicking
+    /// loader counts as failed.
+    pub fn load(&self) -> Result<(VirtualHosts, Option<usize>), String> {
+        let config = panic::catch_unwind(AssertUnwindSafe(|| (self.loader)()))
+            .map_err(|_| String::from("the configuration loader panicked"))??;
+
+        let mut default = self.base_router.clone();
+        default.merge(config.router);
+        let mut host_routers = self.base_hosts.clone();
+        for (host, router) in config.virtual_hosts {
+            let pattern = host.trim().to_ascii_lowercase();
+            match host_routers
+                .iter_mut()
+                .find(|(p, _)| p.trim().to_ascii_lowercase() == pattern)
+            {
+                Some((_, existing)) => existing.merge(router),
+                None => host_routers.push((host, router)),
+            }
+        }
+        let mut hosts = VirtualHosts::with_shared(self.shared.clone(), default);
+        for (host, router) in host_routers {
+            hosts.host(&host, router);
+        }
+        Ok((hosts, config.workers))
+    }
+
+    /// Loads the configuration and swaps it in. On failure, the current
+    /// configuration stays active.
+    pub fn reload(&self, pool: &ThreadPool) -> Result<(), String> {
+        let (hosts, workers) = self.load()?;
+        self.hosts.replace(hosts);
+        if let Some(workers) = workers {
+            pool.resize(workers);
+        }
+        Ok(())
+    }
+
+    /// Reloads on SIGHUP and on [ReloadHandle::reload], until the server stops.
+    pub fn watch(&self, pool: &ThreadPool, lifecycle: &Lifecycle) {
+        signal::watch_sighup();
+        let mut seen = (signal::sighup_count(), self.handle.requests());
+        while !lifecycle.is_stopped() {
+            thread::sleep(Duration::from_millis(100));
+            let current = (signal::sighup_count(), self.handle.requests());
+            if current == seen {
+                continue;
+            }
+            seen = current;
+            match self.reload(pool) {
+                Ok(()) => log("Reloaded the configuration", LogSeverity::INFO),
+                Err(e) => log(
+                    &format!(
+                        "Cannot reload the configuration, keeping the previous one: {}",
+                        e
+                    ),
+                    LogSeverity::ERROR,
+                ),
+            }
+        }
+    }
+}
+
+fn log(msg: &str, severity: LogSeverity) {
+    eprintln!("{}: {}\n", severity, msg);
+}
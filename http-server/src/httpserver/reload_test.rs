@@ -0,0 +1,94 @@
+#[cfg(test)]
+mod reload_test {
+    use super::super::{HTTPStatusCode, HttpServer, HttpVerb, Response, Router, ServerConfig};
+    use crate::httpclient::HttpClient;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    /// A server whose configuration is "read" from the shared string: the
+    /// version to answer with, and the number of workers.
+    fn server(config: &Arc<Mutex<String>>) -> HttpServer {
+        let mut server = HttpServer::new("127.0.0.1:0");
+        server.metrics_route("/metrics");
+        let config = Arc::clone(config);
+        server.reloadable_config(move || {
+            let content = config.lock().unwrap().clone();
+            let (version, workers) = content
+                .split_once(' ')
+                .ok_or_else(|| format!("line 1: invalid setting: {}", content))?;
+            let workers = workers
+                .parse()
+                .map_err(|e| format!("line 1: invalid worker count: {}", e))?;
+            let mut router = Router::new();
+            let version = String::from(version);
+            router.route(HttpVerb::GET, "/version", move |_| {
+                Response::text(HTTPStatusCode::Success(200), &version)
+            });
+            Ok(ServerConfig::new(router).workers(workers))
+        });
+        server
+    }
+
+    fn get(url: &str) -> String {
+        let client = HttpClient::new().timeout(Duration::from_secs(5));
+        client.get(url).send().unwrap().text()
+    }
+
+    fn wait_for(url: &str, expected: &str) {
+        let start = Instant::now();
+        while !get(url).contains(expected) {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "{} not reloaded",
+                url
+            );
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    #[test]
+    fn test_reload() {
+        let config = Arc::new(Mutex::new(String::from("v1 2")));
+        let server = server(&config);
+        let reload = server.reload_handle();
+        let server = server.spawn_ephemeral().unwrap();
+        assert_eq!(get(&server.url("/version")), "v1");
+        assert!(get(&server.url("/metrics")).contains("threadpool_workers 2\n"));
+
+        *config.lock().unwrap() = String::from("v2 7");
+        reload.reload();
+        wait_for(&server.url("/version"), "v2");
+        // the routes of the server itself stay:
+        assert!(get(&server.url("/metrics")).contains("threadpool_workers 7\n"));
+
+        // an invalid configuration keeps the previous one:
+        *config.lock().unwrap() = String::from("v3 many");
+        reload.reload();
+        thread::sleep(Duration::from_millis(300));
+        assert_eq!(get(&server.url("/version")), "v2");
+
+        *config.lock().unwrap() = String::from("v4 1");
+        reload.reload();
+        wait_for(&server.url("/version"), "v4");
+        assert!(get(&server.url("/metrics")).contains("threadpool_workers 1\n"));
+    }
+
+    #[test]
+    fn test_invalid_initial_config() {
+        let config = Arc::new(Mutex::new(String::from("v1")));
+        let mut server = server(&config);
+        let err = server.start().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Cannot load the configuration: line 1: invalid setting: v1"
+        );
+    }
+
+    #[test]
+    fn test_test_client_uses_config() {
+        let config = Arc::new(Mutex::new(String::from("v1 2")));
+        let server = server(&config);
+        assert_eq!(server.test_client().get("/version").send().text(), "v1");
+    }
+}
@@ -1,201 +1,293 @@
-use std::io::ErrorKind;
+use std::io::{Error, ErrorKind};
 use std::str;
+use std::time::Instant;
 use std::{
-    io::{BufRead, BufReader, Read, Write},
-    net::TcpStream,
+    io::{BufRead, BufReader},
+    net::{SocketAddr, TcpStream},
 };
 
-use crate::httpserver::{HeaderMap, RequestParams};
-use http_server::utils::logging::LogSeverity;
-use http_server::utils::BufReaderExt;
+use crate::httpserver::lifecycle::Lifecycle;
+use crate::httpserver::{
+    ConnectionLimits, HeaderMap, HttpVerb, RequestHead, RequestParams, RequestParser, Response,
+    Router,
+};
+use crate::utils::deadline_reader::DeadlineReader;
+use crate::utils::logging::LogSeverity;
+use crate::utils::net::Stream;
 
 use super::HTTPStatusCode;
 
-#[derive(Debug)]
-pub enum HttpVerb {
-    GET,
-    HEAD,
-    POST,
-    PUT,
-    DELETE,
-    CONNECT,
-    OPTIONS,
-    TRACE,
-    PATCH,
-    UNKNOWN,
-}
-
-impl HttpVerb {
-    fn from(string: &str) -> HttpVerb {
-        match string.to_lowercase().as_str() {
-            "get" => HttpVerb::GET,
-            "head" => HttpVerb::HEAD,
-            "post" => HttpVerb::POST,
-            "put" => HttpVerb::PUT,
-            "delete" => HttpVerb::DELETE,
-            "connect" => HttpVerb::CONNECT,
-            "options" => HttpVerb::OPTIONS,
-            "trace" => HttpVerb::TRACE,
-            "patch" => HttpVerb::PATCH,
-            _ => HttpVerb::UNKNOWN,
-        }
-    }
+/// The blocking connection a request was read from; the response is written to it.
+struct Connection {
+    stream: Stream,
+    buf_reader: BufReader<DeadlineReader>,
 }
 
 pub struct Request {
-    tcp_stream: TcpStream,
-    buf_reader: BufReader<TcpStream>,
+    connection: Option<Connection>,
+    received: u64,
     pub headers: HeaderMap,
     pub method: HttpVerb,
+    /// protocol version of the request line, e.g. `HTTP/1.1`
+    pub version: String,
     pub full_url: String,
     pub url: String,
     pub body: Option<String>,
     pub params: RequestParams,
+    /// address of the connected client
+    pub peer_addr: Option<SocketAddr>,
+}
+
+/// A clone is detached from the connection: it carries the request data only,
+/// and [Request::handle] does not write a response for it.
+impl Clone for Request {
+    fn clone(&self) -> Request {
+        Request {
+            connection: None,
+            received: self.bytes_read(),
+            headers: self.headers.clone(),
+            method: self.method.clone(),
+            version: self.version.clone(),
+            full_url: self.full_url.clone(),
+            url: self.url.clone(),
+            body: self.body.clone(),
+            params: self.params.clone(),
+            peer_addr: self.peer_addr,
+        }
+    }
 }
 
 impl Request {
     /// Creates a Request from the given stream. As this stream is possibly from a keep-alive
     /// connection, we also return the still opened Buffered Reader, so that another request
     /// can be established from the already read-in-progress buffer.
-    pub fn from_tcp_stream(stream: TcpStream) -> Result<Request, HTTPStatusCode> {
+    ///
+    /// The request line and headers, and the body, must be received within the
+    /// timeouts given by the limits, else the request fails with 408 Request Timeout.
+    /// The data is parsed by a [RequestParser]: only the bytes of this request are
+    /// taken from the reader, data sent after it stays buffered.
+    pub fn from_tcp_stream(
+        stream: TcpStream,
+        limits: &ConnectionLimits,
+    ) -> Result<Request, HTTPStatusCode> {
+        Request::from_stream(Stream::from(stream), limits)
+    }
+
+    /// Creates a Request from a TCP or Unix domain socket stream, see
+    /// [Request::from_tcp_stream]. Requests from a Unix domain socket have no `peer_addr`.
+    pub fn from_stream(stream: Stream, limits: &ConnectionLimits) -> Result<Request, HTTPStatusCode> {
         let stream_copy = match stream.try_clone() {
             Ok(s) => s,
             Err(_) => return Err(HTTPStatusCode::ServerError(500)),
         };
-        let mut buf_reader = BufReader::new(stream_copy);
-        let mut header_buf = String::new();
-        let mut headers = Vec::new();
-
-        // read 1st line: http request and verb:
-        let line_buf = match buf_reader.read_max_until(10, 8192) {
-            Ok(buf) => buf,
-            Err(err) => match err.kind() {
-                ErrorKind::UnexpectedEof => return Err(HTTPStatusCode::ClientError(413)),
-                _ => return Err(HTTPStatusCode::ServerError(500)),
-            },
-        };
-        let line = String::from_utf8(line_buf).unwrap_or(String::new());
-        let (verb, url) = Request::parse_http_request_line(line.trim());
+        let mut reader = DeadlineReader::new(stream_copy);
+        reader.set_deadline(Some(Instant::now() + limits.header_timeout));
+        let mut buf_reader = BufReader::new(reader);
+        let mut parser = RequestParser::new(limits.max_body_size);
+        let mut buf = Vec::new();
 
-        let full_url = String::from(&url);
-        let url = String::from(match url.split_once('?') {
-            Some(parts) => parts.0,
-            None => &url,
-        });
-        let params = RequestParams::from_request_url(&url);
-
-        // Read header lines:
-        while let Ok(_) = buf_reader.read_line(&mut header_buf) {
-            let line = header_buf.trim();
-            if line.is_empty() {
-                // header end reached
-                break;
-            } else {
-                headers.push(String::from(line));
-                header_buf.clear();
-            }
-        }
-        let header_map = HeaderMap::builder(&headers);
-
-        let mut request = Request {
-            tcp_stream: stream,
-            buf_reader: buf_reader,
-            headers: header_map,
-            method: verb,
-            full_url: full_url,
-            url: url,
-            body: None,
-            params: params,
-        };
-
-        // now read the request body, if any is given:
-        if let Some(bytes_str) = request.headers.get("content-length") {
-            let bytes: usize = match bytes_str.parse() {
-                Ok(val) => val,
-                Err(_) => 0,
+        let parsed = loop {
+            let available = match buf_reader.fill_buf() {
+                Ok(available) => available,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(Request::read_error_status(err)),
             };
-
-            if bytes > 0 {
-                let mut buf = vec![0u8; bytes];
-                let mut body = String::new();
-                if let Ok(_) = request.buf_reader.read_exact(&mut buf) {
-                    body += match std::str::from_utf8(&buf) {
-                        Ok(res) => res,
-                        Err(_) => "",
-                    };
-                    request.body = Some(body);
+            if available.is_empty() {
+                // connection closed before the request was complete
+                return Err(HTTPStatusCode::ClientError(400));
+            }
+            let before = buf.len();
+            buf.extend_from_slice(available);
+            let head_complete = parser.head_complete();
+            match parser.parse(&buf)? {
+                Some(parsed) => {
+                    buf_reader.consume(parsed.len - before);
+                    break parsed;
                 }
+                None => buf_reader.consume(buf.len() - before),
             }
-        }
+            if parser.head_complete() && !head_complete {
+                // the body has its own timeout from now on:
+                let reader = buf_reader.get_mut();
+                reader.set_deadline(Some(Instant::now() + limits.body_timeout));
+                reader.set_min_rate(limits.min_body_rate);
+            }
+        };
 
+        let mut request = parsed.into_request(stream.peer_addr());
+        request.connection = Some(Connection {
+            stream,
+            buf_reader,
+        });
         Ok(request)
     }
 
-    pub fn handle(&mut self) {
-        let code = HTTPStatusCode::Success(200);
-        self.write_response_code(code);
+    /// Creates a Request from an already parsed head and body, e.g. read by the
+    /// event loop. The response is not written by [Request::handle] then, but by
+    /// the caller of [Request::respond].
+    pub fn from_head(head: RequestHead, body: Option<String>, peer_addr: Option<SocketAddr>) -> Request {
+        let url = String::from(match head.full_url.split_once('?') {
+            Some(parts) => parts.0,
+            None => &head.full_url,
+        });
+        let params = RequestParams::from_request_url(&url);
+        Request {
+            connection: None,
+            received: 0,
+            headers: head.headers,
+            method: head.method,
+            version: head.version,
+            full_url: head.full_url,
+            url,
+            body,
+            params,
+            peer_addr,
+        }
+    }
+
+    /// Sets the number of bytes received for a request not read by the Request itself.
+    pub fn set_bytes_read(&mut self, bytes: u64) {
+        self.received = bytes;
+    }
 
-        // output header
-        // TODO: More to come...
-        // TODO: Connection header should not be used, see https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Connection
-        self.write_header("Connection", "close");
+    /// Detaches the request from its connection, e.g. to continue the connection
+    /// with another protocol: returns the stream and the reader, with the data
+    /// it already buffered.
+    pub(crate) fn take_connection(&mut self) -> Option<(Stream, BufReader<DeadlineReader>)> {
+        self.received = self.bytes_read();
+        self.connection
+            .take()
+            .map(|connection| (connection.stream, connection.buf_reader))
+    }
 
-        let mut response = format!("{:?} request read.\n", self.method);
+    /// Runs the request through the router (middlewares and route handler) and
+    /// returns the response, and logs it.
+    pub fn respond(&self, router: &Router) -> Response {
+        let response = router.dispatch(self);
+        self.log(
+            &format!("{} {} {}", self.method, self.full_url, response.status.code()),
+            LogSeverity::INFO,
+        );
+        response
+    }
 
-        if let Some(body) = &self.body {
-            response += format!("Body:\n{}", body).as_str();
-        }
+    /// Runs the request through the router (middlewares and route handler)
+    /// and writes the resulting response to the client. Returns the response
+    /// status and the number of bytes written.
+    pub fn handle(&mut self, router: &Router) -> (HTTPStatusCode, usize) {
+        self.handle_in(router, &Lifecycle::new())
+    }
 
-        // End header:
-        self.write_header("Content-Length", &format!("{}", response.len()));
-        self.write_to_response_stream("\r\n");
+    /// Like [Request::handle], but a streaming response is stopped when the
+    /// server of the lifecycle stops.
+    pub(crate) fn handle_in(
+        &mut self,
+        router: &Router,
+        lifecycle: &Lifecycle,
+    ) -> (HTTPStatusCode, usize) {
+        let response = self.respond(router);
+        let status = response.status;
 
-        // output body
-        self.write_to_response_stream(&response);
+        let connection = match self.connection.as_mut() {
+            Some(c) => c,
+            None => return (status, 0),
+        };
+        // HEAD: the response is the one of the GET request, without body.
+        let written =
+            response.write_and_close(&self.method, &mut connection.stream, |stream, body| {
+                let writer = stream.try_clone()?;
+                let open = lifecycle.open_stream(writer.try_clone().ok());
+                body(Box::new(writer), open.stopped())
+            });
+        let bytes_written = written.unwrap_or_else(|e| {
+            self.log(&e.to_string(), LogSeverity::ERROR);
+            0
+        });
 
         // TODO: read further request in the SAME stream: maybe this is a
         // keep-alive-connection.
         // we therefore get the buf_reader back from the handler.
         // for now, we just stop here.
 
-        self.tcp_stream.shutdown(std::net::Shutdown::Read).unwrap();
-
-        // return
+        if let Some(connection) = self.connection.as_ref() {
+            if let Err(e) = connection.stream.shutdown(std::net::Shutdown::Read) {
+                self.log(&e.to_string(), LogSeverity::ERROR);
+            }
+        }
+        (status, bytes_written)
     }
 
-    fn parse_http_request_line(line: &str) -> (HttpVerb, String) {
-        let mut verb = HttpVerb::UNKNOWN;
-        let mut url = String::new();
-        // TODO: HTTP Version extraction
+    /// Deserializes the JSON request body. Fails with a problem response of
+    /// 415 Unsupported Media Type if the Content-Type is not JSON, or of
+    /// 422 Unprocessable Content if the body does not match the expected type:
+    ///
+    /// ```ignore
+    /// let user: User = match request.json() {
+    ///     Ok(user) => user,
+    ///     Err(problem) => return problem.into(),
+    /// };
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn json<T: serde::de::DeserializeOwned>(&self) -> Result<T, crate::httpserver::Problem> {
+        use crate::httpserver::Problem;
 
-        let parts: Vec<_> = line.split_ascii_whitespace().collect();
-        if parts.len() > 0 {
-            verb = HttpVerb::from(parts[0]);
-        }
-        if parts.len() > 1 {
-            url = String::from(parts[1]);
+        let content_type = self.headers.get("Content-Type").unwrap_or_default();
+        let mime_type = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        if mime_type != "application/json" && !mime_type.ends_with("+json") {
+            return Err(Problem::new(HTTPStatusCode::ClientError(415))
+                .with_detail("Expected a request body of type application/json"));
         }
 
-        (verb, url)
+        serde_json::from_str(self.body.as_deref().unwrap_or_default()).map_err(|e| {
+            Problem::new(HTTPStatusCode::ClientError(422)).with_detail(&e.to_string())
+        })
     }
 
-    fn write_response_code(&mut self, code: HTTPStatusCode) {
-        self.write_to_response_stream(&format!("HTTP/1.1 {} {} \r\n", code.code(), code.message()));
+    /// Number of bytes received from the client for this request.
+    pub fn bytes_read(&self) -> u64 {
+        match &self.connection {
+            Some(c) => c.buf_reader.get_ref().bytes_read(),
+            None => self.received,
+        }
     }
 
-    fn write_header(&mut self, key: &str, value: &str) {
-        let header_str = format!("{}: {}\r\n", key, value);
-        self.write_to_response_stream(&header_str);
+    /// Maps a read error to the response status: a timeout is a 408, a broken
+    /// connection a 400.
+    fn read_error_status(err: Error) -> HTTPStatusCode {
+        match err.kind() {
+            ErrorKind::TimedOut => HTTPStatusCode::ClientError(408),
+            ErrorKind::UnexpectedEof
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted => HTTPStatusCode::ClientError(400),
+            _ => HTTPStatusCode::ServerError(500),
+        }
     }
 
-    fn write_to_response_stream(&mut self, data: &str) {
-        match self.tcp_stream.write(data.as_bytes()) {
-            Ok(_) => return,
-            Err(e) => self.log(&e.to_string(), LogSeverity::ERROR),
-        };
+    /// The requested host name from the `Host` header: lower case, without port.
+    pub fn host(&self) -> Option<String> {
+        self.headers.get("host").map(|host| normalize_host(&host))
     }
 
     fn log(&self, msg: &str, severity: LogSeverity) {
         eprintln!("{}: {}\n", severity, msg);
     }
 }
+
+/// Lower-cases a `Host` header value and strips the port, also from
+/// IPv6 literals (`[::1]:3000`).
+pub fn normalize_host(host: &str) -> String {
+    let host = host.trim();
+    let host = match host.strip_prefix('[') {
+        Some(rest) => match rest.split_once(']') {
+            Some((addr, _)) => addr,
+            None => rest,
+        },
+        None => host.split(':').next().unwrap_or_default(),
+    };
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
@@ -0,0 +1,163 @@
+use crate::httpserver::{HTTPStatusCode, HeaderMap, HttpVerb};
+
+/// Maximum length of the request line and of each header line, in bytes.
+pub const MAX_LINE_LENGTH: usize = 8192;
+
+/// Upper limit for the whole request head when it is buffered before parsing
+/// (non-blocking and async reading), so that clients cannot make the server
+/// buffer without limit.
+pub const MAX_HEAD_SIZE: usize = 64 * 1024;
+
+/// The request line and headers of a request, without the body.
+#[derive(Debug, Clone)]
+pub struct RequestHead {
+    pub method: HttpVerb,
+    pub full_url: String,
+    /// protocol version of the request line, e.g. `HTTP/1.1`
+    pub version: String,
+    pub headers: HeaderMap,
+}
+
+impl RequestHead {
+    /// Builds the head from the (trimmed) request line and header lines.
+    /// An invalid method token, or an HTTP/1.1 request without exactly one
+    /// Host header, is rejected with 400.
+    pub fn parse(
+        request_line: &str,
+        header_lines: &[String],
+    ) -> Result<RequestHead, HTTPStatusCode> {
+        let (method, full_url, version) = parse_request_line(request_line)?;
+        let headers = HeaderMap::builder(header_lines);
+        if version == "HTTP/1.1" && !has_valid_host(&headers) {
+            // an HTTP/1.1 request must contain exactly one Host header (RFC 9112, 3.2)
+            return Err(HTTPStatusCode::ClientError(400));
+        }
+        Ok(RequestHead {
+            method,
+            full_url,
+            version,
+            headers,
+        })
+    }
+
+    /// Length of the request body, from the Content-Length header.
+    pub fn content_length(&self) -> usize {
+        self.headers
+            .get("content-length")
+            .and_then(|l| l.trim().parse().ok())
+            .unwrap_or_default()
+    }
+
+    /// Length of the request body (RFC 9112, 6.3), checked against the limit.
+    /// Requests with ambiguous framing are rejected with 400, as a proxy in front
+    /// of the server could read them differently (request smuggling): an invalid
+    /// Content-Length, repeated ones that disagree, or Transfer-Encoding together
+    /// with Content-Length. A transfer coding alone is not supported here (501),
+    /// the [RequestParser](crate::httpserver::RequestParser) decodes chunked
+    /// bodies itself. A too large body is answered with 413.
+    pub fn body_length(&self, max_body_size: usize) -> Result<usize, HTTPStatusCode> {
+        let bad_request = HTTPStatusCode::ClientError(400);
+        let content_length = self.headers.get("content-length");
+        if self.headers.contains("transfer-encoding") {
+            return match content_length {
+                Some(_) => Err(bad_request),
+                None => Err(HTTPStatusCode::ServerError(501)),
+            };
+        }
+
+        let value = match content_length {
+            Some(value) => value,
+            None => return Ok(0),
+        };
+        // repeated headers (joined to a list) must agree:
+        let mut lengths = value.split(',').map(|l| l.trim());
+        let first = lengths.next().unwrap_or_default();
+        if first.is_empty()
+            || !first.bytes().all(|b| b.is_ascii_digit())
+            || lengths.any(|l| l != first)
+        {
+            return Err(bad_request);
+        }
+        match first.parse::<usize>() {
+            Ok(length) if length <= max_body_size => Ok(length),
+            // too large, or more digits than fit:
+            _ => Err(HTTPStatusCode::ClientError(413)),
+        }
+    }
+
+    /// Whether the client wants to keep the connection open after the response:
+    /// the default for HTTP/1.1, opt-in (`Connection: keep-alive`) for HTTP/1.0.
+    pub fn keep_alive(&self) -> bool {
+        let connection = self
+            .headers
+            .get("connection")
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let has = |option: &str| connection.split(',').any(|o| o.trim() == option);
+        match self.version.as_str() {
+            "HTTP/1.1" => !has("close"),
+            _ => has("keep-alive"),
+        }
+    }
+}
+
+/// Parses the request head from the start of a buffer, for incremental (non-blocking)
+/// reading. Returns the head and its length in bytes (including the empty line),
+/// or None if the head is not complete yet. Too long lines fail with 413 (request line)
+/// or 431 (header line).
+pub fn parse_head(buf: &[u8]) -> Result<Option<(RequestHead, usize)>, HTTPStatusCode> {
+    let mut request_line: Option<String> = None;
+    let mut header_lines = Vec::new();
+    let mut pos = 0;
+    loop {
+        let too_long = match request_line {
+            None => HTTPStatusCode::ClientError(413),
+            Some(_) => HTTPStatusCode::ClientError(431),
+        };
+        let rest = &buf[pos..];
+        let end = match rest.iter().position(|b| *b == b'\n') {
+            Some(end) => end,
+            None if rest.len() > MAX_LINE_LENGTH => return Err(too_long),
+            None => return Ok(None),
+        };
+        if end + 1 > MAX_LINE_LENGTH {
+            return Err(too_long);
+        }
+        let line = String::from_utf8_lossy(&rest[..end]).trim().to_string();
+        pos += end + 1;
+
+        match request_line {
+            None => request_line = Some(line),
+            Some(_) if line.is_empty() => break,
+            Some(_) => header_lines.push(line),
+        }
+    }
+    let head = RequestHead::parse(&request_line.unwrap_or_default(), &header_lines)?;
+    Ok(Some((head, pos)))
+}
+
+fn parse_request_line(line: &str) -> Result<(HttpVerb, String, String), HTTPStatusCode> {
+    let mut url = String::new();
+    // a request line without version is from HTTP/1.0 (or older) clients:
+    let mut version = String::from("HTTP/1.0");
+
+    let parts: Vec<_> = line.split_ascii_whitespace().collect();
+    let verb = parts.first().unwrap_or(&"").parse::<HttpVerb>()?;
+    if parts.len() > 1 {
+        url = String::from(parts[1]);
+    }
+    if parts.len() > 2 {
+        version = String::from(parts[2]);
+    }
+
+    Ok((verb, url, version))
+}
+
+/// Exactly one Host header (it may be empty). Repeated headers are combined
+/// by the HeaderMap, so a list is rejected.
+fn has_valid_host(headers: &HeaderMap) -> bool {
+    match headers.get("host") {
+        Some(host) => !host.contains(','),
+        None => false,
+    }
+}
@@ -0,0 +1,99 @@
+#[cfg(test)]
+mod request_head_test {
+    use super::super::{parse_head, HTTPStatusCode, HttpVerb};
+
+    #[test]
+    fn test_incremental() {
+        let raw = b"POST /a?b=1 HTTP/1.1\r\nHost: example.com\r\nContent-Length: 2\r\n\r\nhi";
+        // incomplete heads, wherever the data ends:
+        for end in [0, 10, 22, 50, 61] {
+            assert!(parse_head(&raw[..end]).unwrap().is_none(), "at {}", end);
+        }
+        let (head, len) = parse_head(raw).unwrap().unwrap();
+        assert_eq!(len, raw.len() - 2);
+        assert_eq!(head.method, HttpVerb::POST);
+        assert_eq!(head.full_url, "/a?b=1");
+        assert_eq!(head.version, "HTTP/1.1");
+        assert_eq!(head.content_length(), 2);
+        assert!(head.keep_alive());
+    }
+
+    #[test]
+    fn test_keep_alive() {
+        let head = |raw: &[u8]| parse_head(raw).unwrap().unwrap().0;
+        assert!(!head(b"GET / HTTP/1.1\r\nHost: a\r\nConnection: close\r\n\r\n").keep_alive());
+        assert!(!head(b"GET / HTTP/1.0\r\n\r\n").keep_alive());
+        assert!(head(b"GET / HTTP/1.0\r\nConnection: Keep-Alive\r\n\r\n").keep_alive());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            parse_head(b"GET / HTTP/1.1\r\n\r\n").unwrap_err(),
+            HTTPStatusCode::ClientError(400)
+        );
+        assert_eq!(
+            parse_head(b"GET / HTTP/1.1\r\nHost: a\r\nHost: b\r\n\r\n").unwrap_err(),
+            HTTPStatusCode::ClientError(400)
+        );
+        // invalid method tokens:
+        for raw in [
+            &b"G(T / HTTP/1.1\r\nHost: a\r\n\r\n"[..],
+            b"\r\nHost: a\r\n\r\n",
+        ] {
+            assert_eq!(
+                parse_head(raw).unwrap_err(),
+                HTTPStatusCode::ClientError(400)
+            );
+        }
+        let long_url = format!("GET /{}", "a".repeat(9000));
+        assert_eq!(
+            parse_head(long_url.as_bytes()).unwrap_err(),
+            HTTPStatusCode::ClientError(413)
+        );
+        let long_header = format!("GET / HTTP/1.1\r\nX: {}\r\n", "a".repeat(9000));
+        assert_eq!(
+            parse_head(long_header.as_bytes()).unwrap_err(),
+            HTTPStatusCode::ClientError(431)
+        );
+    }
+
+    #[test]
+    fn test_body_length() {
+        let length = |raw: &[u8]| parse_head(raw).unwrap().unwrap().0.body_length(100);
+        assert_eq!(length(b"GET / HTTP/1.1\r\nHost: a\r\n\r\n"), Ok(0));
+        assert_eq!(
+            length(b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 42\r\n\r\n"),
+            Ok(42)
+        );
+        // repeated, but equal:
+        assert_eq!(
+            length(b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 4\r\nContent-Length: 4\r\n\r\n"),
+            Ok(4)
+        );
+        let bad_request = Err(HTTPStatusCode::ClientError(400));
+        for raw in [
+            &b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 4\r\nContent-Length: 5\r\n\r\n"[..],
+            b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: +4\r\n\r\n",
+            b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 4x\r\n\r\n",
+            b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 4\r\nTransfer-Encoding: chunked\r\n\r\n",
+        ] {
+            assert_eq!(length(raw), bad_request, "{}", String::from_utf8_lossy(raw));
+        }
+        assert_eq!(
+            length(b"POST / HTTP/1.1\r\nHost: a\r\nTransfer-Encoding: chunked\r\n\r\n"),
+            Err(HTTPStatusCode::ServerError(501))
+        );
+        let too_large = Err(HTTPStatusCode::ClientError(413));
+        assert_eq!(
+            length(b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 101\r\n\r\n"),
+            too_large
+        );
+        assert_eq!(
+            length(
+                b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 99999999999999999999999\r\n\r\n"
+            ),
+            too_large
+        );
+    }
+}
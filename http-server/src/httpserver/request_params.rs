@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use std::collections::hash_map::Iter;
 
 
+#[derive(Debug, Clone)]
 pub struct RequestParams {
     params: HashMap<String, String>,
 }
@@ -29,7 +30,7 @@ impl RequestParams {
         RequestParams { params }
     }
 
-	pub fn pairs(&self) -> Iter<String,String> {
+	pub fn pairs(&self) -> Iter<'_, String,String> {
 		self.params.iter()
 	}
 
@@ -42,10 +43,7 @@ impl RequestParams {
 
 	pub fn get_i64(&self, key: &str) -> Option<i64>  {
 		match self.params.contains_key(key) {
-			true => match str::parse::<i64>(self.get(key, "")) {
-				Ok(nr) => Some(nr),
-				Err(_) => None
-			},
+			true => str::parse::<i64>(self.get(key, "")).ok(),
 			false => None
 		}
 	}
@@ -0,0 +1,312 @@
+use std::net::SocketAddr;
+
+use crate::httpserver::request_head::{parse_head, MAX_HEAD_SIZE, MAX_LINE_LENGTH};
+use crate::httpserver::{HTTPStatusCode, Request, RequestHead};
+
+/// Empty lines ignored before a request line (RFC 9112, 2.2), e.g. the line
+/// break some clients send after the body of a POST request.
+const MAX_EMPTY_LINES: usize = 4;
+
+/// Incremental parser for HTTP/1.x requests, working on bytes instead of a
+/// connection, so that all front ends (blocking, event loop, async) share the
+/// same framing rules and limits.
+///
+/// [RequestParser::parse] is called with the same, growing buffer each time
+/// more data has arrived: the parser remembers how far it got, so each byte
+/// is only looked at once. When a request is complete, it is returned with its
+/// length; the caller removes these bytes from the buffer (pipelined requests
+/// may follow), and the parser starts over with the next request.
+///
+/// ```ignore
+/// let mut parser = RequestParser::new(limits.max_body_size);
+/// loop {
+///     buf.extend_from_slice(&read_more()?);
+///     if let Some(parsed) = parser.parse(&buf)? {
+///         buf.drain(..parsed.len);
+///         return Ok(parsed.into_request(peer_addr));
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct RequestParser {
+    max_body_size: usize,
+    /// start of the request line, after the skipped empty lines
+    start: usize,
+    /// start of the first line of the head not scanned yet
+    scanned: usize,
+    head: Option<(RequestHead, usize)>,
+    body: Body,
+    /// position in the buffer up to which the body is parsed
+    pos: usize,
+}
+
+#[derive(Debug)]
+enum Body {
+    /// the head is not complete yet
+    Unknown,
+    /// a body of a fixed length (0 if none is given)
+    Length(usize),
+    Chunked(Chunk, Vec<u8>),
+}
+
+/// State of a chunked body (RFC 9112, 7.1).
+#[derive(Debug, Clone, Copy)]
+enum Chunk {
+    Size,
+    /// remaining bytes of the chunk data
+    Data(usize),
+    /// the line break after the chunk data
+    DataEnd,
+    /// trailer fields, and their size so far: they are discarded
+    Trailers(usize),
+}
+
+/// A complete request, as returned by the [RequestParser].
+#[derive(Debug)]
+pub struct ParsedRequest {
+    pub head: RequestHead,
+    /// the body, de-chunked
+    pub body: Vec<u8>,
+    /// number of bytes of the buffer the request consists of
+    pub len: usize,
+}
+
+impl ParsedRequest {
+    /// Builds the [Request]: a body that is no valid utf-8 is passed as an empty string.
+    pub fn into_request(self, peer_addr: Option<SocketAddr>) -> Request {
+        let body = match self.body.is_empty() {
+            true => None,
+            false => Some(String::from_utf8(self.body).unwrap_or_default()),
+        };
+        let mut request = Request::from_head(self.head, body, peer_addr);
+        request.set_bytes_read(self.len as u64);
+        request
+    }
+}
+
+impl RequestParser {
+    /// A parser rejecting bodies larger than `max_body_size` bytes with 413.
+    pub fn new(max_body_size: usize) -> RequestParser {
+        RequestParser {
+            max_body_size,
+            start: 0,
+            scanned: 0,
+            head: None,
+            body: Body::Unknown,
+            pos: 0,
+        }
+    }
+
+    /// Whether the head of the current request is complete, and the parser
+    /// waits for the body.
+    pub fn head_complete(&self) -> bool {
+        self.head.is_some()
+    }
+
+    /// Continues parsing the buffer, which starts with the current request and
+    /// holds (at least) the data passed before. Returns the request when it is
+    /// complete, None if more data is needed, or the status to answer an invalid
+    /// request with: 400 for a malformed request or body framing, 413 for a too
+    /// long request line or body, 431 for too large headers, and 501 for an
+    /// unsupported transfer coding.
+    pub fn parse(&mut self, buf: &[u8]) -> Result<Option<ParsedRequest>, HTTPStatusCode> {
+        if self.head.is_none() {
+            let head_len = match self.scan_head(buf)? {
+                Some(head_len) => head_len,
+                None => return Ok(None),
+            };
+            let head = match parse_head(&buf[self.start..head_len])? {
+                Some((head, len)) if self.start + len == head_len => head,
+                _ => return Err(HTTPStatusCode::ClientError(400)),
+            };
+            self.body = body_framing(&head, self.max_body_size)?;
+            self.head = Some((head, head_len));
+            self.pos = head_len;
+        }
+
+        let body_end = match &mut self.body {
+            Body::Unknown => return Ok(None),
+            Body::Length(length) => match buf.len() >= self.pos + *length {
+                true => self.pos + *length,
+                false => return Ok(None),
+            },
+            Body::Chunked(chunk, body) => {
+                match parse_chunked(buf, &mut self.pos, chunk, body, self.max_body_size)? {
+                    true => self.pos,
+                    false => return Ok(None),
+                }
+            }
+        };
+
+        // the request is complete, start over with the next one:
+        let (head, head_len) = match self.head.take() {
+            Some(head) => head,
+            None => return Ok(None),
+        };
+        let body = match std::mem::replace(&mut self.body, Body::Unknown) {
+            Body::Chunked(_, body) => body,
+            _ => buf[head_len..body_end].to_vec(),
+        };
+        self.start = 0;
+        self.scanned = 0;
+        self.pos = 0;
+        Ok(Some(ParsedRequest {
+            head,
+            body,
+            len: body_end,
+        }))
+    }
+
+    /// Scans the new lines of the head for its end (an empty line), and checks
+    /// the line and head size limits. Returns the length of the head, if complete.
+    fn scan_head(&mut self, buf: &[u8]) -> Result<Option<usize>, HTTPStatusCode> {
+        loop {
+            let first_line = self.scanned == self.start;
+            let too_long = match first_line {
+                true => HTTPStatusCode::ClientError(413),
+                false => HTTPStatusCode::ClientError(431),
+            };
+            let rest = &buf[self.scanned..];
+            let end = match rest.iter().take(MAX_LINE_LENGTH).position(|b| *b == b'\n') {
+                Some(end) => end,
+                None if rest.len() >= MAX_LINE_LENGTH => return Err(too_long),
+                None => return Ok(None),
+            };
+            // the same test for the empty line as parse_head:
+            let is_empty = String::from_utf8_lossy(&rest[..end]).trim().is_empty();
+            self.scanned += end + 1;
+            if self.scanned > MAX_HEAD_SIZE {
+                return Err(HTTPStatusCode::ClientError(431));
+            }
+            match (is_empty, first_line) {
+                (true, false) => return Ok(Some(self.scanned)),
+                (true, true) => {
+                    let empty_lines = buf[..self.scanned].iter().filter(|b| **b == b'\n');
+                    if empty_lines.count() > MAX_EMPTY_LINES {
+                        return Err(HTTPStatusCode::ClientError(400));
+                    }
+                    self.start = self.scanned;
+                }
+                (false, _) => (),
+            }
+        }
+    }
+}
+
+/// Parses a complete request from the start of the buffer, see [RequestParser::parse].
+pub fn parse_request(
+    buf: &[u8],
+    max_body_size: usize,
+) -> Result<Option<ParsedRequest>, HTTPStatusCode> {
+    RequestParser::new(max_body_size).parse(buf)
+}
+
+/// Determines how the length of the body is given (RFC 9112, 6.3): by
+/// Content-Length, see [RequestHead::body_length], or as a chunked body.
+/// Requests with ambiguous framing are rejected, as they could be read
+/// differently by a proxy in front of the server (request smuggling).
+fn body_framing(head: &RequestHead, max_body_size: usize) -> Result<Body, HTTPStatusCode> {
+    let bad_request = HTTPStatusCode::ClientError(400);
+    let codings = match head.headers.get("transfer-encoding") {
+        Some(codings) => codings,
+        None => return Ok(Body::Length(head.body_length(max_body_size)?)),
+    };
+    if head.headers.contains("content-length") || head.version != "HTTP/1.1" {
+        return Err(bad_request);
+    }
+    let codings: Vec<String> = codings
+        .split(',')
+        .map(|c| c.trim().to_ascii_lowercase())
+        .filter(|c| !c.is_empty())
+        .collect();
+    match codings.iter().position(|c| c == "chunked") {
+        Some(idx) if idx + 1 != codings.len() => Err(bad_request),
+        Some(0) => Ok(Body::Chunked(Chunk::Size, Vec::new())),
+        // other codings (gzip, ...) are not decoded:
+        Some(_) => Err(HTTPStatusCode::ServerError(501)),
+        None => Err(bad_request),
+    }
+}
+
+/// Continues decoding a chunked body from `pos`. Returns true when the body,
+/// including the trailers, is complete; chunk extensions and trailers are ignored.
+fn parse_chunked(
+    buf: &[u8],
+    pos: &mut usize,
+    chunk: &mut Chunk,
+    body: &mut Vec<u8>,
+    max_body_size: usize,
+) -> Result<bool, HTTPStatusCode> {
+    let bad_request = HTTPStatusCode::ClientError(400);
+    loop {
+        let rest = &buf[*pos..];
+        match *chunk {
+            Chunk::Size => {
+                let line = match next_line(rest)? {
+                    Some(line) => line,
+                    None => return Ok(false),
+                };
+                *pos += line.len();
+                let size = line.split(|b| *b == b';').next().unwrap_or_default();
+                let size = std::str::from_utf8(size).map_err(|_| bad_request)?;
+                let size = size.trim_matches([' ', '\t', '\r', '\n']);
+                if size.is_empty() || !size.bytes().all(|b| b.is_ascii_hexdigit()) {
+                    return Err(bad_request);
+                }
+                let size = usize::from_str_radix(size, 16).map_err(|_| bad_request)?;
+                if size > max_body_size - body.len() {
+                    return Err(HTTPStatusCode::ClientError(413));
+                }
+                *chunk = match size {
+                    0 => Chunk::Trailers(0),
+                    size => Chunk::Data(size),
+                };
+            }
+            Chunk::Data(remaining) => {
+                let available = rest.len().min(remaining);
+                body.extend_from_slice(&rest[..available]);
+                *pos += available;
+                if available < remaining {
+                    *chunk = Chunk::Data(remaining - available);
+                    return Ok(false);
+                }
+                *chunk = Chunk::DataEnd;
+            }
+            Chunk::DataEnd => match rest {
+                [] | [b'\r'] => return Ok(false),
+                [b'\n', ..] => {
+                    *pos += 1;
+                    *chunk = Chunk::Size;
+                }
+                [b'\r', b'\n', ..] => {
+                    *pos += 2;
+                    *chunk = Chunk::Size;
+                }
+                _ => return Err(bad_request),
+            },
+            Chunk::Trailers(size) => {
+                let line = match next_line(rest)? {
+                    Some(line) => line,
+                    None => return Ok(false),
+                };
+                *pos += line.len();
+                if line.iter().all(|b| b.is_ascii_whitespace()) {
+                    return Ok(true);
+                }
+                if size + line.len() > MAX_HEAD_SIZE {
+                    return Err(HTTPStatusCode::ClientError(431));
+                }
+                *chunk = Chunk::Trailers(size + line.len());
+            }
+        }
+    }
+}
+
+/// The next line (including its line break) of a chunked body, if complete.
+fn next_line(rest: &[u8]) -> Result<Option<&[u8]>, HTTPStatusCode> {
+    match rest.iter().take(MAX_LINE_LENGTH).position(|b| *b == b'\n') {
+        Some(end) => Ok(Some(&rest[..=end])),
+        None if rest.len() >= MAX_LINE_LENGTH => Err(HTTPStatusCode::ClientError(400)),
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,474 @@
+#[cfg(test)]
+mod request_parser_test {
+    use super::super::{
+        parse_request, ConnectionLimits, HTTPStatusCode, HttpServer, HttpVerb, ParsedRequest,
+        Request, RequestParser, Response, TestResponse,
+    };
+    use proptest::prelude::*;
+    use std::io::{BufRead, Read, Write};
+    use std::net::{Shutdown, TcpListener, TcpStream};
+    use std::thread;
+    #[cfg(feature = "event-loop")]
+    use std::time::Duration;
+
+    const MAX_BODY_SIZE: usize = 1024;
+
+    fn parse(raw: &[u8]) -> Result<Option<ParsedRequest>, HTTPStatusCode> {
+        parse_request(raw, MAX_BODY_SIZE)
+    }
+
+    fn status(raw: &[u8]) -> HTTPStatusCode {
+        parse(raw).unwrap_err()
+    }
+
+    /// Parses the data in pieces ending at the given positions, like it
+    /// arrives from a connection, and then as a whole.
+    fn parse_in_pieces(
+        raw: &[u8],
+        ends: &[usize],
+    ) -> Result<Option<ParsedRequest>, HTTPStatusCode> {
+        let mut parser = RequestParser::new(MAX_BODY_SIZE);
+        for end in ends {
+            match parser.parse(&raw[..*end]) {
+                Ok(None) => (),
+                result => return result,
+            }
+        }
+        parser.parse(raw)
+    }
+
+    #[test]
+    fn test_content_length() {
+        let raw = b"POST /a?b=1 HTTP/1.1\r\nHost: a\r\nContent-Length: 5\r\n\r\nhello";
+        for end in 0..raw.len() {
+            assert!(parse(&raw[..end]).unwrap().is_none(), "at {}", end);
+        }
+        let parsed = parse(raw).unwrap().unwrap();
+        assert_eq!(parsed.len, raw.len());
+        assert_eq!(parsed.body, b"hello");
+        assert_eq!(parsed.head.method, HttpVerb::POST);
+
+        let request = parsed.into_request(None);
+        assert_eq!(request.url, "/a");
+        assert_eq!(request.body.as_deref(), Some("hello"));
+        assert_eq!(request.bytes_read(), raw.len() as u64);
+
+        // byte by byte, the parser only reports the head before the body:
+        let mut parser = RequestParser::new(MAX_BODY_SIZE);
+        for end in 0..raw.len() {
+            assert!(parser.parse(&raw[..end]).unwrap().is_none());
+            assert_eq!(parser.head_complete(), end >= raw.len() - 5, "at {}", end);
+        }
+        assert_eq!(parser.parse(raw).unwrap().unwrap().body, b"hello");
+        assert!(!parser.head_complete());
+    }
+
+    #[test]
+    fn test_pipelined() {
+        let mut buf =
+            b"GET /1 HTTP/1.1\r\nHost: a\r\n\r\nGET /2 HTTP/1.1\r\nHost: a\r\n\r\nGET".to_vec();
+        let mut parser = RequestParser::new(MAX_BODY_SIZE);
+        for url in ["/1", "/2"] {
+            let parsed = parser.parse(&buf).unwrap().unwrap();
+            assert_eq!(parsed.head.full_url, url);
+            assert!(parsed.body.is_empty());
+            buf.drain(..parsed.len);
+        }
+        assert!(parser.parse(&buf).unwrap().is_none());
+        assert_eq!(buf, b"GET");
+    }
+
+    #[test]
+    fn test_leading_empty_lines() {
+        let raw = b"\r\n\nGET /1 HTTP/1.1\r\nHost: a\r\n\r\n";
+        for end in 0..raw.len() {
+            assert!(parse(&raw[..end]).unwrap().is_none(), "at {}", end);
+        }
+        let all_ends: Vec<usize> = (0..raw.len()).collect();
+        let parsed = parse_in_pieces(raw, &all_ends).unwrap().unwrap();
+        assert_eq!(parsed.head.full_url, "/1");
+        assert_eq!(parsed.len, raw.len());
+
+        // a line break after the body of a pipelined request:
+        let mut buf = b"POST /1 HTTP/1.1\r\nHost: a\r\nContent-Length: 2\r\n\r\nab\r\n\
+                        GET /2 HTTP/1.1\r\nHost: a\r\n\r\n"
+            .to_vec();
+        let mut parser = RequestParser::new(MAX_BODY_SIZE);
+        for url in ["/1", "/2"] {
+            let parsed = parser.parse(&buf).unwrap().unwrap();
+            assert_eq!(parsed.head.full_url, url);
+            buf.drain(..parsed.len);
+        }
+        assert!(buf.is_empty());
+
+        let raw = format!("{}GET / HTTP/1.1\r\nHost: a\r\n\r\n", "\r\n".repeat(5));
+        assert_eq!(status(raw.as_bytes()), HTTPStatusCode::ClientError(400));
+    }
+
+    #[test]
+    fn test_chunked() {
+        let raw = b"POST / HTTP/1.1\r\nHost: a\r\nTransfer-Encoding: chunked\r\n\r\n\
+                    5;name=value\r\nhello\r\n1\r\n \r\nA\r\n0123456789\r\n0\r\nX-Trailer: t\r\n\r\n";
+        for end in 0..raw.len() {
+            assert!(parse(&raw[..end]).unwrap().is_none(), "at {}", end);
+        }
+        let parsed = parse(raw).unwrap().unwrap();
+        assert_eq!(parsed.len, raw.len());
+        assert_eq!(parsed.body, b"hello 0123456789");
+
+        let all_ends: Vec<usize> = (0..raw.len()).collect();
+        let parsed = parse_in_pieces(raw, &all_ends).unwrap().unwrap();
+        assert_eq!(parsed.body, b"hello 0123456789");
+
+        // the coding is case-insensitive:
+        let raw = b"POST / HTTP/1.1\r\nHost: a\r\nTransfer-Encoding: ChunKed\r\n\r\n0\r\n\r\n";
+        assert!(parse(raw).unwrap().unwrap().body.is_empty());
+    }
+
+    #[test]
+    fn test_framing_errors() {
+        let request =
+            |headers: &str| format!("POST / HTTP/1.1\r\nHost: a\r\n{}\r\n\r\n0\r\n\r\n", headers);
+        let bad_request = HTTPStatusCode::ClientError(400);
+        for headers in [
+            // ambiguous: a proxy could use either one
+            "Content-Length: 5\r\nTransfer-Encoding: chunked",
+            "Transfer-Encoding: chunked, chunked",
+            "Transfer-Encoding: chunked, gzip",
+            "Transfer-Encoding: ",
+            "Content-Length: 5, 6",
+            "Content-Length: 5\r\nContent-Length: 6",
+            "Content-Length: +5",
+            "Content-Length: -1",
+            "Content-Length: 0x10",
+            "Content-Length: ",
+        ] {
+            assert_eq!(
+                status(request(headers).as_bytes()),
+                bad_request,
+                "{}",
+                headers
+            );
+        }
+        assert_eq!(
+            status(request("Transfer-Encoding: gzip, chunked").as_bytes()),
+            HTTPStatusCode::ServerError(501)
+        );
+        // repeated, equal lengths are accepted:
+        let raw = b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 1\r\nContent-Length: 1\r\n\r\nx";
+        assert_eq!(parse(raw).unwrap().unwrap().body, b"x");
+        // chunked is not defined for HTTP/1.0:
+        let raw = b"POST / HTTP/1.0\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n";
+        assert_eq!(status(raw), bad_request);
+    }
+
+    #[test]
+    fn test_chunk_errors() {
+        let chunked = |body: &str| {
+            format!(
+                "POST / HTTP/1.1\r\nHost: a\r\nTransfer-Encoding: chunked\r\n\r\n{}",
+                body
+            )
+        };
+        let bad_request = HTTPStatusCode::ClientError(400);
+        for body in [
+            "zz\r\n",
+            "+5\r\nhello\r\n",
+            "\r\n",
+            "5\r\nhelloX",
+            "5\r\nhello\rX",
+            // overflows the size:
+            "10000000000000000\r\n",
+        ] {
+            assert_eq!(status(chunked(body).as_bytes()), bad_request, "{:?}", body);
+        }
+        let long_extension = format!("5;{}", "x".repeat(9000));
+        assert_eq!(status(chunked(&long_extension).as_bytes()), bad_request);
+        assert_eq!(
+            status(chunked("401\r\n").as_bytes()),
+            HTTPStatusCode::ClientError(413)
+        );
+        // the limit is for the whole body:
+        let body = format!("200\r\n{0}\r\n200\r\n{0}\r\n1\r\n", "x".repeat(0x200));
+        assert_eq!(
+            status(chunked(&body).as_bytes()),
+            HTTPStatusCode::ClientError(413)
+        );
+    }
+
+    #[test]
+    fn test_head_limits() {
+        let long_url = format!("GET /{}", "a".repeat(9000));
+        assert_eq!(
+            status(long_url.as_bytes()),
+            HTTPStatusCode::ClientError(413)
+        );
+        let long_header = format!("GET / HTTP/1.1\r\nX: {}", "a".repeat(9000));
+        assert_eq!(
+            status(long_header.as_bytes()),
+            HTTPStatusCode::ClientError(431)
+        );
+        let many_headers = format!("GET / HTTP/1.1\r\n{}", "X: aaaaaaaaaaaa\r\n".repeat(5000));
+        assert_eq!(
+            status(many_headers.as_bytes()),
+            HTTPStatusCode::ClientError(431)
+        );
+        assert_eq!(
+            status(b"GET / HTTP/1.1\r\n\r\n"),
+            HTTPStatusCode::ClientError(400)
+        );
+    }
+
+    /// The body size is checked before any of the body is buffered, also for
+    /// lengths that do not fit into usize.
+    #[test]
+    fn test_body_limit() {
+        for length in ["18446744073709551616", "99999999999999999999999", "1025"] {
+            let raw = format!(
+                "POST / HTTP/1.1\r\nHost: a\r\nContent-Length: {}\r\n\r\n",
+                length
+            );
+            assert_eq!(status(raw.as_bytes()), HTTPStatusCode::ClientError(413));
+        }
+        let raw = b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 1024\r\n\r\n";
+        assert!(parse(raw).unwrap().is_none());
+    }
+
+    /// Not only the length of each header line is limited, but also the number of lines.
+    #[test]
+    fn test_incomplete_head() {
+        let mut raw = b"GET / HTTP/1.1\r\n".to_vec();
+        let mut parser = RequestParser::new(MAX_BODY_SIZE);
+        let result = loop {
+            raw.extend_from_slice(b"X-Header: value\r\n");
+            match parser.parse(&raw) {
+                Ok(None) => (),
+                result => break result,
+            }
+        };
+        assert_eq!(result.unwrap_err(), HTTPStatusCode::ClientError(431));
+        assert!(raw.len() < 65 * 1024);
+    }
+
+    /// Sends the data on a connection, closes the sending side, and reads the
+    /// request from the server side of the connection.
+    fn from_connection(
+        raw: &'static [u8],
+        limits: ConnectionLimits,
+    ) -> Result<Request, HTTPStatusCode> {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let writer = thread::spawn(move || {
+            // the server may answer before all is written:
+            let _ = client.write_all(raw);
+            let _ = client.shutdown(Shutdown::Write);
+            client
+        });
+        let (stream, _) = listener.accept().unwrap();
+        let request = Request::from_tcp_stream(stream, &limits);
+        drop(writer.join().unwrap());
+        request
+    }
+
+    /// The blocking front end reads through the same parser.
+    #[test]
+    fn test_from_tcp_stream() {
+        let limits = ConnectionLimits {
+            max_body_size: MAX_BODY_SIZE,
+            ..ConnectionLimits::default()
+        };
+        // closed in the middle of the head:
+        assert_eq!(
+            from_connection(b"GET / HTT", limits).err(),
+            Some(HTTPStatusCode::ClientError(400))
+        );
+        assert_eq!(
+            from_connection(b"", limits).err(),
+            Some(HTTPStatusCode::ClientError(400))
+        );
+
+        // only this request is taken from the connection, the next one stays buffered:
+        let raw = b"POST / HTTP/1.1\r\nHost: a\r\nTransfer-Encoding: chunked\r\n\r\n\
+                    3\r\nabc\r\n0\r\n\r\nGET /next HTTP/1.1\r\n";
+        let mut request = from_connection(raw, limits).unwrap();
+        assert_eq!(request.body.as_deref(), Some("abc"));
+        let (_, mut reader) = request.take_connection().unwrap();
+        assert_eq!(reader.fill_buf().unwrap(), b"GET /next HTTP/1.1\r\n");
+    }
+
+    /// A server answering with the length of the request body.
+    fn body_length_server() -> HttpServer {
+        let mut server = HttpServer::new("127.0.0.1:0");
+        server.route(HttpVerb::POST, "/", |request: &Request| {
+            let length = request.body.as_ref().map(|b| b.len()).unwrap_or_default();
+            Response::text(HTTPStatusCode::Success(200), &length.to_string())
+        });
+        server
+    }
+
+    #[test]
+    fn test_chunked_request() {
+        let server = body_length_server().spawn_ephemeral().unwrap();
+        let mut stream = TcpStream::connect(server.addr()).unwrap();
+        stream
+            .write_all(
+                b"POST / HTTP/1.1\r\nHost: a\r\nTransfer-Encoding: chunked\r\n\r\n\
+                  4\r\nWiki\r\n5\r\npedia\r\n0\r\n\r\n",
+            )
+            .unwrap();
+        let mut raw = Vec::new();
+        stream.read_to_end(&mut raw).unwrap();
+        assert_eq!(TestResponse::parse(&raw).unwrap().text(), "9");
+    }
+
+    /// A body larger than a single read of the event loop is read to the end,
+    /// although the socket is edge-triggered.
+    #[cfg(feature = "event-loop")]
+    #[test]
+    fn test_event_loop_large_body() {
+        use crate::httpclient::HttpClient;
+        use crate::httpserver::IoMode;
+
+        let mut server = body_length_server();
+        server.io_mode(IoMode::EventLoop);
+        let server = server.spawn_ephemeral().unwrap();
+        let body = "x".repeat(300 * 1024);
+        let client = HttpClient::new().timeout(Duration::from_secs(5));
+        let response = client
+            .post(&server.url("/"))
+            .body(body.as_str())
+            .send()
+            .unwrap();
+        assert_eq!(response.text(), body.len().to_string());
+    }
+
+    /// A request as generated for the property tests.
+    #[derive(Debug, Clone)]
+    struct RandomRequest {
+        method: &'static str,
+        target: String,
+        headers: Vec<(String, String)>,
+        body: Vec<u8>,
+        /// sizes of the chunks, repeated until the body is sent; not chunked if None
+        chunks: Option<Vec<usize>>,
+    }
+
+    impl RandomRequest {
+        fn to_bytes(&self) -> Vec<u8> {
+            let mut raw = format!(
+                "{} {} HTTP/1.1\r\nHost: example.com\r\n",
+                self.method, self.target
+            );
+            for (name, value) in &self.headers {
+                raw += &format!("{}: {}\r\n", name, value);
+            }
+            let mut raw = match &self.chunks {
+                Some(_) => raw + "Transfer-Encoding: chunked\r\n\r\n",
+                None => raw + &format!("Content-Length: {}\r\n\r\n", self.body.len()),
+            }
+            .into_bytes();
+            match &self.chunks {
+                Some(sizes) => {
+                    let mut rest = &self.body[..];
+                    for size in sizes.iter().cycle() {
+                        if rest.is_empty() {
+                            break;
+                        }
+                        let (chunk, tail) = rest.split_at((*size).min(rest.len()));
+                        raw.extend_from_slice(format!("{:x};ext=1\r\n", chunk.len()).as_bytes());
+                        raw.extend_from_slice(chunk);
+                        raw.extend_from_slice(b"\r\n");
+                        rest = tail;
+                    }
+                    raw.extend_from_slice(b"0\r\nX-Trailer: t\r\n\r\n");
+                }
+                None => raw.extend_from_slice(&self.body),
+            }
+            raw
+        }
+    }
+
+    fn random_request() -> impl Strategy<Value = RandomRequest> {
+        let methods = vec!["GET", "POST", "PUT", "PATCH", "DELETE", "HEAD", "OPTIONS"];
+        (
+            prop::sample::select(methods),
+            "/[a-zA-Z0-9/._~%-]{0,30}(\\?[a-z0-9=&]{0,20})?",
+            prop::collection::vec(("[a-z][a-z0-9-]{0,10}", "[!-~]([ -~]{0,20}[!-~])?"), 0..8),
+            prop::collection::vec(any::<u8>(), 0..300),
+            prop::option::of(prop::collection::vec(1usize..64, 1..10)),
+        )
+            .prop_map(|(method, target, headers, body, chunks)| RandomRequest {
+                method,
+                target,
+                // distinct names, so that no headers are combined:
+                headers: headers
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, (name, value))| (format!("x-{}-{}", i, name), value))
+                    .collect(),
+                body,
+                chunks,
+            })
+    }
+
+    /// Bytes that get far into the parser: random data after a valid head.
+    fn random_input() -> impl Strategy<Value = Vec<u8>> {
+        prop_oneof![
+            prop::collection::vec(any::<u8>(), 0..200),
+            "[ -~\r\n]{0,200}".prop_map(String::into_bytes),
+            "[0-9a-fA-F;xX= \r\n]{0,200}".prop_map(|body| {
+                let head = "POST / HTTP/1.1\r\nHost: a\r\nTransfer-Encoding: chunked\r\n\r\n";
+                (String::from(head) + &body).into_bytes()
+            }),
+            "[0-9a-zA-Z:, \r\n-]{0,200}".prop_map(|headers| {
+                (String::from("POST / HTTP/1.1\r\nHost: a\r\n") + &headers).into_bytes()
+            }),
+        ]
+    }
+
+    /// Positions to split the data at, sorted.
+    fn split_points(len: usize, indexes: &[prop::sample::Index]) -> Vec<usize> {
+        let mut ends: Vec<usize> = indexes.iter().map(|i| i.index(len + 1)).collect();
+        ends.sort_unstable();
+        ends
+    }
+
+    proptest! {
+        #[test]
+        fn prop_round_trip(request in random_request()) {
+            let raw = request.to_bytes();
+            let parsed = parse(&raw).unwrap().unwrap();
+            prop_assert_eq!(parsed.len, raw.len());
+            prop_assert_eq!(parsed.head.method.as_str(), request.method);
+            prop_assert_eq!(&parsed.head.full_url, &request.target);
+            prop_assert_eq!(&parsed.head.version, "HTTP/1.1");
+            for (name, value) in &request.headers {
+                prop_assert_eq!(parsed.head.headers.get(name), Some(value.clone()));
+            }
+            prop_assert_eq!(&parsed.body, &request.body);
+        }
+
+        #[test]
+        fn prop_split_reads(
+            request in random_request(),
+            indexes in prop::collection::vec(any::<prop::sample::Index>(), 0..8),
+        ) {
+            let raw = request.to_bytes();
+            let ends = split_points(raw.len() - 1, &indexes);
+            let parsed = parse_in_pieces(&raw, &ends).unwrap().unwrap();
+            prop_assert_eq!(parsed.len, raw.len());
+            prop_assert_eq!(&parsed.body, &request.body);
+        }
+
+        #[test]
+        fn prop_no_panic(
+            raw in random_input(),
+            indexes in prop::collection::vec(any::<prop::sample::Index>(), 0..8),
+        ) {
+            // the same result, however the data arrives:
+            let whole = parse(&raw);
+            let pieces = parse_in_pieces(&raw, &split_points(raw.len(), &indexes));
+            prop_assert_eq!(format!("{:?}", whole), format!("{:?}", pieces));
+        }
+    }
+}
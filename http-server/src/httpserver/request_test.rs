@@ -0,0 +1,183 @@
+#[cfg(test)]
+mod request_test {
+    use super::super::{ConnectionLimits, HTTPStatusCode, Request};
+    use std::io::Write;
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+    use std::time::Duration;
+
+    /// Sends the given chunks to a fresh connection, waiting `pause` between
+    /// them, and parses the request on the server side.
+    fn parse(
+        chunks: &'static [&'static [u8]],
+        pause: Duration,
+        limits: ConnectionLimits,
+    ) -> Result<Request, HTTPStatusCode> {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let writer = thread::spawn(move || {
+            for chunk in chunks {
+                if client.write_all(chunk).is_err() {
+                    break;
+                }
+                thread::sleep(pause);
+            }
+        });
+        let (server, _) = listener.accept().unwrap();
+        let result = Request::from_tcp_stream(server, &limits);
+        writer.join().unwrap();
+        result
+    }
+
+    fn status(result: Result<Request, HTTPStatusCode>) -> usize {
+        match result {
+            Ok(_) => 200,
+            Err(status) => status.code(),
+        }
+    }
+
+    #[test]
+    fn test_reads_body() {
+        let request = parse(
+            &[b"POST / HTTP/1.1\r\nHost: x\r\nContent-Length: 5\r\n\r\nhello"],
+            Duration::ZERO,
+            ConnectionLimits::default(),
+        )
+        .unwrap();
+        assert_eq!(request.body, Some(String::from("hello")));
+    }
+
+    #[test]
+    fn test_header_timeout() {
+        let limits = ConnectionLimits {
+            header_timeout: Duration::from_millis(200),
+            ..ConnectionLimits::default()
+        };
+        let result = parse(
+            &[b"GET / HTTP/1.1\r\n", b"Host: x\r\n"],
+            Duration::from_millis(500),
+            limits,
+        );
+        assert_eq!(status(result), 408);
+    }
+
+    #[test]
+    fn test_body_timeout() {
+        let limits = ConnectionLimits {
+            body_timeout: Duration::from_millis(200),
+            min_body_rate: None,
+            ..ConnectionLimits::default()
+        };
+        let result = parse(
+            &[
+                b"POST / HTTP/1.1\r\nHost: x\r\nContent-Length: 10\r\n\r\nhel",
+                b"lo",
+            ],
+            Duration::from_millis(500),
+            limits,
+        );
+        assert_eq!(status(result), 408);
+    }
+
+    #[test]
+    fn test_short_body() {
+        // the client closes the connection after 3 of 10 bytes:
+        let result = parse(
+            &[b"POST / HTTP/1.1\r\nHost: x\r\nContent-Length: 10\r\n\r\nhel"],
+            Duration::ZERO,
+            ConnectionLimits::default(),
+        );
+        assert_eq!(status(result), 400);
+    }
+
+    #[test]
+    fn test_invalid_content_length() {
+        let result = parse(
+            &[b"POST / HTTP/1.1\r\nHost: x\r\nContent-Length: 1x\r\n\r\nx"],
+            Duration::ZERO,
+            ConnectionLimits::default(),
+        );
+        assert_eq!(status(result), 400);
+    }
+
+    #[test]
+    fn test_body_too_large() {
+        let limits = ConnectionLimits {
+            max_body_size: 4,
+            ..ConnectionLimits::default()
+        };
+        // rejected on the announced length, before the body is read:
+        let result = parse(
+            &[b"POST / HTTP/1.1\r\nHost: x\r\nContent-Length: 5\r\n\r\n"],
+            Duration::ZERO,
+            limits,
+        );
+        assert_eq!(status(result), 413);
+    }
+
+    #[test]
+    fn test_transfer_encoding_with_content_length() {
+        let result = parse(
+            &[b"POST / HTTP/1.1\r\nHost: x\r\nContent-Length: 3\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n"],
+            Duration::ZERO,
+            ConnectionLimits::default(),
+        );
+        assert_eq!(status(result), 400);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_body() {
+        use super::super::{HttpVerb, Response, Router, TestClient};
+        use std::collections::BTreeMap;
+
+        let mut router = Router::new();
+        router.route(HttpVerb::POST, "/users", |request: &Request| {
+            let user: Result<BTreeMap<String, String>, _> = request.json();
+            match user {
+                Ok(user) => Response::text(HTTPStatusCode::Success(201), &user["name"]),
+                Err(problem) => problem.into(),
+            }
+        });
+        let client = TestClient::new(router);
+        let post = |content_type: Option<&str>, body: &str| {
+            let request = client.post("/users").body(body);
+            match content_type {
+                Some(content_type) => request.header("Content-Type", content_type),
+                None => request,
+            }
+            .send()
+        };
+
+        let response = post(
+            Some("application/json; charset=utf-8"),
+            r#"{"name":"alex"}"#,
+        );
+        assert_eq!(response.status.code(), 201);
+        assert_eq!(response.text(), "alex");
+        let response = post(Some("application/merge-patch+json"), r#"{"name":"kim"}"#);
+        assert_eq!(response.text(), "kim");
+
+        for (content_type, body, status) in [
+            (None, r#"{"name":"alex"}"#, 415),
+            (Some("text/plain"), r#"{"name":"alex"}"#, 415),
+            (Some("application/json"), r#"{"name":"#, 422),
+            (Some("application/json"), r#"{"name":1}"#, 422),
+        ] {
+            let response = post(content_type, body);
+            assert_eq!(
+                response.status.code(),
+                status,
+                "{:?} {}",
+                content_type,
+                body
+            );
+            assert_eq!(
+                response.headers.get("Content-Type").as_deref(),
+                Some("application/problem+json")
+            );
+            let problem: BTreeMap<String, serde_json::Value> = response.json().unwrap();
+            assert_eq!(problem["status"], status);
+        }
+    }
+}
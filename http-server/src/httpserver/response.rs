@@ -0,0 +1,189 @@
+use std::io::{Result, Write};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+use crate::httpserver::{HTTPStatusCode, HeaderMap, HttpVerb, Problem};
+
+/// Writes the body of a streaming response to the connection, and returns the
+/// number of bytes written. The flag is set when the server stops: the function
+/// should return soon after.
+pub(crate) type StreamBody =
+    Box<dyn FnOnce(Box<dyn Write + Send>, Arc<AtomicBool>) -> Result<usize> + Send>;
+
+/// An HTTP response, as returned by the route handlers and middlewares: fully
+/// buffered, or with a streaming body, like an
+/// [EventStream](crate::httpserver::EventStream).
+pub struct Response {
+    pub status: HTTPStatusCode,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+    stream: Option<StreamBody>,
+}
+
+impl Response {
+    pub fn new(status: HTTPStatusCode) -> Response {
+        Response {
+            status,
+            headers: HeaderMap::new(),
+            body: Vec::new(),
+            stream: None,
+        }
+    }
+
+    /// Creates a plain text (utf-8) response.
+    pub fn text(status: HTTPStatusCode, body: &str) -> Response {
+        Response::new(status)
+            .with_header("Content-Type", "text/plain; charset=utf-8")
+            .with_body(body)
+    }
+
+    /// Creates a 200 response with the value serialized as JSON. If the value
+    /// cannot be serialized, a 500 problem response is returned instead.
+    #[cfg(feature = "serde")]
+    pub fn json<T: serde::Serialize>(value: &T) -> Response {
+        match serde_json::to_vec(value) {
+            Ok(body) => Response::new(HTTPStatusCode::Success(200))
+                .with_header("Content-Type", "application/json")
+                .with_body(body),
+            Err(e) => crate::httpserver::Problem::new(HTTPStatusCode::ServerError(500))
+                .with_detail(&e.to_string())
+                .into(),
+        }
+    }
+
+    pub fn with_header(mut self, key: &str, value: &str) -> Response {
+        self.headers.set(key, value);
+        self
+    }
+
+    pub fn with_body<T: Into<Vec<u8>>>(mut self, body: T) -> Response {
+        self.body = body.into();
+        self
+    }
+
+    /// Makes this a streaming response: the body is written by the given function
+    /// after the head, and the connection is closed when it returns.
+    pub(crate) fn with_stream<F>(mut self, stream: F) -> Response
+    where
+        F: FnOnce(Box<dyn Write + Send>, Arc<AtomicBool>) -> Result<usize> + Send + 'static,
+    {
+        self.stream = Some(Box::new(stream));
+        self
+    }
+
+    /// Whether the body is streamed instead of buffered in `body`.
+    pub fn is_streaming(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    pub(crate) fn take_stream(&mut self) -> Option<StreamBody> {
+        self.stream.take()
+    }
+
+    /// Adds a header name to the Vary header, if it is not already listed.
+    pub fn add_vary(&mut self, header: &str) {
+        let already_listed = match self.headers.get("Vary") {
+            Some(vary) => vary
+                .split(',')
+                .any(|v| v.trim() == "*" || v.trim().eq_ignore_ascii_case(header)),
+            None => false,
+        };
+        if !already_listed {
+            self.headers.append("Vary", header);
+        }
+    }
+
+    /// Writes the status line, the headers and the body to the given writer,
+    /// returning the number of bytes written.
+    /// A Content-Length header is added if the handler did not set one.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<usize> {
+        let head_len = self.write_head_to(writer)?;
+        writer.write_all(&self.body)?;
+        writer.flush()?;
+        Ok(head_len + self.body.len())
+    }
+
+    /// Writes the status line and the headers only, e.g. as answer to a HEAD request:
+    /// the Content-Length is still the one of the (not sent) body.
+    pub fn write_head_to<W: Write>(&self, writer: &mut W) -> Result<usize> {
+        let mut head = format!(
+            "HTTP/1.1 {} {}\r\n",
+            self.status.code(),
+            self.status.message()
+        );
+        for (key, value) in self.headers.iter() {
+            head += &format!("{}: {}\r\n", key, value);
+        }
+        if !self.headers.contains("Content-Length") && self.may_have_content_length() {
+            head += &format!("Content-Length: {}\r\n", self.body.len());
+        }
+        head += "\r\n";
+
+        writer.write_all(head.as_bytes())?;
+        writer.flush()?;
+        Ok(head.len())
+    }
+
+    /// Writes the response to a connection that is closed afterwards, the same
+    /// way for the server and the [TestClient](crate::httpserver::TestClient):
+    /// with `Connection: close`, only the head as answer to a HEAD request, and
+    /// for a streaming response the head, then the body written by the producer,
+    /// which `run_stream` runs on a writer for the connection. Returns the number
+    /// of bytes written.
+    pub(crate) fn write_and_close<W, F>(
+        mut self,
+        method: &HttpVerb,
+        connection: &mut W,
+        run_stream: F,
+    ) -> Result<usize>
+    where
+        W: Write,
+        F: FnOnce(&W, StreamBody) -> Result<usize>,
+    {
+        // TODO: Connection header should not be used, see https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Connection
+        self.headers.set("Connection", "close");
+        if *method == HttpVerb::HEAD {
+            return self.write_head_to(connection);
+        }
+        let written = self.write_to(connection)?;
+        match self.take_stream() {
+            // the connection stays open until the body is written completely,
+            // or the client has gone away:
+            Some(stream) => Ok(written + run_stream(connection, stream)?),
+            None => Ok(written),
+        }
+    }
+
+    /// Serializes the response for writing it out later, e.g. by a non-blocking writer.
+    /// For HEAD requests, only the status line and the headers are included.
+    /// A streaming response cannot be serialized, it is replaced by a 501 problem.
+    pub fn to_bytes(&self, method: &HttpVerb) -> Vec<u8> {
+        if self.is_streaming() {
+            return Response::stream_not_supported().to_bytes(method);
+        }
+        let mut bytes = Vec::new();
+        // writing to a Vec cannot fail:
+        let _ = match method {
+            HttpVerb::HEAD => self.write_head_to(&mut bytes),
+            _ => self.write_to(&mut bytes),
+        };
+        bytes
+    }
+
+    /// The answer for a streaming response where only buffered responses can be sent.
+    pub(crate) fn stream_not_supported() -> Response {
+        Problem::new(HTTPStatusCode::ServerError(501))
+            .with_detail("Streaming responses are only supported over blocking HTTP/1.1 connections")
+            .into()
+    }
+
+    /// 1xx and 204 responses must not contain a Content-Length, and a 304 would
+    /// have to send the length of the unmodified representation (RFC 9110, 8.6).
+    /// The length of a streamed body is not known in advance.
+    pub(crate) fn may_have_content_length(&self) -> bool {
+        !self.is_streaming()
+            && !matches!(self.status, HTTPStatusCode::Info(_))
+            && self.status.code() != 204
+            && self.status.code() != 304
+    }
+}
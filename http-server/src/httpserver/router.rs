@@ -0,0 +1,222 @@
+use std::sync::Arc;
+
+use crate::httpserver::{HTTPStatusCode, HttpVerb, Middleware, Next, Problem, Request, Response};
+
+/// A request handler: a closure that builds the response for a request.
+pub type Handler = dyn Fn(&Request) -> Response + Send + Sync;
+
+#[derive(Clone)]
+struct Route {
+    method: HttpVerb,
+    path: String,
+    handler: Arc<Handler>,
+}
+
+/// Maps the incoming requests to their handler, by method and path, and runs
+/// the middlewares registered for the request path around it.
+///
+/// A route path is either matched exactly (`/status`), or - if it ends with `/*` -
+/// as prefix (`/static/*` matches `/static` and everything below).
+///
+/// Some methods are handled by the router itself, if no explicit route is registered:
+/// - HEAD runs the GET route (the body is suppressed when the response is written)
+/// - OPTIONS answers with the `Allow` header for the path (or all methods for `OPTIONS *`)
+/// - TRACE echoes the request, but only if enabled (it is off by default)
+/// - unknown methods, and extension methods without any route, are answered
+///   with 501 Not Implemented
+#[derive(Clone, Default)]
+pub struct Router {
+    routes: Vec<Route>,
+    middlewares: Vec<(String, Arc<dyn Middleware>)>,
+    trace_enabled: bool,
+}
+
+impl Router {
+    pub fn new() -> Router {
+        Router::default()
+    }
+
+    pub fn route<F>(&mut self, method: HttpVerb, path: &str, handler: F)
+    where
+        F: Fn(&Request) -> Response + Send + Sync + 'static,
+    {
+        self.routes.push(Route {
+            method,
+            path: String::from(path),
+            handler: Arc::new(handler),
+        });
+    }
+
+    /// Registers a middleware for all requests whose path lies below the given prefix.
+    /// Middlewares run in the order they were registered.
+    pub fn middleware<M>(&mut self, prefix: &str, middleware: M)
+    where
+        M: Middleware + 'static,
+    {
+        self.middlewares
+            .push((String::from(prefix), Arc::new(middleware)));
+    }
+
+    /// Adds the routes and middlewares of another router, after the own ones.
+    /// TRACE is answered if either router has it enabled.
+    pub fn merge(&mut self, other: Router) {
+        self.routes.extend(other.routes);
+        self.middlewares.extend(other.middlewares);
+        self.trace_enabled |= other.trace_enabled;
+    }
+
+    /// Enables / disables the automatic answering of TRACE requests.
+    pub fn trace(&mut self, enabled: bool) {
+        self.trace_enabled = enabled;
+    }
+
+    pub fn dispatch(&self, request: &Request) -> Response {
+        let middlewares: Vec<&dyn Middleware> = self
+            .middlewares
+            .iter()
+            .filter(|(prefix, _)| path_has_prefix(&request.url, prefix))
+            .map(|(_, middleware)| middleware.as_ref())
+            .collect();
+
+        let endpoint = |request: &Request| self.run_handler(request);
+        Next::new(&middlewares, &endpoint).run(request)
+    }
+
+    fn run_handler(&self, request: &Request) -> Response {
+        if !self.is_implemented(&request.method) {
+            return Problem::new(HTTPStatusCode::ServerError(501))
+                .with_detail(&format!("The method {} is not supported", request.method))
+                .into();
+        }
+        if request.method == HttpVerb::OPTIONS && request.url == "*" {
+            return self.options_response(self.allowed_methods("*"));
+        }
+
+        if let Some(route) = self.find_route(&request.method, &request.url) {
+            return (route.handler)(request);
+        }
+        let allowed = self.allowed_methods(&request.url);
+        match request.method {
+            HttpVerb::HEAD => {
+                if let Some(route) = self.find_route(&HttpVerb::GET, &request.url) {
+                    return (route.handler)(request);
+                }
+            }
+            HttpVerb::OPTIONS if !allowed.is_empty() => return self.options_response(allowed),
+            HttpVerb::TRACE if self.trace_enabled => return trace_response(request),
+            _ => (),
+        }
+
+        match allowed.is_empty() {
+            true => Problem::new(HTTPStatusCode::ClientError(404))
+                .with_instance(&request.url)
+                .into(),
+            false => Response::from(
+                Problem::new(HTTPStatusCode::ClientError(405)).with_detail(&format!(
+                    "The method {} is not allowed on {}",
+                    request.method, request.url
+                )),
+            )
+            .with_header("Allow", &method_list(&allowed)),
+        }
+    }
+
+    /// Lists the methods that can be used on the given path (`*` for the whole server),
+    /// including the ones the router answers itself. Empty if no route matches the path.
+    pub fn allowed_methods(&self, path: &str) -> Vec<HttpVerb> {
+        let mut methods: Vec<HttpVerb> = Vec::new();
+        for route in self.routes.iter() {
+            if (path == "*" || route_matches(&route.path, path)) && !methods.contains(&route.method) {
+                methods.push(route.method.clone());
+            }
+        }
+        if methods.is_empty() {
+            return methods;
+        }
+
+        if methods.contains(&HttpVerb::GET) && !methods.contains(&HttpVerb::HEAD) {
+            methods.push(HttpVerb::HEAD);
+        }
+        if !methods.contains(&HttpVerb::OPTIONS) {
+            methods.push(HttpVerb::OPTIONS);
+        }
+        if self.trace_enabled && !methods.contains(&HttpVerb::TRACE) {
+            methods.push(HttpVerb::TRACE);
+        }
+        methods
+    }
+
+    /// The pattern of the route handling the request, used to label metrics
+    /// (so that `/static/a.css` and `/static/b.css` both count as `/static/*`).
+    pub fn route_label(&self, method: &HttpVerb, path: &str) -> String {
+        let route = self
+            .find_route(method, path)
+            .or_else(|| self.find_route(&HttpVerb::GET, path))
+            .or_else(|| self.routes.iter().find(|route| route_matches(&route.path, path)));
+        match route {
+            Some(route) => route.path.clone(),
+            None if path == "*" => String::from("*"),
+            None => String::from("unmatched"),
+        }
+    }
+
+    /// Extension methods are only known to the server if some route uses them.
+    fn is_implemented(&self, method: &HttpVerb) -> bool {
+        match method {
+            HttpVerb::UNKNOWN => false,
+            HttpVerb::Extension(_) => self.routes.iter().any(|route| route.method == *method),
+            _ => true,
+        }
+    }
+
+    fn find_route(&self, method: &HttpVerb, path: &str) -> Option<&Route> {
+        self.routes
+            .iter()
+            .find(|route| route.method == *method && route_matches(&route.path, path))
+    }
+
+    fn options_response(&self, allowed: Vec<HttpVerb>) -> Response {
+        Response::new(HTTPStatusCode::Success(204)).with_header("Allow", &method_list(&allowed))
+    }
+}
+
+fn method_list(methods: &[HttpVerb]) -> String {
+    methods
+        .iter()
+        .map(HttpVerb::to_string)
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Echoes the received request message back to the client (RFC 9110, 9.3.8).
+/// Credentials are left out, as they should not be reflected.
+fn trace_response(request: &Request) -> Response {
+    let mut message = format!("TRACE {} HTTP/1.1\r\n", request.full_url);
+    for (key, value) in request.headers.iter() {
+        if key.eq_ignore_ascii_case("Authorization") || key.eq_ignore_ascii_case("Cookie") {
+            continue;
+        }
+        message += &format!("{}: {}\r\n", key, value);
+    }
+    message += "\r\n";
+    Response::new(HTTPStatusCode::Success(200))
+        .with_header("Content-Type", "message/http")
+        .with_body(message)
+}
+
+fn route_matches(route_path: &str, url: &str) -> bool {
+    match route_path.strip_suffix("/*") {
+        Some(prefix) => path_has_prefix(url, prefix),
+        None => route_path == url,
+    }
+}
+
+/// Checks if the path lies below the given prefix, respecting path segments:
+/// `/admin` is a prefix of `/admin` and `/admin/users`, but not of `/administrator`.
+pub fn path_has_prefix(path: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    match path.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
@@ -0,0 +1,161 @@
+#[cfg(test)]
+mod router_test {
+    use super::super::router::*;
+    use super::super::{HTTPStatusCode, HttpVerb, Request, RequestHead, Response};
+
+    fn router() -> Router {
+        let mut router = Router::new();
+        let ok = |_: &_| Response::new(HTTPStatusCode::Success(200));
+        router.route(HttpVerb::GET, "/users", ok);
+        router.route(HttpVerb::POST, "/users", ok);
+        router.route(HttpVerb::DELETE, "/static/*", ok);
+        router.route(HttpVerb::from("PROPFIND"), "/dav/*", ok);
+        router
+    }
+
+    fn request(request_line: &str, headers: &[&str]) -> Request {
+        let mut lines = vec![String::from("Host: a")];
+        lines.extend(headers.iter().map(|h| String::from(*h)));
+        let head = RequestHead::parse(request_line, &lines).unwrap();
+        Request::from_head(head, None, None)
+    }
+
+    #[test]
+    fn test_allowed_methods() {
+        let router = router();
+        assert_eq!(
+            router.allowed_methods("/users"),
+            vec![HttpVerb::GET, HttpVerb::POST, HttpVerb::HEAD, HttpVerb::OPTIONS]
+        );
+        assert_eq!(
+            router.allowed_methods("/static/css/main.css"),
+            vec![HttpVerb::DELETE, HttpVerb::OPTIONS]
+        );
+        assert_eq!(
+            router.allowed_methods("/dav/file.txt"),
+            vec![HttpVerb::Extension(String::from("PROPFIND")), HttpVerb::OPTIONS]
+        );
+        assert_eq!(router.allowed_methods("/unknown"), vec![]);
+    }
+
+    #[test]
+    fn test_allowed_methods_server_wide() {
+        let mut router = router();
+        assert_eq!(
+            router.allowed_methods("*"),
+            vec![
+                HttpVerb::GET,
+                HttpVerb::POST,
+                HttpVerb::DELETE,
+                HttpVerb::Extension(String::from("PROPFIND")),
+                HttpVerb::HEAD,
+                HttpVerb::OPTIONS
+            ]
+        );
+        router.trace(true);
+        assert!(router.allowed_methods("*").contains(&HttpVerb::TRACE));
+        assert!(router.allowed_methods("/users").contains(&HttpVerb::TRACE));
+    }
+
+    #[test]
+    fn test_head() {
+        let mut router = router();
+        router.route(HttpVerb::GET, "/text", |_| {
+            Response::text(HTTPStatusCode::Success(200), "hello")
+        });
+        // answered by the GET route, the body is suppressed when writing:
+        let response = router.dispatch(&request("HEAD /text HTTP/1.1", &[]));
+        assert_eq!(response.status.code(), 200);
+        let mut written = Vec::new();
+        let len = response.write_head_to(&mut written).unwrap();
+        assert_eq!(len, written.len());
+        let written = String::from_utf8(written).unwrap();
+        assert!(written.contains("Content-Length: 5\r\n"));
+        assert!(written.ends_with("\r\n\r\n"));
+        assert!(!written.contains("hello"));
+        assert_eq!(response.to_bytes(&HttpVerb::HEAD), written.as_bytes());
+    }
+
+    #[test]
+    fn test_options() {
+        let router = router();
+        let response = router.dispatch(&request("OPTIONS * HTTP/1.1", &[]));
+        assert_eq!(response.status.code(), 204);
+        assert_eq!(
+            response.headers.get("allow").unwrap(),
+            "GET, POST, DELETE, PROPFIND, HEAD, OPTIONS"
+        );
+        let response = router.dispatch(&request("OPTIONS /users HTTP/1.1", &[]));
+        assert_eq!(response.status.code(), 204);
+        assert_eq!(
+            response.headers.get("allow").unwrap(),
+            "GET, POST, HEAD, OPTIONS"
+        );
+    }
+
+    #[test]
+    fn test_not_implemented() {
+        let router = router();
+        // an extension method without any route:
+        let response = router.dispatch(&request("BREW /users HTTP/1.1", &[]));
+        assert_eq!(response.status.code(), 501);
+        // an extension method routed elsewhere:
+        let response = router.dispatch(&request("PROPFIND /users HTTP/1.1", &[]));
+        assert_eq!(response.status.code(), 405);
+        assert_eq!(
+            response.headers.get("allow").unwrap(),
+            "GET, POST, HEAD, OPTIONS"
+        );
+    }
+
+    #[test]
+    fn test_trace() {
+        let mut router = router();
+        let trace = request(
+            "TRACE /users?a=1 HTTP/1.1",
+            &[
+                "X-Test: 1",
+                "Authorization: Bearer secret",
+                "Cookie: id=secret",
+            ],
+        );
+        let response = router.dispatch(&trace);
+        assert_eq!(response.status.code(), 405);
+        assert!(!response.headers.get("allow").unwrap().contains("TRACE"));
+
+        router.trace(true);
+        let response = router.dispatch(&trace);
+        assert_eq!(response.status.code(), 200);
+        assert_eq!(
+            response.headers.get("content-type").unwrap(),
+            "message/http"
+        );
+        let body = String::from_utf8(response.body).unwrap();
+        assert!(body.starts_with("TRACE /users?a=1 HTTP/1.1\r\n"));
+        assert!(body.to_lowercase().contains("x-test: 1\r\n"));
+        assert!(!body.contains("secret"));
+        assert!(body.ends_with("\r\n\r\n"));
+    }
+
+    #[test]
+    fn test_route_label() {
+        let mut router = router();
+        let ok = |_: &_| Response::new(HTTPStatusCode::Success(200));
+        router.route(HttpVerb::GET, "/static/css/*", ok);
+        assert_eq!(router.route_label(&HttpVerb::GET, "/users"), "/users");
+        assert_eq!(router.route_label(&HttpVerb::GET, "/static/css/main.css"), "/static/css/*");
+        assert_eq!(router.route_label(&HttpVerb::HEAD, "/users"), "/users");
+        assert_eq!(router.route_label(&HttpVerb::DELETE, "/static/js/main.js"), "/static/*");
+        assert_eq!(router.route_label(&HttpVerb::GET, "/unknown"), "unmatched");
+    }
+
+    #[test]
+    fn test_path_has_prefix() {
+        assert!(path_has_prefix("/admin", "/admin"));
+        assert!(path_has_prefix("/admin/users", "/admin"));
+        assert!(path_has_prefix("/admin/users", "/admin/"));
+        assert!(path_has_prefix("/anything", "/"));
+        assert!(!path_has_prefix("/administrator", "/admin"));
+        assert!(!path_has_prefix("/", "/admin"));
+    }
+}
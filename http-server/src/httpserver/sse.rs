@@ -0,0 +1,256 @@
+//! Server-Sent Events (`text/event-stream`): the handler returns a streaming
+//! response, and the events are pushed through an [EventSender] while the
+//! connection stays open.
+
+use std::io::{self, ErrorKind, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::httpserver::{HTTPStatusCode, Request, Response};
+
+/// The shortest heartbeat interval: a shorter one would keep the heartbeat
+/// thread writing all the time.
+pub const MIN_HEARTBEAT: Duration = Duration::from_millis(10);
+
+/// A single event of an event stream. Line breaks in the data are sent as
+/// multiple `data` lines; line breaks in the id and the event name are removed,
+/// as they would end the field.
+///
+/// ```ignore
+/// sender.send(&Event::new("42%").id("17").event("progress"))?;
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Event {
+    id: Option<String>,
+    event: Option<String>,
+    data: String,
+    retry: Option<Duration>,
+}
+
+impl Event {
+    pub fn new(data: &str) -> Event {
+        Event {
+            data: String::from(data),
+            ..Event::default()
+        }
+    }
+
+    /// The id a reconnecting client sends back in the `Last-Event-ID` header.
+    pub fn id(mut self, id: &str) -> Event {
+        self.id = Some(single_line(id));
+        self
+    }
+
+    /// The event type, `message` if not set.
+    pub fn event(mut self, event: &str) -> Event {
+        self.event = Some(single_line(event));
+        self
+    }
+
+    /// The time the client waits before reconnecting after the connection is lost.
+    pub fn retry(mut self, retry: Duration) -> Event {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// The event in the wire format, ending with the empty line that dispatches it.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = String::new();
+        if let Some(id) = &self.id {
+            out += &format!("id: {}\n", id);
+        }
+        if let Some(event) = &self.event {
+            out += &format!("event: {}\n", event);
+        }
+        if let Some(retry) = self.retry {
+            out += &format!("retry: {}\n", retry.as_millis());
+        }
+        for line in self.data.replace("\r\n", "\n").split(['\r', '\n']) {
+            out += &format!("data: {}\n", line);
+        }
+        out += "\n";
+        out.into_bytes()
+    }
+}
+
+fn single_line(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| !matches!(c, '\r' | '\n'))
+        .collect()
+}
+
+struct Output {
+    writer: Box<dyn Write + Send>,
+    bytes_written: usize,
+    last_write: Instant,
+}
+
+/// Pushes events to the client of an event stream. Sending fails once the
+/// client has gone away or the server is stopping: the producer should stop then.
+pub struct EventSender {
+    output: Arc<Mutex<Output>>,
+    closed: Arc<AtomicBool>,
+    last_event_id: Option<String>,
+}
+
+impl EventSender {
+    /// The id of the last event the client received before reconnecting, from
+    /// the `Last-Event-ID` request header: the stream resumes after it.
+    pub fn last_event_id(&self) -> Option<&str> {
+        self.last_event_id.as_deref()
+    }
+
+    pub fn send(&self, event: &Event) -> io::Result<()> {
+        self.write(&event.to_bytes())
+    }
+
+    /// Sends a comment line, which is ignored by the client.
+    pub fn comment(&self, text: &str) -> io::Result<()> {
+        self.write(format!(": {}\n\n", single_line(text)).as_bytes())
+    }
+
+    /// Whether the stream has ended: a write failed, i.e. the client has gone
+    /// away, or the server is stopping. A producer that waits for its events
+    /// should check this regularly, the server waits for it on shutdown.
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    fn write(&self, bytes: &[u8]) -> io::Result<()> {
+        write_output(&self.output, &self.closed, bytes)
+    }
+}
+
+fn write_output(output: &Mutex<Output>, closed: &AtomicBool, bytes: &[u8]) -> io::Result<()> {
+    if closed.load(Ordering::SeqCst) {
+        return Err(io::Error::new(ErrorKind::BrokenPipe, "event stream closed"));
+    }
+    let mut output = output.lock().unwrap();
+    let result = output
+        .writer
+        .write_all(bytes)
+        .and_then(|_| output.writer.flush());
+    match result {
+        Ok(_) => {
+            output.bytes_written += bytes.len();
+            output.last_write = Instant::now();
+        }
+        Err(_) => closed.store(true, Ordering::SeqCst),
+    }
+    result
+}
+
+/// A `text/event-stream` response. The producer runs on the worker thread
+/// after the response head is written, and the connection is closed when it
+/// returns, so a stream occupies one worker thread for its whole lifetime.
+///
+/// ```ignore
+/// fn progress(request: &Request) -> Response {
+///     EventStream::new(request)
+///         .heartbeat(Duration::from_secs(15))
+///         .respond(|events| {
+///             let start = events.last_event_id().and_then(|id| id.parse().ok()).unwrap_or(0);
+///             for step in start + 1..=100 {
+///                 let event = Event::new(&format!("{}%", step)).id(&step.to_string());
+///                 if events.send(&event).is_err() {
+///                     break;
+///                 }
+///                 thread::sleep(Duration::from_millis(500));
+///             }
+///         })
+/// }
+/// ```
+///
+/// Only the blocking IoMode streams over HTTP/1.1; the event loop, the async
+/// server and HTTP/2 answer with 501 Not Implemented.
+pub struct EventStream {
+    last_event_id: Option<String>,
+    heartbeat: Option<Duration>,
+}
+
+impl EventStream {
+    /// Starts an event stream for the request, resuming after its `Last-Event-ID`.
+    pub fn new(request: &Request) -> EventStream {
+        EventStream {
+            last_event_id: request
+                .headers
+                .get("last-event-id")
+                .map(|id| String::from(id.trim()))
+                .filter(|id| !id.is_empty()),
+            heartbeat: None,
+        }
+    }
+
+    /// Sends a `: keep-alive` comment whenever nothing was sent for the given
+    /// interval, so that proxies do not close the idle connection, and a
+    /// client that has gone away is noticed. Intervals shorter than
+    /// [MIN_HEARTBEAT] are raised to it.
+    pub fn heartbeat(mut self, interval: Duration) -> EventStream {
+        self.heartbeat = Some(interval.max(MIN_HEARTBEAT));
+        self
+    }
+
+    /// Creates the streaming response: the producer pushes the events through
+    /// the sender, the stream ends when it returns.
+    pub fn respond<F>(self, producer: F) -> Response
+    where
+        F: FnOnce(EventSender) + Send + 'static,
+    {
+        Response::new(HTTPStatusCode::Success(200))
+            .with_header("Content-Type", "text/event-stream")
+            .with_header("Cache-Control", "no-cache")
+            .with_stream(move |writer, stopped| self.run(writer, stopped, producer))
+    }
+
+    fn run<F>(
+        self,
+        writer: Box<dyn Write + Send>,
+        closed: Arc<AtomicBool>,
+        producer: F,
+    ) -> io::Result<usize>
+    where
+        F: FnOnce(EventSender),
+    {
+        let output = Arc::new(Mutex::new(Output {
+            writer,
+            bytes_written: 0,
+            last_write: Instant::now(),
+        }));
+        let sender = EventSender {
+            output: Arc::clone(&output),
+            closed: Arc::clone(&closed),
+            last_event_id: self.last_event_id,
+        };
+
+        // the heartbeat thread stops when the producer is done and drops `done`:
+        let (done, stopped) = mpsc::channel::<()>();
+        let heartbeat = self.heartbeat.map(|interval| {
+            let output = Arc::clone(&output);
+            let closed = Arc::clone(&closed);
+            thread::spawn(move || loop {
+                let idle = output.lock().unwrap().last_write.elapsed();
+                match stopped.recv_timeout(interval.saturating_sub(idle)) {
+                    Err(RecvTimeoutError::Timeout) => (),
+                    _ => break,
+                }
+                if output.lock().unwrap().last_write.elapsed() >= interval
+                    && write_output(&output, &closed, b": keep-alive\n\n").is_err()
+                {
+                    break;
+                }
+            })
+        });
+
+        producer(sender);
+        drop(done);
+        if let Some(heartbeat) = heartbeat {
+            let _ = heartbeat.join();
+        }
+        let bytes_written = output.lock().unwrap().bytes_written;
+        Ok(bytes_written)
+    }
+}
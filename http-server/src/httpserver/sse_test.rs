@@ -0,0 +1,214 @@
+#[cfg(test)]
+mod sse_test {
+    use super::super::lifecycle::Lifecycle;
+    use super::super::middleware::EtagMiddleware;
+    use super::super::{
+        ConnectionLimits, Event, EventStream, HttpVerb, Request, RequestHead, Response, Router,
+        MIN_HEARTBEAT,
+    };
+    use std::io::{self, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::atomic::Ordering;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    /// A writer whose output can be inspected while it is owned by the stream.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedBuffer {
+        fn text(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    struct Disconnected;
+
+    impl Write for Disconnected {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::from(io::ErrorKind::BrokenPipe))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn request(headers: &[&str]) -> Request {
+        let mut lines = vec![String::from("Host: a")];
+        lines.extend(headers.iter().map(|h| String::from(*h)));
+        let head = RequestHead::parse("GET /events HTTP/1.1", &lines).unwrap();
+        Request::from_head(head, None, None)
+    }
+
+    #[test]
+    fn test_event_format() {
+        let event = Event::new("first\nsecond\r\nthird")
+            .id("7")
+            .event("progress")
+            .retry(Duration::from_secs(3));
+        assert_eq!(
+            String::from_utf8(event.to_bytes()).unwrap(),
+            "id: 7\nevent: progress\nretry: 3000\ndata: first\ndata: second\ndata: third\n\n"
+        );
+        // a line break would end the field early:
+        let event = Event::new("").id("1\n2").event("a\r\nb");
+        assert_eq!(
+            String::from_utf8(event.to_bytes()).unwrap(),
+            "id: 12\nevent: ab\ndata: \n\n"
+        );
+    }
+
+    #[test]
+    fn test_stream_over_connection() {
+        let mut router = Router::new();
+        router.route(HttpVerb::GET, "/events", |request| {
+            EventStream::new(request).respond(|events| {
+                let last: u32 = events
+                    .last_event_id()
+                    .and_then(|id| id.parse().ok())
+                    .unwrap_or(0);
+                for id in last + 1..=last + 2 {
+                    let event = Event::new(&format!("event {}", id)).id(&id.to_string());
+                    events.send(&event).unwrap();
+                }
+            })
+        });
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut request =
+                Request::from_tcp_stream(stream, &ConnectionLimits::default()).unwrap();
+            request.handle(&router)
+        });
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .write_all(b"GET /events HTTP/1.1\r\nHost: a\r\nLast-Event-ID: 3\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        let (status, bytes_written) = server.join().unwrap();
+
+        assert_eq!(status.code(), 200);
+        assert_eq!(bytes_written, response.len());
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.contains("Content-Type: text/event-stream\r\n"));
+        assert!(head.contains("Cache-Control: no-cache\r\n"));
+        assert!(!head.contains("Content-Length"));
+        assert_eq!(body, "id: 4\ndata: event 4\n\nid: 5\ndata: event 5\n\n");
+    }
+
+    #[test]
+    fn test_heartbeat() {
+        let mut response = EventStream::new(&request(&[]))
+            .heartbeat(Duration::from_millis(20))
+            .respond(|events| {
+                thread::sleep(Duration::from_millis(110));
+                events.send(&Event::new("done")).unwrap();
+            });
+        let buffer = SharedBuffer::default();
+        let stream = response.take_stream().unwrap();
+        let written = stream(Box::new(buffer.clone()), Arc::default()).unwrap();
+
+        let text = buffer.text();
+        assert_eq!(written, text.len());
+        assert!(text.matches(": keep-alive\n\n").count() >= 3);
+        assert!(text.ends_with(": keep-alive\n\ndata: done\n\n"));
+    }
+
+    #[test]
+    fn test_client_gone() {
+        let closed = Arc::new(Mutex::new(None));
+        let result = Arc::clone(&closed);
+        let mut response =
+            EventStream::new(&request(&["Last-Event-ID:  "])).respond(move |events| {
+                assert_eq!(events.last_event_id(), None);
+                assert!(events.comment("hello").is_err());
+                *result.lock().unwrap() = Some(events.is_closed());
+            });
+        let stream = response.take_stream().unwrap();
+        assert_eq!(stream(Box::new(Disconnected), Arc::default()).unwrap(), 0);
+        assert_eq!(*closed.lock().unwrap(), Some(true));
+    }
+
+    #[test]
+    fn test_heartbeat_clamped() {
+        let mut response = EventStream::new(&request(&[]))
+            .heartbeat(Duration::ZERO)
+            .respond(|_| thread::sleep(MIN_HEARTBEAT * 5));
+        let buffer = SharedBuffer::default();
+        let stream = response.take_stream().unwrap();
+        stream(Box::new(buffer.clone()), Arc::default()).unwrap();
+
+        let heartbeats = buffer.text().matches(": keep-alive\n\n").count();
+        assert!((1..=5).contains(&heartbeats), "{} heartbeats", heartbeats);
+    }
+
+    #[test]
+    fn test_stopped_by_shutdown() {
+        let lifecycle = Lifecycle::new();
+        let mut response = EventStream::new(&request(&[])).respond(|events| {
+            events.send(&Event::new("first")).unwrap();
+            let start = Instant::now();
+            while !events.is_closed() {
+                assert!(start.elapsed() < Duration::from_secs(5));
+                thread::sleep(Duration::from_millis(5));
+            }
+            assert!(events.send(&Event::new("second")).is_err());
+        });
+        let buffer = SharedBuffer::default();
+        let open = lifecycle.open_stream(None);
+        let stopped = open.stopped();
+        let stream = response.take_stream().unwrap();
+        let producer = {
+            let buffer = buffer.clone();
+            thread::spawn(move || stream(Box::new(buffer), stopped).unwrap())
+        };
+
+        // shut down once the first event is sent:
+        let start = Instant::now();
+        while buffer.text().is_empty() {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(5));
+        }
+        lifecycle.shutdown_handle().shutdown(Duration::ZERO);
+        assert_eq!(producer.join().unwrap(), "data: first\n\n".len());
+        assert_eq!(buffer.text(), "data: first\n\n");
+
+        // a stream opened after the shutdown is stopped right away:
+        let open = lifecycle.open_stream(None);
+        assert!(open.stopped().load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_streaming_not_buffered() {
+        let mut router = Router::new();
+        router.middleware("/", EtagMiddleware::new());
+        router.route(HttpVerb::GET, "/events", |request| {
+            EventStream::new(request).respond(|_| ())
+        });
+        let response = router.dispatch(&request(&[]));
+        assert!(response.is_streaming());
+        assert!(!response.headers.contains("etag"));
+
+        // without a connection to stream to, e.g. in the event loop:
+        let bytes = response.to_bytes(&HttpVerb::GET);
+        assert!(String::from_utf8(bytes)
+            .unwrap()
+            .starts_with("HTTP/1.1 501 Not Implemented\r\n"));
+        assert!(!Response::new(super::super::HTTPStatusCode::Success(200)).is_streaming());
+    }
+}
@@ -0,0 +1,264 @@
+use std::io::{self, Write};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::httpserver::{
+    parse_request, ConnectionLimits, HTTPStatusCode, HeaderMap, HttpVerb, Problem, Response,
+    Router, ShutdownHandle, VirtualHosts,
+};
+
+/// Runs requests through the whole request pipeline in-process, without
+/// sockets: the request is serialized and parsed again like one read from a
+/// connection, dispatched to the (virtual host) router with its middlewares,
+/// and the written response is parsed into a [TestResponse]. The response is
+/// written by the same code as on a connection of the server.
+///
+/// The features of the server working on connections rather than requests are
+/// left out: the [IpFilter](crate::httpserver::IpFilter) (also for requests
+/// from trusted proxies), HTTP/2 and the `h2c` upgrade, the timeouts of the
+/// [ConnectionLimits] and the limit of pending connections.
+///
+/// ```ignore
+/// let client = server.test_client();
+/// let response = client.post("/users").header("X-Id", "7").body("...").send();
+/// assert_eq!(response.status.code(), 201);
+/// assert_eq!(response.headers.get("location").unwrap(), "/users/7");
+/// ```
+pub struct TestClient {
+    hosts: VirtualHosts,
+    peer_addr: SocketAddr,
+}
+
+impl TestClient {
+    pub fn new(router: Router) -> TestClient {
+        TestClient::with_hosts(VirtualHosts::new(router))
+    }
+
+    pub fn with_hosts(hosts: VirtualHosts) -> TestClient {
+        TestClient {
+            hosts,
+            peer_addr: SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 40000)),
+        }
+    }
+
+    /// Sets the client address of the requests, 127.0.0.1 by default.
+    pub fn peer_addr(mut self, peer_addr: SocketAddr) -> TestClient {
+        self.peer_addr = peer_addr;
+        self
+    }
+
+    pub fn request(&self, method: HttpVerb, url: &str) -> TestRequest<'_> {
+        TestRequest {
+            client: self,
+            method,
+            url: String::from(url),
+            version: String::from("HTTP/1.1"),
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn get(&self, url: &str) -> TestRequest<'_> {
+        self.request(HttpVerb::GET, url)
+    }
+
+    pub fn head(&self, url: &str) -> TestRequest<'_> {
+        self.request(HttpVerb::HEAD, url)
+    }
+
+    pub fn post(&self, url: &str) -> TestRequest<'_> {
+        self.request(HttpVerb::POST, url)
+    }
+
+    pub fn put(&self, url: &str) -> TestRequest<'_> {
+        self.request(HttpVerb::PUT, url)
+    }
+
+    pub fn delete(&self, url: &str) -> TestRequest<'_> {
+        self.request(HttpVerb::DELETE, url)
+    }
+
+    /// Runs the raw request bytes through the pipeline, and returns the raw
+    /// response bytes, as they would be written to the connection.
+    pub fn send_raw(&self, raw: &[u8]) -> Vec<u8> {
+        // the same framing rules and limits as the server:
+        let max_body_size = ConnectionLimits::default().max_body_size;
+        let request = match parse_request(raw, max_body_size) {
+            Ok(Some(parsed)) => parsed.into_request(Some(self.peer_addr)),
+            // an incomplete request, e.g. a body shorter than its Content-Length
+            Ok(None) => return error_response(HTTPStatusCode::ClientError(400)),
+            Err(status) => return error_response(status),
+        };
+
+        let router = self.hosts.select(request.host().as_deref());
+        let response = request.respond(router);
+        let mut out = SharedBuffer::default();
+        // a streaming response runs until its producer is done:
+        let _ = response.write_and_close(&request.method, &mut out, |out, body| {
+            body(Box::new(out.clone()), Arc::default())
+        });
+        out.bytes()
+    }
+}
+
+fn error_response(status: HTTPStatusCode) -> Vec<u8> {
+    Response::from(Problem::new(status))
+        .with_header("Connection", "close")
+        .to_bytes(&HttpVerb::UNKNOWN)
+}
+
+/// A request of a [TestClient], sent by [TestRequest::send]. A `Host` header
+/// (`localhost`) and the `Content-Length` of the body are added if not set.
+pub struct TestRequest<'a> {
+    client: &'a TestClient,
+    method: HttpVerb,
+    url: String,
+    version: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl TestRequest<'_> {
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((String::from(name), String::from(value)));
+        self
+    }
+
+    pub fn body<T: Into<Vec<u8>>>(mut self, body: T) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Sets the protocol version of the request line, `HTTP/1.1` by default.
+    pub fn version(mut self, version: &str) -> Self {
+        self.version = String::from(version);
+        self
+    }
+
+    /// The request as sent over a connection.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let has = |name: &str| {
+            self.headers
+                .iter()
+                .any(|(n, _)| n.eq_ignore_ascii_case(name))
+        };
+        let mut head = format!("{} {} {}\r\n", self.method, self.url, self.version);
+        if !has("host") {
+            head += "Host: localhost\r\n";
+        }
+        for (name, value) in self.headers.iter() {
+            head += &format!("{}: {}\r\n", name, value);
+        }
+        if !self.body.is_empty() && !has("content-length") {
+            head += &format!("Content-Length: {}\r\n", self.body.len());
+        }
+        head += "\r\n";
+        let mut bytes = head.into_bytes();
+        bytes.extend_from_slice(&self.body);
+        bytes
+    }
+
+    pub fn send(self) -> TestResponse {
+        let raw = self.client.send_raw(&self.to_bytes());
+        TestResponse::parse(&raw).expect("the server wrote an invalid response")
+    }
+}
+
+/// A response parsed from the bytes written by the server.
+#[derive(Debug)]
+pub struct TestResponse {
+    pub status: HTTPStatusCode,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl TestResponse {
+    /// Parses an HTTP/1.1 response with a body up to the end of the bytes.
+    /// Returns None if the status line or the end of the head is missing.
+    pub fn parse(raw: &[u8]) -> Option<TestResponse> {
+        let head_end = raw.windows(4).position(|w| w == b"\r\n\r\n")?;
+        let head = String::from_utf8_lossy(&raw[..head_end]);
+        let mut lines = head.split("\r\n");
+        let code = lines.next()?.split(' ').nth(1)?.parse().ok()?;
+        let header_lines: Vec<String> = lines.map(String::from).collect();
+        Some(TestResponse {
+            status: HTTPStatusCode::from_code(code),
+            headers: HeaderMap::builder(&header_lines),
+            body: raw[head_end + 4..].to_vec(),
+        })
+    }
+
+    /// The body as (lossy) utf-8 text.
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    /// Deserializes the JSON body.
+    #[cfg(feature = "serde")]
+    pub fn json<T: serde::de::DeserializeOwned>(&self) -> serde_json::Result<T> {
+        serde_json::from_slice(&self.body)
+    }
+}
+
+/// Collects the response, also from the thread of a streaming response.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn bytes(&self) -> Vec<u8> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A server listening on an ephemeral port of the loopback interface, started
+/// by [HttpServer::spawn_ephemeral](crate::httpserver::HttpServer::spawn_ephemeral).
+/// It is shut down when dropped, after the requests in progress are done.
+pub struct EphemeralServer {
+    addr: SocketAddr,
+    shutdown: ShutdownHandle,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl EphemeralServer {
+    pub(crate) fn new(
+        addr: SocketAddr,
+        shutdown: ShutdownHandle,
+        thread: JoinHandle<()>,
+    ) -> EphemeralServer {
+        EphemeralServer {
+            addr,
+            shutdown,
+            thread: Some(thread),
+        }
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The absolute `http://` URL of the path on this server.
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+}
+
+impl Drop for EphemeralServer {
+    fn drop(&mut self) {
+        self.shutdown.shutdown(Duration::ZERO);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
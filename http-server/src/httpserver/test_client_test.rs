@@ -0,0 +1,162 @@
+#[cfg(test)]
+mod test_client_test {
+    use super::super::middleware::EtagMiddleware;
+    use super::super::{
+        Event, EventStream, HTTPStatusCode, HttpServer, HttpVerb, Request, Response, Router,
+        TestClient, TestResponse,
+    };
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    fn echo(request: &Request) -> Response {
+        Response::text(
+            HTTPStatusCode::Success(200),
+            &format!(
+                "{} {} {}",
+                request.method,
+                request.headers.get("x-name").unwrap_or_default(),
+                request.body.as_deref().unwrap_or_default()
+            ),
+        )
+    }
+
+    fn server() -> HttpServer {
+        let mut server = HttpServer::new("127.0.0.1:0");
+        server.middleware("/", EtagMiddleware::new());
+        server.route(HttpVerb::GET, "/echo", echo);
+        server.route(HttpVerb::POST, "/echo", echo);
+        let mut admin = Router::new();
+        admin.route(HttpVerb::GET, "/echo", |_| {
+            Response::text(HTTPStatusCode::Success(200), "admin")
+        });
+        server.virtual_host("admin.example.com", admin);
+        server.metrics_route("/metrics");
+        server
+    }
+
+    #[test]
+    fn test_requests() {
+        let client = server().test_client();
+
+        let response = client.get("/echo").header("X-Name", "test").send();
+        assert_eq!(response.status.code(), 200);
+        assert_eq!(response.text(), "GET test ");
+        assert_eq!(response.headers.get("connection").unwrap(), "close");
+        assert!(response.headers.contains("etag"));
+
+        let response = client.post("/echo").body("hello").send();
+        assert_eq!(response.text(), "POST  hello");
+        assert_eq!(response.headers.get("content-length").unwrap(), "11");
+
+        // the length of the body that is not sent:
+        let response = client.head("/echo").send();
+        assert_eq!(response.headers.get("content-length").unwrap(), "6");
+        assert!(response.body.is_empty());
+
+        let response = client.delete("/missing").send();
+        assert_eq!(response.status.code(), 404);
+        assert_eq!(
+            response.headers.get("content-type").unwrap(),
+            "application/problem+json"
+        );
+
+        let response = client
+            .get("/echo")
+            .header("Host", "Admin.Example.com:3000")
+            .send();
+        assert_eq!(response.text(), "admin");
+        // the server's middlewares and routes apply to the virtual hosts, too:
+        assert!(response.headers.contains("etag"));
+        let response = client
+            .get("/metrics")
+            .header("Host", "admin.example.com")
+            .send();
+        assert_eq!(response.status.code(), 200);
+    }
+
+    #[test]
+    fn test_invalid_requests() {
+        let client = TestClient::new(Router::new());
+        // HTTP/1.1 without Host, an invalid method, and a too long request line:
+        let response = TestResponse::parse(&client.send_raw(b"GET / HTTP/1.1\r\n\r\n")).unwrap();
+        assert_eq!(response.status.code(), 400);
+        let response = TestResponse::parse(&client.send_raw(b"G(T / HTTP/1.0\r\n\r\n")).unwrap();
+        assert_eq!(response.status.code(), 400);
+        let long_url = format!("/{}", "a".repeat(9000));
+        let response = client.get(&long_url).send();
+        assert_eq!(response.status.code(), 413);
+
+        // the framing rules of the server: a short body, conflicting lengths, an unsupported coding:
+        let requests: [(&[u8], usize); 3] = [
+            (
+                b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 5\r\n\r\nab",
+                400,
+            ),
+            (
+                b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 1\r\nContent-Length: 2\r\n\r\nab",
+                400,
+            ),
+            (
+                b"POST / HTTP/1.1\r\nHost: a\r\nTransfer-Encoding: gzip, chunked\r\n\r\n0\r\n\r\n",
+                501,
+            ),
+        ];
+        for (raw, status) in requests {
+            let response = TestResponse::parse(&client.send_raw(raw)).unwrap();
+            assert_eq!(response.status.code(), status);
+        }
+
+        // HTTP/1.0 does not need a Host header:
+        let raw = client.send_raw(b"OPTIONS * HTTP/1.0\r\n\r\n");
+        assert!(raw.starts_with(b"HTTP/1.1 "));
+        assert!(TestResponse::parse(b"HTTP/1.1 200 OK\r\n").is_none());
+    }
+
+    #[test]
+    fn test_event_stream() {
+        let mut router = Router::new();
+        router.route(HttpVerb::GET, "/events", |request| {
+            EventStream::new(request).respond(|events| {
+                for id in 1..=2 {
+                    events
+                        .send(&Event::new("tick").id(&id.to_string()))
+                        .unwrap();
+                }
+            })
+        });
+        let response = TestClient::new(router).get("/events").send();
+        assert_eq!(
+            response.headers.get("content-type").unwrap(),
+            "text/event-stream"
+        );
+        assert_eq!(
+            response.text(),
+            "id: 1\ndata: tick\n\nid: 2\ndata: tick\n\n"
+        );
+    }
+
+    #[test]
+    fn test_spawn_ephemeral() {
+        let server = server().spawn_ephemeral().unwrap();
+        assert_ne!(server.addr().port(), 0);
+        assert_eq!(
+            server.url("/echo"),
+            format!("http://127.0.0.1:{}/echo", server.addr().port())
+        );
+
+        let mut stream = TcpStream::connect(server.addr()).unwrap();
+        stream
+            .write_all(b"GET /echo HTTP/1.1\r\nHost: a\r\nX-Name: tcp\r\n\r\n")
+            .unwrap();
+        let mut raw = Vec::new();
+        stream.read_to_end(&mut raw).unwrap();
+        let response = TestResponse::parse(&raw).unwrap();
+        assert_eq!(response.status.code(), 200);
+        assert_eq!(response.text(), "GET tcp ");
+
+        // dropping stops the server:
+        let addr = server.addr();
+        drop(server);
+        assert!(TcpStream::connect(addr).is_err());
+    }
+}
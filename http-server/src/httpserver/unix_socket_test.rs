@@ -0,0 +1,45 @@
+#[cfg(all(test, unix))]
+mod unix_socket_test {
+    use super::super::{HTTPStatusCode, HttpServer, HttpVerb, Response};
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_unix_socket_alongside_tcp() {
+        let path =
+            std::env::temp_dir().join(format!("http-server-test-{}.sock", std::process::id()));
+        let mut server = HttpServer::new("127.0.0.1:0");
+        server.unix_socket(path.to_str().unwrap());
+        server.route(HttpVerb::GET, "/peer", |request| {
+            let peer = request.peer_addr.map(|a| a.to_string()).unwrap_or_default();
+            Response::text(HTTPStatusCode::Success(200), &format!("peer={}", peer))
+        });
+        let shutdown = server.shutdown_handle();
+        let running = thread::spawn(move || server.start().unwrap());
+
+        let start = Instant::now();
+        let mut stream = loop {
+            match UnixStream::connect(&path) {
+                Ok(stream) => break stream,
+                Err(_) if start.elapsed() < Duration::from_secs(5) => {
+                    thread::sleep(Duration::from_millis(10))
+                }
+                Err(e) => panic!("server not listening: {}", e),
+            }
+        };
+        stream
+            .write_all(b"GET /peer HTTP/1.1\r\nHost: a\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        // no IP address on a Unix domain socket:
+        assert!(response.ends_with("\r\n\r\npeer="));
+
+        shutdown.shutdown(Duration::ZERO);
+        running.join().unwrap();
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,70 @@
+use crate::httpserver::Router;
+use crate::utils::wildcard;
+
+/// Selects the router by the requested host name, to serve several sites from
+/// one server. A host pattern is either an exact name (`www.example.com`) or a
+/// wildcard (`*.example.com`, matching all subdomains). Exact names win over
+/// wildcards, the longest wildcard pattern over shorter ones. Requests for
+/// unknown hosts (or without `Host`) are handled by the default router.
+///
+/// Host names are compared case-insensitively, without the port.
+///
+/// The middlewares and routes of the shared router apply to all hosts (and
+/// the default): its middlewares run before the ones of the host, and its
+/// routes win over the host's routes for the same path.
+#[derive(Clone, Default)]
+pub struct VirtualHosts {
+    hosts: Vec<(String, Router)>,
+    default: Router,
+    shared: Router,
+}
+
+impl VirtualHosts {
+    pub fn new(default: Router) -> VirtualHosts {
+        VirtualHosts::with_shared(Router::new(), default)
+    }
+
+    /// Virtual hosts with a router for all hosts, e.g. the server's middlewares
+    /// and its metrics and health routes.
+    pub fn with_shared(shared: Router, default: Router) -> VirtualHosts {
+        let mut hosts = VirtualHosts {
+            hosts: Vec::new(),
+            default: Router::new(),
+            shared,
+        };
+        hosts.default = hosts.with_shared_routes(default);
+        hosts
+    }
+
+    /// Adds the router for a host name or pattern, replacing an existing one.
+    pub fn host(&mut self, pattern: &str, router: Router) {
+        let pattern = pattern.trim().to_ascii_lowercase();
+        let router = self.with_shared_routes(router);
+        self.hosts.retain(|(p, _)| *p != pattern);
+        self.hosts.push((pattern, router));
+    }
+
+    fn with_shared_routes(&self, router: Router) -> Router {
+        let mut combined = self.shared.clone();
+        combined.merge(router);
+        combined
+    }
+
+    /// Returns the router for the (normalized) host name, see
+    /// [Request::host](crate::httpserver::Request::host).
+    pub fn select(&self, host: Option<&str>) -> &Router {
+        let host = match host {
+            Some(h) if !h.is_empty() => h,
+            _ => return &self.default,
+        };
+        if let Some((_, router)) = self.hosts.iter().find(|(p, _)| p == host) {
+            return router;
+        }
+        self.hosts
+            .iter()
+            .filter(|(p, _)| p.contains('*') && wildcard::matches(p, host))
+            .max_by_key(|(p, _)| p.len())
+            .map(|(_, router)| router)
+            .unwrap_or(&self.default)
+    }
+}
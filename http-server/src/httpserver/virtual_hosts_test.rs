@@ -0,0 +1,61 @@
+#[cfg(test)]
+mod virtual_hosts_test {
+    use super::super::{normalize_host, HTTPStatusCode, HttpVerb, Response, Router, VirtualHosts};
+
+    /// a router with a single route, to tell the routers apart
+    fn router(path: &str) -> Router {
+        let mut router = Router::new();
+        router.route(HttpVerb::GET, path, |_| {
+            Response::new(HTTPStatusCode::Success(200))
+        });
+        router
+    }
+
+    fn selected(hosts: &VirtualHosts, host: Option<&str>) -> &'static str {
+        let router = hosts.select(host);
+        ["/default", "/www", "/wildcard", "/deep"]
+            .into_iter()
+            .find(|path| !router.allowed_methods(path).is_empty())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_select() {
+        let mut hosts = VirtualHosts::new(router("/default"));
+        hosts.host("WWW.example.com", router("/www"));
+        hosts.host("*.example.com", router("/wildcard"));
+        hosts.host("*.eu.example.com", router("/deep"));
+
+        assert_eq!(selected(&hosts, Some("www.example.com")), "/www");
+        assert_eq!(selected(&hosts, Some("shop.example.com")), "/wildcard");
+        assert_eq!(selected(&hosts, Some("shop.eu.example.com")), "/deep");
+        assert_eq!(selected(&hosts, Some("example.com")), "/default");
+        assert_eq!(selected(&hosts, Some("other.org")), "/default");
+        assert_eq!(selected(&hosts, Some("")), "/default");
+        assert_eq!(selected(&hosts, None), "/default");
+    }
+
+    #[test]
+    fn test_shared() {
+        let mut shared = router("/shared");
+        shared.trace(true);
+        let mut hosts = VirtualHosts::with_shared(shared, router("/default"));
+        hosts.host("www.example.com", router("/www"));
+
+        for host in [Some("www.example.com"), None] {
+            let router = hosts.select(host);
+            assert!(!router.allowed_methods("/shared").is_empty());
+            assert!(router.allowed_methods("*").contains(&HttpVerb::TRACE));
+        }
+        assert_eq!(selected(&hosts, Some("www.example.com")), "/www");
+        assert_eq!(selected(&hosts, None), "/default");
+    }
+
+    #[test]
+    fn test_normalize_host() {
+        assert_eq!(normalize_host("WWW.Example.com:8080"), "www.example.com");
+        assert_eq!(normalize_host("example.com."), "example.com");
+        assert_eq!(normalize_host("[::1]:3000"), "::1");
+        assert_eq!(normalize_host("127.0.0.1"), "127.0.0.1");
+    }
+}
@@ -1,4 +1,3 @@
-use std::{io::{Read, Result, BufReader}, net::TcpStream};
-
+pub mod httpclient;
+pub mod httpserver;
 pub mod utils;
-
@@ -1,7 +1,75 @@
-mod httpserver;
-use httpserver::HttpServer;
+use std::thread;
+use std::time::Duration;
+
+use http_server::httpserver::middleware::EtagMiddleware;
+use http_server::httpserver::{
+    Event, EventStream, HTTPStatusCode, HttpServer, HttpVerb, Request, Response,
+};
+use http_server::utils::systemd;
 
 fn main() {
-    let server = HttpServer::new("127.0.0.1:3000");
+    let args: Vec<String> = std::env::args().collect();
+    // `--systemd`: take the sockets of a socket unit (before the thread pool
+    // starts, see systemd::listen_fds), and notify systemd when ready
+    let socket_activated = args.iter().any(|arg| arg == "--systemd");
+    let inherited = match socket_activated {
+        true => systemd::listen_fds().unwrap(),
+        false => Vec::new(),
+    };
+
+    let mut server = HttpServer::new("127.0.0.1:3000");
+    // `--event-loop`: multiplex the connections in a reactor thread instead of
+    // handling each connection by a worker thread
+    #[cfg(feature = "event-loop")]
+    if std::env::args().any(|arg| arg == "--event-loop") {
+        server.io_mode(http_server::httpserver::IoMode::EventLoop);
+    }
+    // `--unix-socket <path>`: also listen on a Unix domain socket, e.g. for a reverse proxy
+    if let Some(idx) = args.iter().position(|arg| arg == "--unix-socket") {
+        server.unix_socket(args.get(idx + 1).expect("--unix-socket needs a path"));
+    }
+    if socket_activated {
+        server.socket_activation(inherited);
+        server.systemd_notify(true);
+    }
+    server.enable_h2c(true);
+    server.metrics_route("/metrics");
+    server.health_routes("/healthz", "/readyz");
+    server.middleware("/", EtagMiddleware::new());
+    server.route(HttpVerb::GET, "/ticks", ticks);
+    server.route(HttpVerb::GET, "/*", echo);
+    server.route(HttpVerb::POST, "/*", echo);
     server.start().unwrap();
 }
+
+/// Demo handler: answers with the request method and the request body.
+fn echo(request: &Request) -> Response {
+    let mut response = format!("{} request read.\n", request.method);
+
+    if let Some(body) = &request.body {
+        response += format!("Body:\n{}", body).as_str();
+    }
+    Response::text(HTTPStatusCode::Success(200), &response)
+}
+
+/// Demo event stream: sends a numbered tick every second, resuming after the
+/// last tick a reconnecting client has seen.
+fn ticks(request: &Request) -> Response {
+    EventStream::new(request)
+        .heartbeat(Duration::from_secs(15))
+        .respond(|events| {
+            let last: u64 = events
+                .last_event_id()
+                .and_then(|id| id.parse().ok())
+                .unwrap_or(0);
+            for tick in last.saturating_add(1)..=last.saturating_add(60) {
+                let event = Event::new(&format!("tick {}", tick))
+                    .id(&tick.to_string())
+                    .event("tick");
+                if events.send(&event).is_err() {
+                    break;
+                }
+                thread::sleep(Duration::from_secs(1));
+            }
+        })
+}
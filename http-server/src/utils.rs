@@ -1,5 +1,41 @@
 pub mod threadpool;
 pub mod logging;
+pub mod base64;
+pub mod sha1;
+pub mod wildcard;
+pub mod deadline_reader;
+pub mod prometheus;
+pub mod json;
+pub mod http_date;
+pub mod cidr;
+pub mod signal;
+pub mod net;
+pub mod systemd;
 mod limited_buffered_reader;
 
-pub use limited_buffered_reader::BufReaderExt;
\ No newline at end of file
+pub use limited_buffered_reader::BufReaderExt;
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod deadline_reader_test;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod limited_buffered_reader_test;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod prometheus_test;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod http_date_test;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod cidr_test;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod threadpool_test;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod net_test;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod systemd_test;
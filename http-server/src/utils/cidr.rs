@@ -0,0 +1,95 @@
+//! CIDR network ranges (`10.0.0.0/8`, `fd00::/8`) for IPv4 and IPv6.
+
+use std::fmt::{Display, Formatter};
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// An IP network: an address and a prefix length. IPv4-mapped IPv6 addresses
+/// (`::ffff:10.0.0.1`) are treated as the IPv4 address they map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    /// Creates the network containing `addr`, with the host bits cleared.
+    /// Returns None if the prefix length is too long for the address family.
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Option<Cidr> {
+        let addr = canonical(addr);
+        let network = match addr {
+            IpAddr::V4(a) if prefix_len <= 32 => {
+                IpAddr::from((u32::from(a) & mask32(prefix_len)).to_be_bytes())
+            }
+            IpAddr::V6(a) if prefix_len <= 128 => {
+                IpAddr::from((u128::from(a) & mask128(prefix_len)).to_be_bytes())
+            }
+            _ => return None,
+        };
+        Some(Cidr {
+            network,
+            prefix_len,
+        })
+    }
+
+    /// Whether the address lies within this network.
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        match (self.network, canonical(addr)) {
+            (IpAddr::V4(net), IpAddr::V4(a)) => {
+                u32::from(a) & mask32(self.prefix_len) == u32::from(net)
+            }
+            (IpAddr::V6(net), IpAddr::V6(a)) => {
+                u128::from(a) & mask128(self.prefix_len) == u128::from(net)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    /// Parses `address/prefix-length`; a plain address is a single host network.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (addr, prefix_len) = match s.split_once('/') {
+            Some((addr, len)) => (addr, Some(len)),
+            None => (s, None),
+        };
+        let addr: IpAddr = addr
+            .parse()
+            .map_err(|_| format!("invalid IP address: {}", addr))?;
+        let prefix_len = match prefix_len {
+            Some(len) => len
+                .parse::<u8>()
+                .map_err(|_| format!("invalid prefix length: {}", len))?,
+            None if canonical(addr).is_ipv4() => 32,
+            None => 128,
+        };
+        Cidr::new(addr, prefix_len).ok_or(format!("prefix length too long: {}", s))
+    }
+}
+
+impl Display for Cidr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix_len)
+    }
+}
+
+fn canonical(addr: IpAddr) -> IpAddr {
+    match addr {
+        IpAddr::V6(a) => match a.to_ipv4_mapped() {
+            Some(v4) => IpAddr::V4(v4),
+            None => addr,
+        },
+        v4 => v4,
+    }
+}
+
+fn mask32(prefix_len: u8) -> u32 {
+    u32::MAX.checked_shl(32 - prefix_len as u32).unwrap_or(0)
+}
+
+fn mask128(prefix_len: u8) -> u128 {
+    u128::MAX.checked_shl(128 - prefix_len as u32).unwrap_or(0)
+}
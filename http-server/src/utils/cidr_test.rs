@@ -0,0 +1,43 @@
+#[cfg(test)]
+mod cidr_test {
+    use super::super::cidr::Cidr;
+    use std::net::IpAddr;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_ipv4() {
+        let net: Cidr = "10.1.2.3/16".parse().unwrap();
+        assert_eq!(net.to_string(), "10.1.0.0/16");
+        assert!(net.contains(ip("10.1.255.1")));
+        assert!(!net.contains(ip("10.2.0.1")));
+        assert!(net.contains(ip("::ffff:10.1.0.7")));
+
+        let host: Cidr = "192.168.0.1".parse().unwrap();
+        assert!(host.contains(ip("192.168.0.1")));
+        assert!(!host.contains(ip("192.168.0.2")));
+
+        let all: Cidr = "0.0.0.0/0".parse().unwrap();
+        assert!(all.contains(ip("8.8.8.8")));
+        assert!(!all.contains(ip("::1")));
+    }
+
+    #[test]
+    fn test_ipv6() {
+        let net: Cidr = "fd00:1234::/32".parse().unwrap();
+        assert!(net.contains(ip("fd00:1234::1")));
+        assert!(!net.contains(ip("fd00:1235::1")));
+        assert!(!net.contains(ip("10.0.0.1")));
+        assert!("::1".parse::<Cidr>().unwrap().contains(ip("::1")));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+        assert!("::/129".parse::<Cidr>().is_err());
+        assert!("10.0.0/8".parse::<Cidr>().is_err());
+        assert!("10.0.0.0/x".parse::<Cidr>().is_err());
+    }
+}
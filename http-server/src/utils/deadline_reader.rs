@@ -0,0 +1,102 @@
+use std::{
+    io::{Error, ErrorKind, Read, Result},
+    time::{Duration, Instant},
+};
+
+use crate::utils::net::Stream;
+
+/// Grace period before the minimum transfer rate is enforced, so that a
+/// slow start (e.g. TCP slow start, a short network hiccup) is tolerated.
+const RATE_GRACE_PERIOD: Duration = Duration::from_secs(2);
+
+/// A reader on a (TCP or Unix domain socket) stream that enforces an absolute deadline and an optional
+/// minimum transfer rate: the socket read timeout is adjusted before each read,
+/// so a client trickling in single bytes cannot hold the connection forever
+/// (slowloris). A violation is reported as `ErrorKind::TimedOut`.
+pub struct DeadlineReader {
+    stream: Stream,
+    deadline: Option<Instant>,
+    min_rate: Option<u64>,
+    idle_timeout: Option<Duration>,
+    rate_start: Instant,
+    rate_bytes: u64,
+    total_bytes: u64,
+}
+
+impl DeadlineReader {
+    pub fn new(stream: impl Into<Stream>) -> DeadlineReader {
+        DeadlineReader {
+            stream: stream.into(),
+            deadline: None,
+            min_rate: None,
+            idle_timeout: None,
+            rate_start: Instant::now(),
+            rate_bytes: 0,
+            total_bytes: 0,
+        }
+    }
+
+    /// Total number of bytes read from the stream.
+    pub fn bytes_read(&self) -> u64 {
+        self.total_bytes
+    }
+
+    /// Sets the point in time when reading must be done (None: no deadline).
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    /// Sets the minimum transfer rate in bytes per second (None: no minimum),
+    /// measured from now on.
+    pub fn set_min_rate(&mut self, bytes_per_second: Option<u64>) {
+        self.min_rate = bytes_per_second.filter(|rate| *rate > 0);
+        self.rate_start = Instant::now();
+        self.rate_bytes = 0;
+    }
+
+    /// Sets the maximum time to wait for data in each read (None: no limit),
+    /// e.g. to close idle connections.
+    pub fn set_idle_timeout(&mut self, timeout: Option<Duration>) {
+        self.idle_timeout = timeout;
+    }
+
+    /// The time to wait for data: until either the deadline passes or the transfer rate
+    /// drops below the minimum, if no more data arrives, or the idle timeout passes.
+    fn time_left(&self) -> Option<Duration> {
+        let now = Instant::now();
+        let mut until = self.deadline;
+        if let Some(rate) = self.min_rate {
+            let covered = Duration::from_secs_f64(self.rate_bytes as f64 / rate as f64);
+            let rate_deadline = self.rate_start + RATE_GRACE_PERIOD + covered;
+            until = Some(until.map_or(rate_deadline, |d| d.min(rate_deadline)));
+        }
+        let left = until.map(|until| until.saturating_duration_since(now));
+        match (left, self.idle_timeout) {
+            (Some(left), Some(idle)) => Some(left.min(idle)),
+            (left, idle) => left.or(idle),
+        }
+    }
+}
+
+impl Read for DeadlineReader {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let timeout = self.time_left();
+        if timeout == Some(Duration::ZERO) {
+            return Err(Error::new(ErrorKind::TimedOut, "read deadline exceeded"));
+        }
+        self.stream.set_read_timeout(timeout)?;
+
+        match self.stream.read(buf) {
+            Ok(n) => {
+                self.rate_bytes += n as u64;
+                self.total_bytes += n as u64;
+                Ok(n)
+            }
+            // depending on the platform, a read timeout is reported as WouldBlock or TimedOut:
+            Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {
+                Err(Error::new(ErrorKind::TimedOut, "read deadline exceeded"))
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
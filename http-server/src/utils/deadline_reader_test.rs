@@ -0,0 +1,103 @@
+#[cfg(test)]
+mod deadline_reader_test {
+    use super::super::deadline_reader::*;
+    use std::io::{ErrorKind, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    /// Returns a connected (client, server) socket pair.
+    fn socket_pair() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        (client, server)
+    }
+
+    #[test]
+    fn test_reads_within_deadline() {
+        let (mut client, server) = socket_pair();
+        let mut reader = DeadlineReader::new(server);
+        reader.set_deadline(Some(Instant::now() + Duration::from_secs(5)));
+
+        client.write_all(b"hello").unwrap();
+        let mut buf = [0u8; 5];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hello");
+    }
+
+    #[test]
+    fn test_deadline_is_absolute() {
+        let (mut client, server) = socket_pair();
+        let mut reader = DeadlineReader::new(server);
+        let start = Instant::now();
+        reader.set_deadline(Some(start + Duration::from_millis(300)));
+
+        // a slow client, sending a byte every 100ms, must not extend the deadline:
+        let writer = thread::spawn(move || {
+            for _ in 0..10 {
+                if client.write_all(b"x").is_err() {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+        });
+        let mut buf = [0u8; 100];
+        let err = reader.read_exact(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_millis(900));
+        drop(reader);
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn test_min_rate() {
+        let (mut client, server) = socket_pair();
+        let mut reader = DeadlineReader::new(server);
+        reader.set_deadline(Some(Instant::now() + Duration::from_secs(30)));
+        reader.set_min_rate(Some(1000));
+        let start = Instant::now();
+
+        // a byte every 200ms is far below 1000 bytes/s: after the grace
+        // period, the read fails long before the deadline:
+        let writer = thread::spawn(move || {
+            for _ in 0..30 {
+                if client.write_all(b"x").is_err() {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(200));
+            }
+        });
+        let mut buf = [0u8; 100];
+        let err = reader.read_exact(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_secs(5));
+        drop(reader);
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn test_idle_timeout_is_per_read() {
+        let (mut client, server) = socket_pair();
+        let mut reader = DeadlineReader::new(server);
+        reader.set_idle_timeout(Some(Duration::from_millis(300)));
+
+        // data every 100ms keeps the reader going, longer than the idle timeout:
+        let writer = thread::spawn(move || {
+            for _ in 0..5 {
+                client.write_all(b"x").unwrap();
+                thread::sleep(Duration::from_millis(100));
+            }
+            client
+        });
+        let mut buf = [0u8; 5];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"xxxxx");
+
+        let start = Instant::now();
+        let err = reader.read(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_millis(900));
+        drop(writer.join().unwrap());
+    }
+}
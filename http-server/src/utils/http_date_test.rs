@@ -0,0 +1,51 @@
+#[cfg(test)]
+mod http_date_test {
+    use super::super::http_date::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format(UNIX_EPOCH), "Thu, 01 Jan 1970 00:00:00 GMT");
+        assert_eq!(
+            format(UNIX_EPOCH + Duration::from_secs(784111777)),
+            "Sun, 06 Nov 1994 08:49:37 GMT"
+        );
+        assert_eq!(
+            format(UNIX_EPOCH + Duration::from_secs(951782400)),
+            "Tue, 29 Feb 2000 00:00:00 GMT"
+        );
+    }
+
+    #[test]
+    fn test_parse_all_formats() {
+        let expected = Some(UNIX_EPOCH + Duration::from_secs(784111777));
+        assert_eq!(parse("Sun, 06 Nov 1994 08:49:37 GMT"), expected);
+        assert_eq!(parse("Sunday, 06-Nov-94 08:49:37 GMT"), expected);
+        assert_eq!(parse("Sun Nov  6 08:49:37 1994"), expected);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("yesterday"), None);
+        assert_eq!(parse("Sun, 06 Foo 1994 08:49:37 GMT"), None);
+        assert_eq!(parse("Sun, 06 Nov 1994 25:49:37 GMT"), None);
+        assert_eq!(parse("Sun, 06 Nov 1994 08:49:37 CET"), None);
+    }
+
+    /// Large years overflowed the computation of the seconds (and panicked).
+    #[test]
+    fn test_regression_year_out_of_range() {
+        assert_eq!(parse("Sun, 06 Nov 99999999999999999 08:49:37 GMT"), None);
+        assert_eq!(parse("Sun Nov  6 08:49:37 -9223372036854775808"), None);
+        assert_eq!(parse("Sun, 06 Nov 10000 08:49:37 GMT"), None);
+        assert_eq!(parse("Sun, 06 Nov 1969 08:49:37 GMT"), None);
+        assert!(parse("Fri, 31 Dec 9999 23:59:59 GMT").is_some());
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        assert_eq!(parse(&format(time)), Some(time));
+    }
+}
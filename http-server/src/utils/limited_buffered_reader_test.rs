@@ -0,0 +1,50 @@
+#[cfg(test)]
+mod limited_buffered_reader_test {
+    use super::super::BufReaderExt;
+    use std::io::{BufRead, BufReader, Cursor, ErrorKind, Read};
+
+    #[test]
+    fn test_read_until_delimiter() {
+        let mut reader = Cursor::new(b"GET / HTTP/1.1\r\nHost: foo\r\n".to_vec());
+        assert_eq!(reader.read_max_until(b'\n', 100).unwrap(), b"GET / HTTP/1.1\r\n");
+        assert_eq!(reader.read_max_until(b'\n', 100).unwrap(), b"Host: foo\r\n");
+        assert_eq!(reader.read_max_until(b'\n', 100).unwrap(), b"");
+    }
+
+    #[test]
+    fn test_read_across_buffer_chunks() {
+        // a tiny buffer forces several fill_buf() calls per line:
+        let mut reader = BufReader::with_capacity(3, Cursor::new(b"abcdefgh\nrest".to_vec()));
+        assert_eq!(reader.read_max_until(b'\n', 100).unwrap(), b"abcdefgh\n");
+
+        // the bytes after the delimiter are still available:
+        let mut rest = String::new();
+        reader.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, "rest");
+    }
+
+    #[test]
+    fn test_max_bytes() {
+        let mut reader = Cursor::new(b"abcd\n".to_vec());
+        assert_eq!(reader.read_max_until(b'\n', 5).unwrap(), b"abcd\n");
+
+        let mut reader = BufReader::with_capacity(2, Cursor::new(b"abcd\n".to_vec()));
+        let err = reader.read_max_until(b'\n', 4).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        // no more than max_bytes are consumed:
+        assert_eq!(reader.fill_buf().unwrap(), b"\n");
+    }
+
+    #[test]
+    fn test_eof_before_delimiter() {
+        let mut reader = Cursor::new(b"GET / HT".to_vec());
+        let err = reader.read_max_until(b'\n', 100).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_eof_without_input() {
+        let mut reader = Cursor::new(Vec::new());
+        assert_eq!(reader.read_max_until(b'\n', 100).unwrap(), b"");
+    }
+}
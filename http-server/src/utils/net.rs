@@ -0,0 +1,281 @@
+use std::fmt::{Display, Formatter};
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::Path;
+
+/// A connection accepted by the server: TCP, or a Unix domain socket, e.g.
+/// from a reverse proxy on the same host.
+#[derive(Debug)]
+pub enum Stream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Stream {
+    pub fn try_clone(&self) -> io::Result<Stream> {
+        match self {
+            Stream::Tcp(s) => s.try_clone().map(Stream::Tcp),
+            #[cfg(unix)]
+            Stream::Unix(s) => s.try_clone().map(Stream::Unix),
+        }
+    }
+
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Stream::Tcp(s) => s.set_read_timeout(timeout),
+            #[cfg(unix)]
+            Stream::Unix(s) => s.set_read_timeout(timeout),
+        }
+    }
+
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Stream::Tcp(s) => s.set_write_timeout(timeout),
+            #[cfg(unix)]
+            Stream::Unix(s) => s.set_write_timeout(timeout),
+        }
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        match self {
+            Stream::Tcp(s) => s.set_nonblocking(nonblocking),
+            #[cfg(unix)]
+            Stream::Unix(s) => s.set_nonblocking(nonblocking),
+        }
+    }
+
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        match self {
+            Stream::Tcp(s) => s.shutdown(how),
+            #[cfg(unix)]
+            Stream::Unix(s) => s.shutdown(how),
+        }
+    }
+
+    /// The address of the client; None for Unix domain sockets, which have
+    /// no IP address.
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        match self {
+            Stream::Tcp(s) => s.peer_addr().ok(),
+            #[cfg(unix)]
+            Stream::Unix(_) => None,
+        }
+    }
+
+    /// Describes the client for the logs: its IP address and port, or for a
+    /// Unix domain socket (whose clients are usually unnamed) the socket path.
+    pub fn peer_name(&self) -> String {
+        match self {
+            Stream::Tcp(s) => match s.peer_addr() {
+                Ok(addr) => addr.to_string(),
+                Err(_) => String::from("unknown"),
+            },
+            #[cfg(unix)]
+            Stream::Unix(s) => {
+                let path = s.peer_addr().ok().and_then(|a| a.as_pathname().map(PathBuf::from));
+                let path = path.or_else(|| {
+                    s.local_addr()
+                        .ok()
+                        .and_then(|a| a.as_pathname().map(PathBuf::from))
+                });
+                match path {
+                    Some(path) => ListenAddr::Unix(path).to_string(),
+                    None => String::from("unix:unnamed"),
+                }
+            }
+        }
+    }
+}
+
+impl From<TcpStream> for Stream {
+    fn from(stream: TcpStream) -> Stream {
+        Stream::Tcp(stream)
+    }
+}
+
+#[cfg(unix)]
+impl From<UnixStream> for Stream {
+    fn from(stream: UnixStream) -> Stream {
+        Stream::Unix(stream)
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(s) => s.read(buf),
+            #[cfg(unix)]
+            Stream::Unix(s) => s.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(s) => s.write(buf),
+            #[cfg(unix)]
+            Stream::Unix(s) => s.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Tcp(s) => s.flush(),
+            #[cfg(unix)]
+            Stream::Unix(s) => s.flush(),
+        }
+    }
+}
+
+/// The address a [Listener] is bound to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListenAddr {
+    Tcp(SocketAddr),
+    /// the path of the socket file
+    Unix(PathBuf),
+}
+
+impl Display for ListenAddr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ListenAddr::Tcp(addr) => write!(f, "{}", addr),
+            ListenAddr::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// A bound server socket, accepting [Stream]s.
+#[derive(Debug)]
+pub enum Listener {
+    Tcp(TcpListener),
+    /// `owned`: the socket file was created by the server and is removed when it stops
+    #[cfg(unix)]
+    Unix {
+        listener: UnixListener,
+        path: PathBuf,
+        owned: bool,
+    },
+}
+
+impl Listener {
+    pub fn accept(&self) -> io::Result<Stream> {
+        match self {
+            Listener::Tcp(l) => l.accept().map(|(s, _)| Stream::Tcp(s)),
+            #[cfg(unix)]
+            Listener::Unix { listener: l, .. } => l.accept().map(|(s, _)| Stream::Unix(s)),
+        }
+    }
+
+    pub fn local_addr(&self) -> io::Result<ListenAddr> {
+        match self {
+            Listener::Tcp(l) => l.local_addr().map(ListenAddr::Tcp),
+            #[cfg(unix)]
+            Listener::Unix { path, .. } => Ok(ListenAddr::Unix(path.clone())),
+        }
+    }
+
+    /// Binds a Unix domain socket at the given path, with the given file
+    /// permissions (e.g. `0o660`, so that only the owner and the group, e.g.
+    /// of a reverse proxy, can connect). A socket file left over by a server
+    /// that was not shut down cleanly is removed first.
+    #[cfg(unix)]
+    pub fn bind_unix(path: &Path, mode: u32) -> io::Result<Listener> {
+        use std::fs;
+        use std::os::unix::fs::PermissionsExt;
+
+        remove_stale_socket(path)?;
+        let listener = UnixListener::bind(path)?;
+        fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+        Ok(Listener::Unix {
+            listener,
+            path: PathBuf::from(path),
+            owned: true,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn bind_unix(_path: &std::path::Path, _mode: u32) -> io::Result<Listener> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Unix domain sockets are not supported on this platform",
+        ))
+    }
+
+    /// Wraps a listening socket inherited from the parent process (e.g. by
+    /// systemd socket activation): a TCP or a Unix domain socket, bound to a
+    /// path. The socket file of the latter is left alone when the server stops.
+    ///
+    /// # Safety
+    ///
+    /// The file descriptor must be an open, listening stream socket, owned by
+    /// nothing else in the process.
+    #[cfg(unix)]
+    pub unsafe fn from_raw_fd(fd: std::os::unix::io::RawFd) -> io::Result<Listener> {
+        use std::os::unix::io::{FromRawFd, IntoRawFd};
+
+        let listener = TcpListener::from_raw_fd(fd);
+        if listener.local_addr().is_ok() {
+            return Ok(Listener::Tcp(listener));
+        }
+        // no IP address, so probably a Unix domain socket:
+        let listener = UnixListener::from_raw_fd(listener.into_raw_fd());
+        let path = listener.local_addr()?.as_pathname().map(PathBuf::from);
+        match path {
+            Some(path) => Ok(Listener::Unix {
+                listener,
+                path,
+                owned: false,
+            }),
+            None => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("file descriptor {} is no TCP or named Unix domain socket", fd),
+            )),
+        }
+    }
+
+    /// Removes the socket file of a Unix domain socket, when the server stops.
+    pub fn cleanup(&self) {
+        #[cfg(unix)]
+        if let Listener::Unix {
+            path, owned: true, ..
+        } = self
+        {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Removes a socket file at the path if no server is listening on it anymore.
+/// Other files, and sockets of a running server, are left alone: binding fails then.
+#[cfg(unix)]
+fn remove_stale_socket(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    if !metadata.file_type().is_socket() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} exists and is not a socket", path.display()),
+        ));
+    }
+    match UnixStream::connect(path) {
+        Ok(_) => Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            format!("another server is listening on {}", path.display()),
+        )),
+        Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => std::fs::remove_file(path),
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,74 @@
+#[cfg(all(test, unix))]
+mod net_test {
+    use super::super::net::{ListenAddr, Listener};
+    use std::fs;
+    use std::io::{ErrorKind, Read, Write};
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::PathBuf;
+
+    fn socket_path(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("http-server-{}-{}.sock", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_bind_unix() {
+        let path = socket_path("bind");
+        let listener = Listener::bind_unix(&path, 0o600).unwrap();
+        assert_eq!(
+            listener.local_addr().unwrap(),
+            ListenAddr::Unix(path.clone())
+        );
+        assert_eq!(
+            listener.local_addr().unwrap().to_string(),
+            format!("unix:{}", path.display())
+        );
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        let mut client = UnixStream::connect(&path).unwrap();
+        let mut stream = listener.accept().unwrap();
+        assert_eq!(stream.peer_addr(), None);
+        assert_eq!(stream.peer_name(), format!("unix:{}", path.display()));
+        client.write_all(b"ping").unwrap();
+        let mut buf = [0; 4];
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ping");
+
+        listener.cleanup();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_stale_socket_is_removed() {
+        let path = socket_path("stale");
+        // a socket file without a server, as left by a crashed server:
+        drop(UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+        let listener = Listener::bind_unix(&path, 0o660).unwrap();
+        assert!(UnixStream::connect(&path).is_ok());
+        listener.cleanup();
+    }
+
+    #[test]
+    fn test_running_server_is_not_replaced() {
+        let path = socket_path("in-use");
+        let listener = Listener::bind_unix(&path, 0o660).unwrap();
+        let err = Listener::bind_unix(&path, 0o660).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::AddrInUse);
+        listener.cleanup();
+    }
+
+    #[test]
+    fn test_other_file_is_not_removed() {
+        let path = socket_path("file");
+        fs::write(&path, "data").unwrap();
+        let err = Listener::bind_unix(&path, 0o660).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "data");
+        fs::remove_file(&path).unwrap();
+    }
+}
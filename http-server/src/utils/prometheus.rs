@@ -0,0 +1,152 @@
+//! A minimal metrics registry, rendering the Prometheus text exposition format
+//! (see https://prometheus.io/docs/instrumenting/exposition_formats/).
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+
+/// A family of counters, one per combination of label values.
+pub struct CounterVec {
+    name: &'static str,
+    help: &'static str,
+    label_names: Vec<&'static str>,
+    values: Mutex<BTreeMap<Vec<String>, u64>>,
+}
+
+impl CounterVec {
+    pub fn new(name: &'static str, help: &'static str, label_names: &[&'static str]) -> CounterVec {
+        CounterVec {
+            name,
+            help,
+            label_names: label_names.to_vec(),
+            values: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn inc_by(&self, label_values: &[&str], value: u64) {
+        let key = label_values.iter().map(|v| String::from(*v)).collect();
+        let mut values = self.values.lock().unwrap();
+        *values.entry(key).or_insert(0) += value;
+    }
+
+    pub fn get(&self, label_values: &[&str]) -> u64 {
+        let key: Vec<String> = label_values.iter().map(|v| String::from(*v)).collect();
+        *self.values.lock().unwrap().get(&key).unwrap_or(&0)
+    }
+
+    pub fn render(&self, out: &mut String) {
+        write_header(out, self.name, self.help, "counter");
+        for (label_values, value) in self.values.lock().unwrap().iter() {
+            let labels = format_labels(&self.label_names, label_values, None);
+            let _ = writeln!(out, "{}{} {}", self.name, labels, value);
+        }
+    }
+}
+
+#[derive(Clone)]
+struct HistogramData {
+    bucket_counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+/// A family of histograms with fixed bucket bounds, one per combination of label values.
+pub struct HistogramVec {
+    name: &'static str,
+    help: &'static str,
+    label_names: Vec<&'static str>,
+    buckets: Vec<f64>,
+    values: Mutex<BTreeMap<Vec<String>, HistogramData>>,
+}
+
+impl HistogramVec {
+    pub fn new(
+        name: &'static str,
+        help: &'static str,
+        label_names: &[&'static str],
+        buckets: &[f64],
+    ) -> HistogramVec {
+        HistogramVec {
+            name,
+            help,
+            label_names: label_names.to_vec(),
+            buckets: buckets.to_vec(),
+            values: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn observe(&self, label_values: &[&str], value: f64) {
+        let key = label_values.iter().map(|v| String::from(*v)).collect();
+        let mut values = self.values.lock().unwrap();
+        let data = values.entry(key).or_insert_with(|| HistogramData {
+            bucket_counts: vec![0; self.buckets.len()],
+            sum: 0.0,
+            count: 0,
+        });
+        for (idx, bound) in self.buckets.iter().enumerate() {
+            if value <= *bound {
+                data.bucket_counts[idx] += 1;
+            }
+        }
+        data.sum += value;
+        data.count += 1;
+    }
+
+    pub fn render(&self, out: &mut String) {
+        write_header(out, self.name, self.help, "histogram");
+        for (label_values, data) in self.values.lock().unwrap().iter() {
+            for (bound, count) in self.buckets.iter().zip(data.bucket_counts.iter()) {
+                let le = format_value(*bound);
+                let labels = format_labels(&self.label_names, label_values, Some(&le));
+                let _ = writeln!(out, "{}_bucket{} {}", self.name, labels, count);
+            }
+            let labels = format_labels(&self.label_names, label_values, Some("+Inf"));
+            let _ = writeln!(out, "{}_bucket{} {}", self.name, labels, data.count);
+
+            let labels = format_labels(&self.label_names, label_values, None);
+            let _ = writeln!(out, "{}_sum{} {}", self.name, labels, format_value(data.sum));
+            let _ = writeln!(out, "{}_count{} {}", self.name, labels, data.count);
+        }
+    }
+}
+
+/// Renders a single, unlabeled metric whose value is taken at render time.
+pub fn render_single(out: &mut String, name: &str, help: &str, metric_type: &str, value: f64) {
+    write_header(out, name, help, metric_type);
+    let _ = writeln!(out, "{} {}", name, format_value(value));
+}
+
+fn write_header(out: &mut String, name: &str, help: &str, metric_type: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, metric_type);
+}
+
+fn format_labels(names: &[&str], values: &[String], le: Option<&str>) -> String {
+    let mut pairs: Vec<String> = names
+        .iter()
+        .zip(values.iter())
+        .map(|(name, value)| format!("{}=\"{}\"", name, escape_label_value(value)))
+        .collect();
+    if let Some(le) = le {
+        pairs.push(format!("le=\"{}\"", le));
+    }
+    match pairs.is_empty() {
+        true => String::new(),
+        false => format!("{{{}}}", pairs.join(",")),
+    }
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn format_value(value: f64) -> String {
+    match value.is_infinite() {
+        true if value > 0.0 => String::from("+Inf"),
+        true => String::from("-Inf"),
+        false => format!("{}", value),
+    }
+}
@@ -0,0 +1,50 @@
+#[cfg(test)]
+mod prometheus_test {
+    use super::super::prometheus::*;
+
+    #[test]
+    fn test_counter_rendering() {
+        let counter = CounterVec::new("requests_total", "Number of requests.", &["method", "path"]);
+        counter.inc_by(&["GET", "/"], 1);
+        counter.inc_by(&["GET", "/"], 2);
+        counter.inc_by(&["POST", "/say \"hi\""], 1);
+
+        let mut out = String::new();
+        counter.render(&mut out);
+        assert_eq!(
+            out,
+            "# HELP requests_total Number of requests.\n\
+             # TYPE requests_total counter\n\
+             requests_total{method=\"GET\",path=\"/\"} 3\n\
+             requests_total{method=\"POST\",path=\"/say \\\"hi\\\"\"} 1\n"
+        );
+    }
+
+    #[test]
+    fn test_histogram_rendering() {
+        let histogram = HistogramVec::new("duration_seconds", "Duration.", &["route"], &[0.1, 1.0]);
+        histogram.observe(&["/a"], 0.05);
+        histogram.observe(&["/a"], 0.5);
+        histogram.observe(&["/a"], 2.0);
+
+        let mut out = String::new();
+        histogram.render(&mut out);
+        assert_eq!(
+            out,
+            "# HELP duration_seconds Duration.\n\
+             # TYPE duration_seconds histogram\n\
+             duration_seconds_bucket{route=\"/a\",le=\"0.1\"} 1\n\
+             duration_seconds_bucket{route=\"/a\",le=\"1\"} 2\n\
+             duration_seconds_bucket{route=\"/a\",le=\"+Inf\"} 3\n\
+             duration_seconds_sum{route=\"/a\"} 2.55\n\
+             duration_seconds_count{route=\"/a\"} 3\n"
+        );
+    }
+
+    #[test]
+    fn test_single_value_rendering() {
+        let mut out = String::new();
+        render_single(&mut out, "workers", "Worker threads.", "gauge", 5.0);
+        assert_eq!(out, "# HELP workers Worker threads.\n# TYPE workers gauge\nworkers 5\n");
+    }
+}
@@ -0,0 +1,43 @@
+//! Minimal SIGHUP handling without external crates: the signal handler only
+//! increments a counter, interested threads poll it (e.g. to reload their
+//! configuration).
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Once;
+
+static SIGHUP_COUNT: AtomicU64 = AtomicU64::new(0);
+static INSTALL: Once = Once::new();
+
+#[cfg(unix)]
+mod ffi {
+    use std::os::raw::c_int;
+
+    pub const SIGHUP: c_int = 1;
+
+    extern "C" {
+        pub fn signal(signum: c_int, handler: extern "C" fn(c_int)) -> usize;
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn on_sighup(_: std::os::raw::c_int) {
+    // only async-signal-safe work here:
+    SIGHUP_COUNT.fetch_add(1, Ordering::SeqCst);
+}
+
+/// Installs the SIGHUP handler (once per process). Without it, SIGHUP
+/// terminates the process. Does nothing on non-unix platforms.
+pub fn watch_sighup() {
+    INSTALL.call_once(|| {
+        #[cfg(unix)]
+        unsafe {
+            ffi::signal(ffi::SIGHUP, on_sighup);
+        }
+    });
+}
+
+/// Number of SIGHUP signals received so far. A watcher remembers the last seen
+/// value and reloads when it changed.
+pub fn sighup_count() -> u64 {
+    SIGHUP_COUNT.load(Ordering::SeqCst)
+}
@@ -0,0 +1,126 @@
+//! Minimal systemd integration without external crates: socket activation
+//! (the `sd_listen_fds` protocol) and readiness notification (`sd_notify`).
+//! Both do nothing when the process is not started by systemd.
+//!
+//! To try it out locally, without a unit file:
+//!
+//! ```text
+//! systemd-socket-activate -l 127.0.0.1:3000 -l /tmp/http-server.sock ./http-server --systemd
+//! ```
+
+use std::io;
+
+use crate::utils::net::Listener;
+
+/// The first file descriptor passed by systemd, after stdin, stdout and stderr.
+#[cfg(unix)]
+const LISTEN_FDS_START: i32 = 3;
+
+#[cfg(unix)]
+mod ffi {
+    use std::os::raw::c_int;
+
+    pub const F_GETFD: c_int = 1;
+    pub const F_SETFD: c_int = 2;
+    pub const FD_CLOEXEC: c_int = 1;
+
+    extern "C" {
+        pub fn fcntl(fd: c_int, cmd: c_int, ...) -> c_int;
+    }
+}
+
+/// Takes the listening sockets passed by systemd (or another supervisor),
+/// in the order of the socket unit. Returns none if `LISTEN_PID` is not the
+/// current process, e.g. when the server is started by hand. The variables are
+/// removed from the environment, so that child processes do not take them
+/// as well.
+///
+/// Call it before any other threads are started, first thing in `main`:
+/// `std::env::remove_var` is only sound as long as no other thread reads or
+/// writes the environment at the same time.
+#[cfg(unix)]
+pub fn listen_fds() -> io::Result<Vec<Listener>> {
+    let count = listen_fd_count(
+        std::env::var("LISTEN_PID").ok().as_deref(),
+        std::env::var("LISTEN_FDS").ok().as_deref(),
+        std::process::id(),
+    );
+    std::env::remove_var("LISTEN_PID");
+    std::env::remove_var("LISTEN_FDS");
+    std::env::remove_var("LISTEN_FDNAMES");
+
+    let mut listeners = Vec::new();
+    for fd in LISTEN_FDS_START..LISTEN_FDS_START + count as i32 {
+        // inherited without close-on-exec, which std sets on its own sockets:
+        unsafe {
+            let flags = ffi::fcntl(fd, ffi::F_GETFD);
+            if flags < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            ffi::fcntl(fd, ffi::F_SETFD, flags | ffi::FD_CLOEXEC);
+            listeners.push(Listener::from_raw_fd(fd)?);
+        }
+    }
+    Ok(listeners)
+}
+
+#[cfg(not(unix))]
+pub fn listen_fds() -> io::Result<Vec<Listener>> {
+    Ok(Vec::new())
+}
+
+/// Number of passed file descriptors, from the values of `LISTEN_PID` and `LISTEN_FDS`.
+pub fn listen_fd_count(listen_pid: Option<&str>, listen_fds: Option<&str>, pid: u32) -> usize {
+    match listen_pid.and_then(|p| p.trim().parse::<u32>().ok()) {
+        Some(listen_pid) if listen_pid == pid => (),
+        _ => return 0,
+    }
+    listen_fds
+        .and_then(|n| n.trim().parse::<usize>().ok())
+        .unwrap_or(0)
+}
+
+/// Sends a state change (e.g. `READY=1` or `STOPPING=1`) to the service
+/// manager, if `NOTIFY_SOCKET` is set. Returns whether it was sent.
+pub fn notify(state: &str) -> io::Result<bool> {
+    match std::env::var("NOTIFY_SOCKET") {
+        Ok(socket) if !socket.is_empty() => notify_socket(&socket, state).map(|_| true),
+        _ => Ok(false),
+    }
+}
+
+/// Sends the state to a notification socket: a path, or a name in the
+/// abstract namespace starting with `@` (Linux only).
+#[cfg(unix)]
+pub fn notify_socket(socket: &str, state: &str) -> io::Result<()> {
+    use std::os::unix::net::UnixDatagram;
+
+    let sender = UnixDatagram::unbound()?;
+    match socket.strip_prefix('@') {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            sender.send_to_addr(state.as_bytes(), &addr)?;
+        }
+        #[cfg(not(target_os = "linux"))]
+        Some(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "abstract socket addresses are only supported on Linux",
+            ))
+        }
+        None => {
+            sender.send_to(state.as_bytes(), socket)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn notify_socket(_socket: &str, _state: &str) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "systemd notification is only supported on unix platforms",
+    ))
+}
@@ -0,0 +1,73 @@
+#[cfg(all(test, unix))]
+mod systemd_test {
+    use super::super::net::{ListenAddr, Listener};
+    use super::super::systemd::{listen_fd_count, notify_socket};
+    use std::net::TcpListener;
+    use std::os::unix::io::IntoRawFd;
+    use std::os::unix::net::{UnixDatagram, UnixListener, UnixStream};
+
+    #[test]
+    fn test_listen_fd_count() {
+        assert_eq!(listen_fd_count(Some("42"), Some("2"), 42), 2);
+        // passed to another process, e.g. the parent:
+        assert_eq!(listen_fd_count(Some("41"), Some("2"), 42), 0);
+        assert_eq!(listen_fd_count(None, Some("2"), 42), 0);
+        assert_eq!(listen_fd_count(Some("42"), None, 42), 0);
+        assert_eq!(listen_fd_count(Some("42"), Some("x"), 42), 0);
+        assert_eq!(listen_fd_count(Some("pid"), Some("1"), 42), 0);
+    }
+
+    #[test]
+    fn test_inherited_listeners() {
+        let tcp = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = tcp.local_addr().unwrap();
+        let listener = unsafe { Listener::from_raw_fd(tcp.into_raw_fd()) }.unwrap();
+        assert_eq!(listener.local_addr().unwrap(), ListenAddr::Tcp(addr));
+        std::net::TcpStream::connect(addr).unwrap();
+        assert!(listener.accept().unwrap().peer_addr().is_some());
+
+        let path = std::env::temp_dir().join(format!("http-server-fd-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let unix = UnixListener::bind(&path).unwrap();
+        let listener = unsafe { Listener::from_raw_fd(unix.into_raw_fd()) }.unwrap();
+        assert_eq!(
+            listener.local_addr().unwrap(),
+            ListenAddr::Unix(path.clone())
+        );
+        UnixStream::connect(&path).unwrap();
+        assert!(listener.accept().is_ok());
+        // the socket file belongs to systemd:
+        listener.cleanup();
+        assert!(path.exists());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_notify() {
+        let path =
+            std::env::temp_dir().join(format!("http-server-notify-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let receiver = UnixDatagram::bind(&path).unwrap();
+        notify_socket(path.to_str().unwrap(), "READY=1").unwrap();
+        let mut buf = [0; 64];
+        let len = receiver.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"READY=1");
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(notify_socket("/nonexistent/notify.sock", "READY=1").is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_notify_abstract_socket() {
+        use std::os::linux::net::SocketAddrExt;
+
+        let name = format!("http-server-notify-{}", std::process::id());
+        let addr = std::os::unix::net::SocketAddr::from_abstract_name(&name).unwrap();
+        let receiver = UnixDatagram::bind_addr(&addr).unwrap();
+        notify_socket(&format!("@{}", name), "STOPPING=1").unwrap();
+        let mut buf = [0; 64];
+        let len = receiver.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"STOPPING=1");
+    }
+}
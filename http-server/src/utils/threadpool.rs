@@ -1,5 +1,7 @@
 use std::{
+    panic::{self, AssertUnwindSafe},
     sync::{
+        atomic::{AtomicUsize, Ordering},
         mpsc,
         Arc, Mutex,
     },
@@ -13,31 +15,48 @@ use std::{
 // thread_pool.execute(|id| { print!("Hi, I'm Thread {id}."});
 type Job = Box<dyn FnOnce(usize) + Send + Sync + 'static>;
 
+enum Message {
+    Job(Job),
+    /// the pool shrinks: the worker receiving this stops
+    Retire,
+}
+
 struct Worker {
     id: usize,
     thread: Option<JoinHandle<()>>,
 }
 
 impl Worker {
-    fn new(id: usize, receiver: Arc<Mutex<mpsc::Receiver<Job>>>) -> Worker {
+    fn new(id: usize, receiver: Arc<Mutex<mpsc::Receiver<Message>>>, status: ThreadPoolStatus) -> Worker {
         let mut w = Worker { id, thread: None };
-        w.start(receiver);
+        w.start(receiver, status);
 
-        return w;
+        w
     }
 
-    fn start(&mut self, receiver: Arc<Mutex<mpsc::Receiver<Job>>>) {
+    fn start(&mut self, receiver: Arc<Mutex<mpsc::Receiver<Message>>>, status: ThreadPoolStatus) {
         let id = self.id;
         self.thread = Some(thread::spawn(move || loop {
             // lock the mutex before receiving a msg, to avoid mutual access:
-            let guard = receiver.lock().unwrap();
+            // (a poisoned lock still guards a working receiver)
+            let guard = receiver.lock().unwrap_or_else(|e| e.into_inner());
             let msg = guard.recv(); // implements deref, so access to the inner channel receiver can be done here
                                     // drop the mutex guard early, otherwise it would block
                                     // the lock the whole time
             drop(guard);
             match msg {
-                Ok(job) => {
-                    job(id);
+                Ok(Message::Job(job)) => {
+                    status.queued_jobs.fetch_sub(1, Ordering::SeqCst);
+                    status.busy_workers.fetch_add(1, Ordering::SeqCst);
+                    // a panicking job must not take the worker down with it:
+                    if panic::catch_unwind(AssertUnwindSafe(|| job(id))).is_err() {
+                        println!("Thread {}: Job panicked", id);
+                    }
+                    status.busy_workers.fetch_sub(1, Ordering::SeqCst);
+                }
+                Ok(Message::Retire) => {
+                    println!("Thread {}: Retired", id);
+                    break;
                 }
                 Err(_) => {
                     println!("Thread {}: Disconnected, shutting down...", id);
@@ -47,6 +66,10 @@ impl Worker {
         }));
     }
 
+    fn is_finished(&self) -> bool {
+        self.thread.as_ref().is_some_and(JoinHandle::is_finished)
+    }
+
     fn join(&mut self) {
         // Why that complicated? thread.join() does take
         // ownership of thread: but it cannot be moved out of
@@ -55,15 +78,45 @@ impl Worker {
         // take() takes the value out of the Option and returns
         // ownership:
         if let Some(t) = self.thread.take() {
-            t.join().unwrap();
+            if t.join().is_err() {
+                println!("Thread {}: Terminated by a panic", self.id);
+            }
         }
     }
 }
 
+/// A read-only view on the pool's load, which can be shared with other
+/// threads (e.g. to report it as metrics).
+#[derive(Clone)]
+pub struct ThreadPoolStatus {
+    nr_of_threads: Arc<AtomicUsize>,
+    queued_jobs: Arc<AtomicUsize>,
+    busy_workers: Arc<AtomicUsize>,
+}
+
+impl ThreadPoolStatus {
+    pub fn nr_of_threads(&self) -> usize {
+        self.nr_of_threads.load(Ordering::SeqCst)
+    }
+
+    /// Number of jobs waiting for a free worker thread.
+    pub fn queued_jobs(&self) -> usize {
+        self.queued_jobs.load(Ordering::SeqCst)
+    }
+
+    /// Number of workers currently executing a job.
+    pub fn busy_workers(&self) -> usize {
+        self.busy_workers.load(Ordering::SeqCst)
+    }
+}
+
 pub struct ThreadPool {
     _nr_of_threads: usize,
-    sender: Option<mpsc::Sender<Job>>,
-    workers: Vec<Option<Worker>>,
+    sender: Option<mpsc::Sender<Message>>,
+    receiver: Arc<Mutex<mpsc::Receiver<Message>>>,
+    // locked, to be resized while jobs are executed:
+    workers: Mutex<Vec<Option<Worker>>>,
+    status: ThreadPoolStatus,
 }
 
 impl ThreadPool {
@@ -72,11 +125,16 @@ impl ThreadPool {
         // is shared in the workers.
         let (sender, receiver) = mpsc::channel();
         let receiver = Arc::new(Mutex::new(receiver));
+        let status = ThreadPoolStatus {
+            nr_of_threads: Arc::new(AtomicUsize::new(nr_of_threads)),
+            queued_jobs: Arc::new(AtomicUsize::new(0)),
+            busy_workers: Arc::new(AtomicUsize::new(0)),
+        };
 
         let mut workers = Vec::with_capacity(nr_of_threads);
 
         for id in 1..=nr_of_threads {
-            let w = Worker::new(id, receiver.clone());
+            let w = Worker::new(id, receiver.clone(), status.clone());
             workers.push(Some(w));
         }
 
@@ -86,25 +144,79 @@ impl ThreadPool {
                 _ => nr_of_threads,
             },
             sender: Some(sender),
-            workers,
+            receiver,
+            workers: Mutex::new(workers),
+            status,
         }
     }
 
+    /// Number of jobs waiting for a free worker thread.
+    pub fn queued_jobs(&self) -> usize {
+        self.status.queued_jobs()
+    }
+
+    pub fn status(&self) -> ThreadPoolStatus {
+        self.status.clone()
+    }
+
     pub fn execute<T>(&self, f: T)
     where
         T: FnOnce(usize) + Send + Sync + 'static,
     {
-        // send new job by using the channel:
-        self.sender.as_ref().unwrap().send(Box::new(f)).unwrap();
+        // send new job by using the channel; after a shutdown, the job is dropped:
+        let sender = match self.sender.as_ref() {
+            Some(sender) => sender,
+            None => return,
+        };
+        self.status.queued_jobs.fetch_add(1, Ordering::SeqCst);
+        if sender.send(Message::Job(Box::new(f))).is_err() {
+            self.status.queued_jobs.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Changes the number of worker threads (at least 1), e.g. on a configuration
+    /// reload. Surplus workers stop after their current job, once the jobs queued
+    /// before are started.
+    pub fn resize(&self, nr_of_threads: usize) {
+        let nr_of_threads = nr_of_threads.max(1);
+        let sender = match self.sender.as_ref() {
+            Some(sender) => sender,
+            None => return,
+        };
+        let mut workers = self.workers.lock().unwrap_or_else(|e| e.into_inner());
+        // forget the retired workers:
+        workers.retain_mut(|w| match w {
+            Some(worker) if worker.is_finished() => {
+                worker.join();
+                false
+            }
+            _ => true,
+        });
+
+        let current = self.status.nr_of_threads();
+        if nr_of_threads > current {
+            let next_id = workers.iter().flatten().map(|w| w.id).max().unwrap_or(0) + 1;
+            for id in next_id..next_id + nr_of_threads - current {
+                let w = Worker::new(id, self.receiver.clone(), self.status.clone());
+                workers.push(Some(w));
+            }
+        }
+        for _ in nr_of_threads..current {
+            let _ = sender.send(Message::Retire);
+        }
+        self.status
+            .nr_of_threads
+            .store(nr_of_threads, Ordering::SeqCst);
     }
 
     pub fn shutdown(&mut self) {
         // signalling the workers to shhut down by
         // closing the channel's sender:
         drop(self.sender.take());
-        // then wait for all workers to be done:
-        for w in &mut self.workers {
-            w.take().unwrap().join();
+        // then wait for all workers to be done (a second shutdown has none left):
+        let workers = self.workers.get_mut().unwrap_or_else(|e| e.into_inner());
+        for mut w in workers.iter_mut().filter_map(Option::take) {
+            w.join();
         }
     }
 }
@@ -0,0 +1,59 @@
+#[cfg(test)]
+mod threadpool_test {
+    use super::super::threadpool::ThreadPool;
+    use std::sync::{mpsc, Arc, Barrier};
+    use std::time::Duration;
+
+    /// A panicking job took its worker down, and the shutdown panicked when
+    /// joining it; a second shutdown, or a job after it, panicked as well.
+    #[test]
+    fn test_regression_panicking_job() {
+        let mut pool = ThreadPool::builder(1);
+        pool.execute(|_| panic!("job failed"));
+        // the single worker is still there:
+        let (sender, receiver) = mpsc::channel();
+        pool.execute(move |id| sender.send(id).unwrap());
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(5)), Ok(1));
+
+        pool.shutdown();
+        pool.shutdown();
+        pool.execute(|_| ());
+        assert_eq!(pool.queued_jobs(), 0);
+    }
+
+    #[test]
+    fn test_resize() {
+        let mut pool = ThreadPool::builder(1);
+        pool.resize(3);
+        assert_eq!(pool.status().nr_of_threads(), 3);
+        // three jobs waiting for each other need three workers:
+        let barrier = Arc::new(Barrier::new(3));
+        let (sender, receiver) = mpsc::channel();
+        for _ in 0..3 {
+            let (barrier, sender) = (Arc::clone(&barrier), sender.clone());
+            pool.execute(move |id| {
+                barrier.wait();
+                sender.send(id).unwrap();
+            });
+        }
+        let mut ids: Vec<usize> = (0..3)
+            .map(|_| receiver.recv_timeout(Duration::from_secs(5)).unwrap())
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec![1, 2, 3]);
+
+        pool.resize(0);
+        assert_eq!(pool.status().nr_of_threads(), 1);
+        // the remaining worker still runs the jobs:
+        for _ in 0..3 {
+            let sender = sender.clone();
+            pool.execute(move |id| sender.send(id).unwrap());
+        }
+        for _ in 0..3 {
+            assert!(receiver.recv_timeout(Duration::from_secs(5)).is_ok());
+        }
+        pool.resize(2);
+        assert_eq!(pool.status().nr_of_threads(), 2);
+        pool.shutdown();
+    }
+}
@@ -0,0 +1,66 @@
+//! Starts the server binary the way systemd does for a socket unit: with the
+//! listening socket on fd 3, and `LISTEN_PID` / `LISTEN_FDS` set.
+#![cfg(unix)]
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::os::raw::c_int;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+extern "C" {
+    fn dup(fd: c_int) -> c_int;
+    fn dup2(old_fd: c_int, new_fd: c_int) -> c_int;
+}
+
+/// Kills the server at the end of the test, also if it fails.
+struct Server(Child);
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+#[test]
+fn test_serves_on_passed_socket() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let fd = listener.as_raw_fd();
+
+    // LISTEN_PID is the pid of the shell, which the server keeps with exec:
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg("LISTEN_PID=$$ exec \"$0\" --systemd")
+        .arg(env!("CARGO_BIN_EXE_http-server"))
+        .env("LISTEN_FDS", "1")
+        .env_remove("NOTIFY_SOCKET")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    // the listener becomes fd 3, without close-on-exec (through a copy, as
+    // it may be fd 3 already):
+    unsafe {
+        command.pre_exec(move || match dup2(dup(fd), 3) {
+            -1 => Err(std::io::Error::last_os_error()),
+            _ => Ok(()),
+        });
+    }
+    let _server = Server(command.spawn().unwrap());
+    drop(listener);
+
+    let mut client = TcpStream::connect(addr).unwrap();
+    client
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    client
+        .write_all(b"GET / HTTP/1.1\r\nHost: a\r\nConnection: close\r\n\r\n")
+        .unwrap();
+    let mut response = String::new();
+    client.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert!(response.ends_with("GET request read.\n"), "{}", response);
+}